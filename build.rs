@@ -0,0 +1,135 @@
+// Packs the `initrd` directory into a ustar archive that the kernel embeds and unpacks into its
+// root filesystem at boot, see `src/initrd.rs`
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BLOCK_SIZE: usize = 512;
+const SOURCE: &str = "initrd";
+
+fn main() {
+    // Cargo scans the whole directory for changes
+    println!("cargo:rerun-if-changed={}", SOURCE);
+
+    let mut archive = Vec::new();
+    append_directory(&mut archive, Path::new(SOURCE), "").expect("failed to pack the initrd");
+    // Two zero blocks end the archive
+    archive.resize(archive.len() + 2 * BLOCK_SIZE, 0);
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("initrd.tar");
+    fs::write(out, archive).expect("failed to write the initrd");
+}
+
+// Adds the entries of `directory` in name order, `prefix` is its path inside the archive
+fn append_directory(archive: &mut Vec<u8>, directory: &Path, prefix: &str) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .into_string()
+            .expect("non UTF-8 name in the initrd");
+        let path = format!("{}{}", prefix, name);
+        let metadata = fs::symlink_metadata(entry.path())?;
+        let mode = mode(&metadata);
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+
+        if metadata.file_type().is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            let target = target.to_str().expect("non UTF-8 link in the initrd");
+            append_header(archive, &path, b'2', mode, 0, modified, target);
+        } else if metadata.is_dir() {
+            let path = format!("{}/", path);
+            append_header(archive, &path, b'5', mode, 0, modified, "");
+            append_directory(archive, &entry.path(), &path)?;
+        } else {
+            let content = fs::read(entry.path())?;
+            append_header(archive, &path, b'0', mode, content.len(), modified, "");
+            archive.extend_from_slice(&content);
+            archive.resize(archive.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+        }
+    }
+
+    Ok(())
+}
+
+fn append_header(
+    archive: &mut Vec<u8>,
+    path: &str,
+    kind: u8,
+    mode: u32,
+    size: usize,
+    modified: u64,
+    link: &str,
+) {
+    let mut header = [0u8; BLOCK_SIZE];
+
+    // Names that do not fit are split at a slash into the prefix field
+    let (prefix, name) = match path.len() {
+        0..=100 => ("", path),
+        _ => {
+            let split = path[..path.len() - 1]
+                .rfind('/')
+                .filter(|&split| split <= 155 && path.len() - split - 1 <= 100)
+                .unwrap_or_else(|| panic!("'{}' is too long for a ustar archive", path));
+            (&path[..split], &path[split + 1..])
+        }
+    };
+    assert!(link.len() <= 100, "link target '{}' is too long", link);
+
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    put_octal(&mut header[100..108], u64::from(mode));
+    // Everything belongs to root, the owners on the build machine mean nothing to the kernel
+    put_octal(&mut header[108..116], 0);
+    put_octal(&mut header[116..124], 0);
+    put_octal(&mut header[124..136], size as u64);
+    put_octal(&mut header[136..148], modified);
+    header[156] = kind;
+    header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[265..269].copy_from_slice(b"root");
+    header[297..301].copy_from_slice(b"root");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|&byte| u64::from(byte)).sum();
+    put_octal(&mut header[148..155], checksum);
+
+    archive.extend_from_slice(&header);
+}
+
+// Writes `value` as zero-padded octal digits followed by a NUL
+fn put_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    assert!(
+        digits.len() < field.len(),
+        "{} does not fit in a tar header",
+        value
+    );
+
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+// Without Unix permissions only the read-only flag is known
+#[cfg(not(unix))]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    match (metadata.is_dir(), metadata.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
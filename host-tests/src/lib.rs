@@ -0,0 +1,32 @@
+// Kernel modules that only depend on `core`, `alloc` and crates that build for any target,
+// compiled for the host so that their unit tests can run with a plain `cargo test` from this
+// directory. The filesystem leaves out `devfs` and `procfs` there, they need the hardware.
+#![no_std]
+
+extern crate alloc;
+
+#[path = "../../src/command/lexer.rs"]
+pub mod lexer;
+
+#[path = "../../src/command/glob.rs"]
+pub mod glob;
+
+#[path = "../../src/users/passwd.rs"]
+pub mod passwd;
+
+#[path = "../../src/clock.rs"]
+pub mod clock;
+
+#[path = "../../src/devices"]
+pub mod devices {
+    pub mod block;
+}
+
+// Submodules of a file included with `path` are looked for next to it, inside of an inline
+// module they are looked for in `file_system/` like in the kernel
+#[path = "../../src"]
+mod kernel {
+    pub mod file_system;
+}
+
+pub use kernel::file_system;
@@ -0,0 +1,51 @@
+use charizard_host_tests::file_system::FileSystem;
+
+#[test]
+fn writes_past_the_end_leave_zero_filled_holes() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"ab").unwrap();
+
+    assert_eq!(fs.write_at("/data", 5, b"xyz"), Ok(3));
+    assert_eq!(fs.read_file("/data").unwrap(), b"ab\0\0\0xyz");
+    assert_eq!(fs.size("/data"), Ok(8));
+
+    // Overwriting in the middle neither grows nor shrinks the file
+    fs.write_at("/data", 1, b"B\x01").unwrap();
+    assert_eq!(fs.read_file("/data").unwrap(), b"aB\x01\0\0xyz");
+}
+
+#[test]
+fn reads_stop_at_the_end_of_file() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"hello").unwrap();
+
+    assert_eq!(fs.read_at("/data", 1, 3).unwrap(), b"ell");
+    assert_eq!(fs.read_at("/data", 3, 100).unwrap(), b"lo");
+    assert_eq!(fs.read_at("/data", 9, 4).unwrap(), b"");
+}
+
+#[test]
+fn append_and_truncate_change_the_size() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/log", b"one").unwrap();
+
+    assert_eq!(fs.append("/log", b"\xfftwo"), Ok(4));
+    assert_eq!(fs.read_file("/log").unwrap(), b"one\xfftwo");
+
+    fs.truncate("/log", 2).unwrap();
+    assert_eq!(fs.read_file("/log").unwrap(), b"on");
+    // Growing zero-fills like a write past the end
+    fs.truncate("/log", 4).unwrap();
+    assert_eq!(fs.read_file("/log").unwrap(), b"on\0\0");
+}
+
+#[test]
+fn directories_have_no_bytes() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+
+    assert!(fs.read_file("/docs").is_err());
+    assert!(fs.write_at("/docs", 0, b"x").is_err());
+    assert!(fs.truncate("/docs", 0).is_err());
+    assert!(fs.append("/missing", b"x").is_err());
+}
@@ -0,0 +1,111 @@
+use charizard_host_tests::file_system::{
+    descriptor::{OpenMode, SeekFrom},
+    ramfs::RamFs,
+    FileSystem,
+};
+
+#[test]
+fn every_handle_has_its_own_cursor() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"abcdef").unwrap();
+    let first = fs.open("/data", OpenMode::Read).unwrap();
+    let second = fs.open("/data", OpenMode::Read).unwrap();
+
+    let mut buffer = [0; 4];
+    assert_eq!(fs.read(first, &mut buffer[..2]), Ok(2));
+    assert_eq!(&buffer[..2], b"ab");
+    assert_eq!(fs.read(second, &mut buffer), Ok(4));
+    assert_eq!(&buffer, b"abcd");
+    assert_eq!(fs.read(first, &mut buffer), Ok(4));
+    assert_eq!(&buffer, b"cdef");
+    assert_eq!(fs.read(first, &mut buffer), Ok(0));
+
+    // Writes move their own cursor, appends always go to the end
+    let writer = fs.open("/data", OpenMode::Write).unwrap();
+    let appender = fs.open("/data", OpenMode::Append).unwrap();
+    fs.write(writer, b"12").unwrap();
+    fs.write(appender, b"xy").unwrap();
+    fs.write(writer, b"3").unwrap();
+    assert_eq!(fs.read_file("/data").unwrap(), b"123y");
+
+    for handle in [first, second, writer, appender] {
+        fs.close(handle).unwrap();
+    }
+}
+
+#[test]
+fn seeking_past_the_end_is_allowed() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"abc").unwrap();
+
+    let handle = fs.open("/data", OpenMode::Read).unwrap();
+    assert_eq!(fs.seek(handle, SeekFrom::End(2)), Ok(5));
+    let mut buffer = [0; 4];
+    assert_eq!(fs.read(handle, &mut buffer), Ok(0));
+    assert_eq!(fs.seek(handle, SeekFrom::Current(-4)), Ok(1));
+    assert_eq!(fs.read(handle, &mut buffer), Ok(2));
+    assert_eq!(&buffer[..2], b"bc");
+    assert!(fs.seek(handle, SeekFrom::Current(-10)).is_err());
+    fs.close(handle).unwrap();
+
+    // Writing there leaves a hole
+    let handle = fs.open("/data", OpenMode::Write).unwrap();
+    fs.seek(handle, SeekFrom::Start(2)).unwrap();
+    fs.write(handle, b"z").unwrap();
+    fs.close(handle).unwrap();
+    assert_eq!(fs.read_file("/data").unwrap(), b"\0\0z");
+}
+
+#[test]
+fn closed_handles_are_reused() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/a", b"a").unwrap();
+    fs.create_file("/b", b"b").unwrap();
+
+    let first = fs.open("/a", OpenMode::Read).unwrap();
+    let second = fs.open("/b", OpenMode::Read).unwrap();
+    fs.close(first).unwrap();
+    assert!(fs.close(first).is_err());
+    assert!(fs.read(first, &mut [0; 1]).is_err());
+
+    // The lowest free slot comes back, now naming the other file
+    let third = fs.open("/b", OpenMode::Read).unwrap();
+    assert_eq!(third, first);
+    let mut buffer = [0; 1];
+    fs.read(third, &mut buffer).unwrap();
+    assert_eq!(&buffer, b"b");
+
+    fs.close(second).unwrap();
+    fs.close(third).unwrap();
+}
+
+#[test]
+fn handles_check_their_mode() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"abc").unwrap();
+    fs.create_directory("/docs").unwrap();
+
+    let reader = fs.open("/data", OpenMode::Read).unwrap();
+    assert!(fs.write(reader, b"x").is_err());
+    let writer = fs.open("/new", OpenMode::Write).unwrap();
+    assert!(fs.read(writer, &mut [0; 1]).is_err());
+
+    assert!(fs.open("/missing", OpenMode::Read).is_err());
+    assert!(fs.open("/docs", OpenMode::Read).is_err());
+}
+
+#[test]
+fn open_files_keep_their_filesystem_mounted() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+    fs.mount("/mnt", Box::new(RamFs::new())).unwrap();
+    fs.create_file("/mnt/data", b"abc").unwrap();
+
+    let handle = fs.open("/mnt/data", OpenMode::Read).unwrap();
+    assert!(fs.unmount("/mnt").is_err());
+    assert_eq!(fs.read_file("/mnt/data").unwrap(), b"abc");
+
+    fs.close(handle).unwrap();
+    fs.unmount("/mnt").unwrap();
+    assert!(fs.lookup("/mnt/data").is_err());
+}
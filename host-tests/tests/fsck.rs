@@ -0,0 +1,194 @@
+use charizard_host_tests::file_system::{
+    access::Credentials,
+    vfs::{DirEntry, Metadata, NodeKind, Vfs},
+    Conflict, FileSystem,
+};
+
+// A read-only backend serving a fixed list of nodes and directory entries, which need not agree
+// with each other, so that it can be broken in ways the real backends never are
+struct Fixed {
+    // Path, kind, inode number and link count of every node but the root
+    nodes: Vec<(&'static str, NodeKind, u64, usize)>,
+    // Directory, name and kind of every directory entry
+    entries: Vec<(&'static str, &'static str, NodeKind)>,
+}
+
+impl Fixed {
+    fn node(&self, path: &str) -> Result<(NodeKind, u64, usize), String> {
+        if path == "/" {
+            return Ok((NodeKind::Directory, 1, 1));
+        }
+
+        self.nodes
+            .iter()
+            .find(|node| node.0 == path)
+            .map(|node| (node.1, node.2, node.3))
+            .ok_or_else(|| format!("'{}' not found", path))
+    }
+}
+
+impl Vfs for Fixed {
+    fn name(&self) -> &str {
+        "fixed"
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        self.node(path).map(|node| node.0)
+    }
+
+    fn create(&mut self, _: &str, _: NodeKind) -> Result<(), String> {
+        Err(String::from("Read-only"))
+    }
+
+    fn read(&self, _: &str, _: usize, _: &mut [u8]) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn write(&mut self, _: &str, _: usize, _: &[u8]) -> Result<usize, String> {
+        Err(String::from("Read-only"))
+    }
+
+    fn truncate(&mut self, _: &str, _: usize) -> Result<(), String> {
+        Err(String::from("Read-only"))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| entry.0 == path)
+            .map(|entry| DirEntry {
+                name: String::from(entry.1),
+                kind: entry.2,
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, _: &str) -> Result<(), String> {
+        Err(String::from("Read-only"))
+    }
+
+    fn rename(&mut self, _: &str, _: &str) -> Result<(), String> {
+        Err(String::from("Read-only"))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, String> {
+        Err(format!("'{}' has no target", path))
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let (kind, inode, links) = self.node(path)?;
+
+        Ok(Metadata {
+            kind,
+            size: 0,
+            inode,
+            links,
+            uid: 0,
+            gid: 0,
+            mode: 0o755,
+            created: 0,
+            modified: 0,
+            accessed: 0,
+        })
+    }
+}
+
+#[test]
+fn a_file_and_a_directory_cannot_share_a_name() {
+    let mut fs = FileSystem::new();
+
+    fs.create_file("/name", b"").unwrap();
+    assert!(fs.create_directory("/name").is_err());
+    fs.create_directory("/other").unwrap();
+    assert!(fs.create_file("/other", b"").is_err());
+    assert!(fs.symlink("/name", "/other").is_err());
+    assert!(fs.link("/name", "/other").is_err());
+
+    assert_eq!(fs.list_directory("/").unwrap(), ["name", "/other"]);
+    assert_eq!(fs.fsck("/").unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn consistent_trees_have_no_problems() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_directory("/docs/drafts").unwrap();
+    fs.create_file("/docs/report.txt", b"report").unwrap();
+    fs.link("/docs/report.txt", "/docs/drafts/same.txt")
+        .unwrap();
+    fs.symlink("../report.txt", "/docs/drafts/link").unwrap();
+    fs.symlink("/nowhere", "/dangling").unwrap();
+    fs.copy_node("/docs", "/backup", Conflict::Fail).unwrap();
+    fs.move_node("/backup", "/docs/drafts/backup", Conflict::Fail)
+        .unwrap();
+    fs.delete_node("/docs/report.txt").unwrap();
+
+    assert_eq!(fs.fsck("/").unwrap(), Vec::<String>::new());
+    assert_eq!(fs.fsck("/docs/drafts").unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn broken_trees_are_reported() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+    let broken = Fixed {
+        nodes: Vec::from([
+            ("/dir", NodeKind::Directory, 2, 1),
+            // The same directory again, as a cycle would be seen
+            ("/dir/up", NodeKind::Directory, 2, 1),
+            ("/one", NodeKind::File, 3, 1),
+            ("/two", NodeKind::File, 3, 1),
+            ("/alone", NodeKind::File, 4, 2),
+            ("/link", NodeKind::Symlink, 5, 1),
+            ("/device", NodeKind::CharDevice, 6, 1),
+        ]),
+        entries: Vec::from([
+            ("/", "dir", NodeKind::Directory),
+            ("/", "one", NodeKind::File),
+            ("/", "one", NodeKind::File),
+            ("/", "two", NodeKind::File),
+            ("/", "alone", NodeKind::File),
+            ("/", "link", NodeKind::Symlink),
+            ("/", "device", NodeKind::File),
+            ("/", "ghost", NodeKind::File),
+            ("/dir", "up", NodeKind::Directory),
+            ("/dir", "..", NodeKind::Directory),
+            ("/dir", "", NodeKind::File),
+        ]),
+    };
+    fs.mount("/mnt", Box::new(broken)).unwrap();
+
+    let mut problems = fs.fsck("/mnt/dir").unwrap();
+    problems.sort();
+    assert_eq!(
+        problems,
+        [
+            "'/mnt/alone' has 2 links but 1 entries point at it",
+            "'/mnt/device' is listed as a file but is a character device",
+            "'/mnt/dir' has an entry with the invalid name ''",
+            "'/mnt/dir' has an entry with the invalid name '..'",
+            "'/mnt/dir/up' is the same directory as '/mnt/dir'",
+            "'/mnt/ghost' is listed but not found: '/ghost' not found",
+            "'/mnt/one' has 1 links but 2 entries point at it",
+            "'/mnt/one' is listed more than once",
+            "The target of '/mnt/link' cannot be read: '/link' has no target",
+        ]
+    );
+    // The root filesystem below the mount is fine
+    assert_eq!(fs.fsck("/").unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn only_root_checks_filesystems() {
+    let mut fs = FileSystem::new();
+    fs.set_credentials(Credentials {
+        uid: 1000,
+        gid: 1000,
+        groups: Vec::new(),
+    });
+
+    assert!(fs.fsck("/").is_err());
+}
@@ -0,0 +1,189 @@
+// Random sequences of filesystem operations, with every filesystem checked by `fsck` after
+// every step
+//
+// Paths are drawn from a handful of names, so operations keep running into existing nodes,
+// missing parents, links and each other's subtrees. Most of them fail, which is fine as long as
+// the tree stays consistent either way. A failure names the seed and the operations that led up
+// to it, so it can be replayed by running the same seed.
+
+use charizard_host_tests::file_system::{access::Credentials, ramfs::RamFs, Conflict, FileSystem};
+
+const SEEDS: u64 = 64;
+const STEPS: usize = 400;
+
+// Names paths are made of, `..` is resolved by the filesystem
+const NAMES: [&str; 5] = ["a", "b", "c", "mnt", ".."];
+
+// xorshift64, good enough to spread the operations and trivially reproducible
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn name(&mut self) -> &'static str {
+        NAMES[self.below(NAMES.len())]
+    }
+
+    // An absolute path of one to three components
+    fn path(&mut self) -> String {
+        let depth = 1 + self.below(3);
+        (0..depth).map(|_| format!("/{}", self.name())).collect()
+    }
+
+    fn conflict(&mut self) -> Conflict {
+        [Conflict::Fail, Conflict::Skip, Conflict::Overwrite][self.below(3)]
+    }
+
+    fn data(&mut self) -> Vec<u8> {
+        let len = self.below(32);
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+// Runs one random operation and returns what it was, for the failure message
+fn step(fs: &mut FileSystem, random: &mut Random) -> String {
+    let path = random.path();
+
+    let (operation, result) = match random.below(20) {
+        // Creating more than deleting lets the tree grow deep enough to be interesting
+        0..=3 => {
+            let data = random.data();
+            let result = fs.create_file(&path, &data);
+            (format!("create_file {}", path), result)
+        }
+        4..=7 => (
+            format!("create_directory {}", path),
+            fs.create_directory(&path),
+        ),
+        8 => {
+            // Relative targets are resolved against the link's directory
+            let target = if random.below(2) == 0 {
+                random.path()
+            } else {
+                String::from(random.name())
+            };
+            let result = fs.symlink(&target, &path);
+            (format!("symlink {} {}", target, path), result)
+        }
+        9 => {
+            let new = random.path();
+            (format!("link {} {}", path, new), fs.link(&path, &new))
+        }
+        10 | 11 => {
+            let offset = random.below(64);
+            let data = random.data();
+            let result = fs.write_at(&path, offset, &data).map(|_| ());
+            (format!("write_at {} {}", path, offset), result)
+        }
+        12 => {
+            let len = random.below(64);
+            (
+                format!("truncate {} {}", path, len),
+                fs.truncate(&path, len),
+            )
+        }
+        13 | 14 => {
+            let (to, conflict) = (random.path(), random.conflict());
+            let result = fs.move_node(&path, &to, conflict);
+            (format!("move_node {} {} {:?}", path, to, conflict), result)
+        }
+        15 => {
+            let (to, conflict) = (random.path(), random.conflict());
+            let result = fs.copy_node(&path, &to, conflict);
+            (format!("copy_node {} {} {:?}", path, to, conflict), result)
+        }
+        16 => {
+            let name = random.name();
+            let result = fs.rename_node(&path, name);
+            (format!("rename_node {} {}", path, name), result)
+        }
+        17 => (format!("delete_node {}", path), fs.delete_node(&path)),
+        18 => (format!("delete_tree {}", path), fs.delete_tree(&path)),
+        _ => {
+            // Reads must not change the tree either
+            let result = fs.read_file(&path).map(|_| ());
+            (format!("read_file {}", path), result)
+        }
+    };
+
+    match result {
+        Ok(()) => operation,
+        Err(error) => format!("{} (failed: {})", operation, error),
+    }
+}
+
+// Checks every mounted filesystem
+fn check(fs: &FileSystem) -> Vec<String> {
+    let mount_points: Vec<String> = fs.mounts().map(|mount| mount.path.clone()).collect();
+
+    mount_points
+        .iter()
+        .flat_map(|path| fs.fsck(path).unwrap())
+        .collect()
+}
+
+fn run(seed: u64, user: Option<Credentials>) {
+    let mut random = Random::new(seed);
+    let mut fs = FileSystem::new();
+    // A second filesystem, so that operations also cross mounts
+    fs.create_directory("/mnt").unwrap();
+    fs.mount("/mnt", Box::new(RamFs::new())).unwrap();
+
+    let mut history = Vec::new();
+    for _ in 0..STEPS {
+        match &user {
+            // Switched back and forth so that some nodes belong to root and fail the checks
+            Some(credentials) if random.below(2) == 0 => {
+                fs.set_credentials(credentials.clone());
+            }
+            _ => {
+                fs.set_credentials(Credentials::root());
+            }
+        }
+        history.push(step(&mut fs, &mut random));
+
+        fs.set_credentials(Credentials::root());
+        let problems = check(&fs);
+        assert!(
+            problems.is_empty(),
+            "seed {}: {:?} after\n{}",
+            seed,
+            problems,
+            history.join("\n")
+        );
+    }
+}
+
+#[test]
+fn random_operations_keep_the_tree_consistent() {
+    for seed in 0..SEEDS {
+        run(seed, None);
+    }
+}
+
+#[test]
+fn random_operations_of_a_user_keep_the_tree_consistent() {
+    let user = Credentials {
+        uid: 1000,
+        gid: 1000,
+        groups: Vec::new(),
+    };
+
+    for seed in 0..SEEDS {
+        run(seed, Some(user.clone()));
+    }
+}
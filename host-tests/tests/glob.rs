@@ -0,0 +1,71 @@
+use charizard_host_tests::glob::{is_pattern, matches, unescape};
+
+#[test]
+fn literal_names() {
+    assert!(matches("config", "config"));
+    assert!(!matches("config", "config2"));
+    assert!(!matches("config", "conf"));
+    assert!(matches("", ""));
+}
+
+#[test]
+fn star() {
+    assert!(matches("*.txt", "a.txt"));
+    assert!(matches("*.txt", ".txt"));
+    assert!(!matches("*.txt", "a.txt.bak"));
+    assert!(matches("a*b*c", "abc"));
+    assert!(matches("a*b*c", "axxbyybzc"));
+    assert!(!matches("a*b*c", "axxbyy"));
+    assert!(matches("*", ""));
+    assert!(matches("**", "anything"));
+}
+
+#[test]
+fn question_mark() {
+    assert!(matches("?.log", "a.log"));
+    assert!(!matches("?.log", "ab.log"));
+    assert!(!matches("?", ""));
+    assert!(matches("h?llo", "héllo"));
+}
+
+#[test]
+fn classes() {
+    assert!(matches("[a-c]x", "bx"));
+    assert!(!matches("[a-c]x", "dx"));
+    assert!(matches("[!a-c]x", "dx"));
+    assert!(matches("[^a-c]x", "dx"));
+    assert!(!matches("[!a-c]x", "ax"));
+    assert!(matches("[abz]", "z"));
+    assert!(matches("[]]", "]"));
+    assert!(matches("[a-]", "-"));
+    assert!(matches("[0-9][0-9]", "42"));
+}
+
+#[test]
+fn unclosed_bracket_is_literal() {
+    assert!(matches("[abc", "[abc"));
+    assert!(!matches("[abc", "a"));
+}
+
+#[test]
+fn escapes() {
+    assert!(matches(r"\*", "*"));
+    assert!(!matches(r"\*", "a"));
+    assert!(matches(r"a\?*", "a?bc"));
+    assert!(matches(r"[\]]", "]"));
+}
+
+#[test]
+fn detects_patterns() {
+    assert!(is_pattern("*.txt"));
+    assert!(is_pattern("[ab]"));
+    assert!(is_pattern("a?"));
+    assert!(!is_pattern("plain"));
+    assert!(!is_pattern(r"\*"));
+}
+
+#[test]
+fn unescapes() {
+    assert_eq!(unescape(r"a\*b\\c"), r"a*b\c");
+    assert_eq!(unescape("plain"), "plain");
+}
@@ -0,0 +1,153 @@
+use charizard_host_tests::lexer::{tokenize, Token};
+
+fn word(s: &str) -> Token {
+    Token::Word(String::from(s))
+}
+
+fn words(input: &str) -> Vec<String> {
+    tokenize(input)
+        .unwrap()
+        .into_iter()
+        .map(|token| match token {
+            Token::Word(word) => word,
+            operator => panic!("unexpected operator {:?}", operator),
+        })
+        .collect()
+}
+
+#[test]
+fn empty_input() {
+    assert_eq!(tokenize("").unwrap(), vec![]);
+    assert_eq!(tokenize("   \t ").unwrap(), vec![]);
+}
+
+#[test]
+fn splits_on_whitespace() {
+    assert_eq!(
+        words("  touch   notes.txt\thello  "),
+        ["touch", "notes.txt", "hello"]
+    );
+}
+
+#[test]
+fn double_quotes_group_words() {
+    assert_eq!(
+        words(r#"touch notes.txt "hello world""#),
+        ["touch", "notes.txt", "hello world"]
+    );
+}
+
+#[test]
+fn single_quotes_are_literal() {
+    assert_eq!(words(r#"'a \" b' 'c\d'"#), [r#"a \" b"#, r"c\d"]);
+}
+
+#[test]
+fn double_quote_escapes() {
+    assert_eq!(
+        words(r#""say \"hi\"" "back\\slash" "\n""#),
+        [r#"say "hi""#, r"back\slash", r"\n"]
+    );
+}
+
+#[test]
+fn backslash_outside_quotes() {
+    assert_eq!(words(r"hello\ world a\'b \\"), ["hello world", "a'b", r"\"]);
+}
+
+#[test]
+fn empty_arguments() {
+    assert_eq!(words(r#"touch a '' """#), ["touch", "a", "", ""]);
+    assert_eq!(words("''"), [""]);
+}
+
+#[test]
+fn quotes_join_adjacent_text() {
+    assert_eq!(words(r#"a"b c"d'e f'"#), ["ab cde f"]);
+    assert_eq!(words(r#"""a"#), ["a"]);
+}
+
+#[test]
+fn unicode_is_preserved() {
+    assert_eq!(words("cat 'héllo wörld'"), ["cat", "héllo wörld"]);
+}
+
+#[test]
+fn operators() {
+    assert_eq!(
+        tokenize("ls / | grep foo > out.txt").unwrap(),
+        vec![
+            word("ls"),
+            word("/"),
+            Token::Pipe,
+            word("grep"),
+            word("foo"),
+            Token::RedirectOut,
+            word("out.txt"),
+        ]
+    );
+    assert_eq!(
+        tokenize("a>>b<c|d").unwrap(),
+        vec![
+            word("a"),
+            Token::RedirectAppend,
+            word("b"),
+            Token::RedirectIn,
+            word("c"),
+            Token::Pipe,
+            word("d"),
+        ]
+    );
+    assert_eq!(
+        tokenize("> >").unwrap(),
+        vec![Token::RedirectOut, Token::RedirectOut]
+    );
+}
+
+#[test]
+fn quoted_operators_are_words() {
+    assert_eq!(
+        words(r#"echo '|' ">>" \< a\>b"#),
+        ["echo", "|", ">>", "<", "a>b"]
+    );
+}
+
+#[test]
+fn unterminated_quotes() {
+    assert!(tokenize("echo 'abc").is_err());
+    assert!(tokenize(r#"echo "abc"#).is_err());
+    assert!(tokenize(r#"echo "abc\"#).is_err());
+}
+
+#[test]
+fn trailing_backslash() {
+    assert!(tokenize(r"echo abc\").is_err());
+}
+
+#[test]
+fn token_display() {
+    assert_eq!(Token::RedirectAppend.to_string(), ">>");
+    assert_eq!(word("a b").to_string(), "a b");
+}
+
+#[test]
+fn unquoted_wildcards_make_globs() {
+    assert_eq!(
+        tokenize("rm logs/*.txt a? [ab]").unwrap(),
+        vec![
+            word("rm"),
+            Token::Glob(String::from("logs/*.txt")),
+            Token::Glob(String::from("a?")),
+            Token::Glob(String::from("[ab]")),
+        ]
+    );
+}
+
+#[test]
+fn quoted_wildcards_are_escaped_in_globs() {
+    assert_eq!(words(r#"'*.txt' \? "[a]""#), ["*.txt", "?", "[a]"]);
+    assert_eq!(
+        tokenize(r#""my *"*"#).unwrap(),
+        vec![Token::Glob(String::from(r"my \**"))]
+    );
+}
@@ -0,0 +1,74 @@
+use charizard_host_tests::file_system::{
+    ramfs::RamFs,
+    vfs::{NodeKind, Vfs},
+    FileSystem,
+};
+
+#[test]
+fn links_are_counted() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_file("/docs/a.txt", b"shared").unwrap();
+    assert_eq!(fs.stat("/docs/a.txt").unwrap().links, 1);
+
+    fs.link("/docs/a.txt", "/b.txt").unwrap();
+    fs.link("/b.txt", "/docs/c.txt").unwrap();
+    let inode = fs.stat("/docs/a.txt").unwrap().inode;
+    for path in ["/docs/a.txt", "/b.txt", "/docs/c.txt"] {
+        let metadata = fs.stat(path).unwrap();
+        assert_eq!((metadata.inode, metadata.links), (inode, 3), "{}", path);
+    }
+
+    fs.delete_node("/docs/a.txt").unwrap();
+    assert_eq!(fs.stat("/b.txt").unwrap().links, 2);
+    // Renames move the entry, not the count
+    fs.rename_node("/b.txt", "renamed.txt").unwrap();
+    assert_eq!(fs.stat("/renamed.txt").unwrap().links, 2);
+}
+
+#[test]
+fn links_share_their_content() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/a.txt", b"one").unwrap();
+    fs.link("/a.txt", "/b.txt").unwrap();
+
+    fs.append("/b.txt", b" two").unwrap();
+    assert_eq!(fs.read_file("/a.txt").unwrap(), b"one two");
+    fs.truncate("/a.txt", 1).unwrap();
+    assert_eq!(fs.read_file("/b.txt").unwrap(), b"o");
+
+    // The content outlives the name it was created under
+    fs.delete_node("/a.txt").unwrap();
+    assert_eq!(fs.read_file("/b.txt").unwrap(), b"o");
+}
+
+#[test]
+fn directories_cannot_be_linked() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_file("/a.txt", b"").unwrap();
+
+    assert!(fs.link("/docs", "/again").is_err());
+    assert!(fs.link("/missing", "/again").is_err());
+    assert!(fs.link("/a.txt", "/docs").is_err());
+    assert!(fs.lookup("/again").is_err());
+}
+
+#[test]
+fn the_inode_is_freed_with_its_last_link() {
+    let mut ramfs = RamFs::new();
+    ramfs.create("/a.txt", NodeKind::File).unwrap();
+    ramfs.write("/a.txt", 0, b"data").unwrap();
+    ramfs.link("/a.txt", "/b.txt").unwrap();
+    let inode = ramfs.stat("/a.txt").unwrap().inode;
+
+    ramfs.unlink("/a.txt").unwrap();
+    assert_eq!(ramfs.stat("/b.txt").unwrap().links, 1);
+    assert_eq!(ramfs.check(), Vec::<String>::new());
+
+    // An inode kept after its last link would show up as unreachable, ids are never reused
+    ramfs.unlink("/b.txt").unwrap();
+    assert_eq!(ramfs.check(), Vec::<String>::new());
+    ramfs.create("/c.txt", NodeKind::File).unwrap();
+    assert_ne!(ramfs.stat("/c.txt").unwrap().inode, inode);
+}
@@ -0,0 +1,71 @@
+use charizard_host_tests::clock::{self, TICKS_PER_SECOND};
+use charizard_host_tests::file_system::{vfs::NodeKind, FileSystem};
+
+// Moves the kernel clock forward by a second, the timer interrupt does it in the kernel
+fn wait_a_second() -> u64 {
+    for _ in 0..TICKS_PER_SECOND {
+        clock::tick();
+    }
+    clock::now()
+}
+
+#[test]
+fn writes_update_the_modification_time() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"abc").unwrap();
+    let created = fs.stat("/data").unwrap();
+
+    let now = wait_a_second();
+    fs.write_at("/data", 0, b"x").unwrap();
+    let written = fs.stat("/data").unwrap();
+    assert!(written.modified >= now);
+    assert_eq!(written.created, created.created);
+
+    let now = wait_a_second();
+    fs.truncate("/data", 0).unwrap();
+    assert!(fs.stat("/data").unwrap().modified >= now);
+}
+
+#[test]
+fn reads_update_the_access_time_only() {
+    let mut fs = FileSystem::new();
+    fs.create_file("/data", b"abc").unwrap();
+    let before = fs.stat("/data").unwrap();
+
+    let now = wait_a_second();
+    fs.read_file("/data").unwrap();
+    let after = fs.stat("/data").unwrap();
+    assert!(after.accessed >= now);
+    assert_eq!(after.modified, before.modified);
+}
+
+#[test]
+fn directory_changes_update_the_directory() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+
+    let now = wait_a_second();
+    fs.create_file("/docs/new.txt", b"").unwrap();
+    assert!(fs.stat("/docs").unwrap().modified >= now);
+
+    let now = wait_a_second();
+    fs.delete_node("/docs/new.txt").unwrap();
+    assert!(fs.stat("/docs").unwrap().modified >= now);
+}
+
+#[test]
+fn metadata_describes_the_node() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_file("/docs/a.txt", b"hello").unwrap();
+
+    let file = fs.stat("/docs/a.txt").unwrap();
+    assert_eq!((file.kind, file.size, file.links), (NodeKind::File, 5, 1));
+    assert_eq!((file.uid, file.gid, file.mode), (0, 0, 0o644));
+    assert_eq!(file.permissions(), "-rw-r--r--");
+
+    let directory = fs.stat("/docs").unwrap();
+    assert_eq!(directory.kind, NodeKind::Directory);
+    assert_eq!(directory.permissions(), "drwxr-xr-x");
+    assert_ne!(directory.inode, file.inode);
+}
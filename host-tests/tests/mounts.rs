@@ -0,0 +1,71 @@
+use charizard_host_tests::file_system::{ramfs::RamFs, Conflict, FileSystem};
+
+// A tree with `/mnt/hidden.txt` on the root filesystem
+fn with_mount_point() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+    fs.create_file("/mnt/hidden.txt", b"underneath").unwrap();
+    fs
+}
+
+#[test]
+fn mounts_shadow_the_directory_below() {
+    let mut fs = with_mount_point();
+    fs.mount("/mnt", Box::new(RamFs::new())).unwrap();
+
+    assert!(fs.read_dir("/mnt").unwrap().is_empty());
+    assert!(fs.lookup("/mnt/hidden.txt").is_err());
+    fs.create_file("/mnt/new.txt", b"on top").unwrap();
+
+    let unmounted = fs.unmount("/mnt").unwrap();
+    // What was written went to the mounted filesystem, the old content is back
+    assert_eq!(fs.read_file("/mnt/hidden.txt").unwrap(), b"underneath");
+    assert!(fs.lookup("/mnt/new.txt").is_err());
+    assert!(unmounted.lookup("/new.txt").is_ok());
+}
+
+#[test]
+fn mounts_nest_and_unmount_innermost_first() {
+    let mut fs = with_mount_point();
+    fs.mount("/mnt", Box::new(RamFs::new())).unwrap();
+    fs.create_directory("/mnt/inner").unwrap();
+    fs.mount("/mnt/inner", Box::new(RamFs::new())).unwrap();
+    fs.create_file("/mnt/inner/deep.txt", b"deep").unwrap();
+
+    let mounts: Vec<&str> = fs.mounts().map(|mount| mount.path.as_str()).collect();
+    assert_eq!(mounts, ["/", "/mnt", "/mnt/inner"]);
+
+    assert!(fs.unmount("/mnt").is_err());
+    assert!(fs.mount("/mnt", Box::new(RamFs::new())).is_err());
+    assert!(fs.delete_tree("/mnt/inner").is_err());
+
+    fs.unmount("/mnt/inner").unwrap();
+    assert!(fs.read_dir("/mnt/inner").unwrap().is_empty());
+    fs.unmount("/mnt").unwrap();
+    assert!(fs.unmount("/mnt").is_err());
+    assert!(fs.unmount("/").is_err());
+}
+
+#[test]
+fn mounts_need_an_existing_directory() {
+    let mut fs = with_mount_point();
+
+    assert!(fs.mount("/missing", Box::new(RamFs::new())).is_err());
+    assert!(fs.mount("/mnt/hidden.txt", Box::new(RamFs::new())).is_err());
+}
+
+#[test]
+fn moves_stay_on_one_filesystem_and_copies_cross() {
+    let mut fs = with_mount_point();
+    fs.create_directory("/other").unwrap();
+    fs.mount("/other", Box::new(RamFs::new())).unwrap();
+
+    assert!(fs
+        .move_node("/mnt/hidden.txt", "/other/moved.txt", Conflict::Fail)
+        .is_err());
+    assert!(fs.link("/mnt/hidden.txt", "/other/linked.txt").is_err());
+
+    fs.copy_node("/mnt/hidden.txt", "/other/copy.txt", Conflict::Fail)
+        .unwrap();
+    assert_eq!(fs.read_file("/other/copy.txt").unwrap(), b"underneath");
+}
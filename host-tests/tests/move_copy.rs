@@ -0,0 +1,132 @@
+use charizard_host_tests::file_system::{Conflict, FileSystem};
+
+// `/src.txt` and `/dst.txt` with different content, and a directory `/dir` holding `/dir/a.txt`
+fn conflicting() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_file("/src.txt", b"source").unwrap();
+    fs.create_file("/dst.txt", b"destination").unwrap();
+    fs.create_directory("/dir").unwrap();
+    fs.create_file("/dir/a.txt", b"a").unwrap();
+    fs
+}
+
+#[test]
+fn fail_keeps_both_nodes() {
+    let mut fs = conflicting();
+
+    assert!(fs
+        .move_node("/src.txt", "/dst.txt", Conflict::Fail)
+        .is_err());
+    assert!(fs
+        .copy_node("/src.txt", "/dst.txt", Conflict::Fail)
+        .is_err());
+    assert_eq!(fs.read_file("/src.txt").unwrap(), b"source");
+    assert_eq!(fs.read_file("/dst.txt").unwrap(), b"destination");
+
+    // Also when copying a directory onto an existing one
+    fs.create_directory("/other").unwrap();
+    assert!(fs.copy_node("/dir", "/other", Conflict::Fail).is_err());
+    assert!(fs.lookup("/other/a.txt").is_err());
+}
+
+#[test]
+fn skip_leaves_the_destination_alone() {
+    let mut fs = conflicting();
+
+    fs.move_node("/src.txt", "/dst.txt", Conflict::Skip)
+        .unwrap();
+    fs.copy_node("/src.txt", "/dst.txt", Conflict::Skip)
+        .unwrap();
+    assert_eq!(fs.read_file("/src.txt").unwrap(), b"source");
+    assert_eq!(fs.read_file("/dst.txt").unwrap(), b"destination");
+
+    // Directories are merged, existing files in them are skipped
+    fs.create_directory("/copy").unwrap();
+    fs.create_file("/copy/a.txt", b"kept").unwrap();
+    fs.create_file("/dir/b.txt", b"b").unwrap();
+    fs.copy_node("/dir", "/copy", Conflict::Skip).unwrap();
+    assert_eq!(fs.read_file("/copy/a.txt").unwrap(), b"kept");
+    assert_eq!(fs.read_file("/copy/b.txt").unwrap(), b"b");
+}
+
+#[test]
+fn overwrite_replaces_the_destination() {
+    let mut fs = conflicting();
+
+    fs.copy_node("/src.txt", "/dst.txt", Conflict::Overwrite)
+        .unwrap();
+    assert_eq!(fs.read_file("/dst.txt").unwrap(), b"source");
+
+    fs.write_at("/src.txt", 0, b"S").unwrap();
+    fs.move_node("/src.txt", "/dst.txt", Conflict::Overwrite)
+        .unwrap();
+    assert_eq!(fs.read_file("/dst.txt").unwrap(), b"Source");
+    assert!(fs.lookup("/src.txt").is_err());
+
+    // A file never replaces a directory, nor a directory a file
+    assert!(fs
+        .copy_node("/dst.txt", "/dir", Conflict::Overwrite)
+        .is_err());
+    assert_eq!(
+        fs.move_node("/dst.txt", "/dir", Conflict::Overwrite),
+        Err(String::from("'/dir' is a directory"))
+    );
+    assert_eq!(
+        fs.move_node("/dir", "/dst.txt", Conflict::Overwrite),
+        Err(String::from("'/dst.txt' is not a directory"))
+    );
+    assert!(fs.lookup("/dir/a.txt").is_ok());
+    assert_eq!(fs.read_file("/dst.txt").unwrap(), b"Source");
+}
+
+#[test]
+fn missing_sources_are_reported() {
+    let mut fs = conflicting();
+
+    assert!(fs
+        .move_node("/missing", "/missing", Conflict::Fail)
+        .is_err());
+    assert!(fs
+        .move_node("/missing", "/moved", Conflict::Overwrite)
+        .is_err());
+    // Moving a node onto itself does nothing
+    fs.move_node("/src.txt", "/src.txt", Conflict::Fail)
+        .unwrap();
+    assert_eq!(fs.read_file("/src.txt").unwrap(), b"source");
+}
+
+#[test]
+fn directories_are_copied_recursively() {
+    let mut fs = conflicting();
+    fs.create_directory("/dir/sub").unwrap();
+    fs.create_file("/dir/sub/deep.txt", b"deep").unwrap();
+    fs.symlink("a.txt", "/dir/link").unwrap();
+
+    fs.copy_node("/dir", "/copy", Conflict::Fail).unwrap();
+    assert_eq!(fs.read_file("/copy/sub/deep.txt").unwrap(), b"deep");
+    // Symbolic links are copied as links, so this one points into the copy
+    assert_eq!(fs.readlink("/copy/link").unwrap(), "a.txt");
+    fs.write_at("/copy/a.txt", 0, b"b").unwrap();
+    assert_eq!(fs.read_file("/dir/a.txt").unwrap(), b"a");
+}
+
+#[test]
+fn directories_cannot_go_into_themselves() {
+    let mut fs = conflicting();
+    fs.create_directory("/dir/sub").unwrap();
+
+    assert_eq!(
+        fs.move_node("/dir", "/dir/sub/dir", Conflict::Fail),
+        Err(String::from("Cannot move '/dir' into itself"))
+    );
+    assert!(fs.move_node("/dir", "/dir/moved", Conflict::Fail).is_err());
+    assert!(fs
+        .copy_node("/dir", "/dir/sub/copy", Conflict::Fail)
+        .is_err());
+    assert!(fs.lookup("/dir/sub").is_ok());
+    assert!(fs.lookup("/dir/sub/copy").is_err());
+
+    // A sibling whose name starts the same is not inside
+    fs.move_node("/dir", "/dir2", Conflict::Fail).unwrap();
+    assert!(fs.lookup("/dir2/sub").is_ok());
+}
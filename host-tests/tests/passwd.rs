@@ -0,0 +1,80 @@
+use charizard_host_tests::passwd::{hash_password, parse_group, parse_passwd, sha256};
+
+fn hex(digest: [u8; 32]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn sha256_test_vectors() {
+    assert_eq!(
+        hex(sha256(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex(sha256(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    // Two blocks, the padding does not fit after 56 bytes
+    assert_eq!(
+        hex(sha256(
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+        )),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(
+        hex(sha256(&[b'a'; 1000])),
+        "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+    );
+}
+
+#[test]
+fn parses_users() {
+    let text = format!(
+        "# comment\nroot::0:0:root:/root\n\nalice:{}:1000:1000:Alice Liddell:/home/alice\n",
+        hash_password("alice", "wonderland")
+    );
+    let users = parse_passwd(&text).unwrap();
+
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].name, "root");
+    assert!(!users[0].has_password());
+    assert!(users[0].check_password("anything"));
+
+    assert_eq!(users[1].uid, 1000);
+    assert_eq!(users[1].full_name, "Alice Liddell");
+    assert_eq!(users[1].home, "/home/alice");
+    assert!(users[1].check_password("wonderland"));
+    assert!(!users[1].check_password("looking glass"));
+}
+
+#[test]
+fn salts_with_the_name() {
+    assert_ne!(
+        hash_password("alice", "secret"),
+        hash_password("bob", "secret")
+    );
+    assert_eq!(hash_password("alice", "secret").len(), 64);
+}
+
+#[test]
+fn parses_groups() {
+    let groups = parse_group("users::100:alice,bob\nalice::1000:\n").unwrap();
+
+    assert_eq!(groups[0].name, "users");
+    assert_eq!(groups[0].gid, 100);
+    assert_eq!(groups[0].members, ["alice", "bob"]);
+    assert!(groups[1].members.is_empty());
+}
+
+#[test]
+fn rejects_malformed_lines() {
+    assert_eq!(
+        parse_passwd("root::0:0:root:/root\nbroken:line\n"),
+        Err(String::from("Line 2 of passwd is malformed"))
+    );
+    assert_eq!(
+        parse_passwd("alice::x:1000::/home/alice"),
+        Err(String::from("Line 1 of passwd has an invalid id 'x'"))
+    );
+    assert!(parse_group("staff:50").is_err());
+}
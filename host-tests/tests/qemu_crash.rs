@@ -0,0 +1,159 @@
+// Kills QEMU while the kernel is writing to a cfs volume, then boots it again on the same disk
+// image and checks that the volume mounts at the last committed state
+//
+// The kernel side is `tests/cfs_crash.rs`. Its executable is copied out of `deps` so that
+// `bootimage runner` starts it with the drives given here instead of the snapshot ones in
+// `test-args`, since the writes have to reach the image to be seen by the second boot. It needs
+// `bootimage` and `qemu-system-x86_64`, like `cargo test` in the kernel crate, so it only runs
+// when asked for:
+//
+//     cargo test --test qemu_crash -- --ignored
+//
+// The cfs tests in the kernel crate cut the power at every sector of a few operations, this test
+// complements them with real kills at points nobody chose.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+// Must match `MARKER` in `tests/cfs_crash.rs`
+const MARKER: &[u8] = b"CFS CRASH TEST";
+
+// Transactions to wait for before the kill of each round
+const ROUNDS: [usize; 4] = [0, 3, 10, 40];
+
+// (0x10 << 1) | 1, `QemuExitCode::Success` through `isa-debug-exit`
+const SUCCESS: i32 = 33;
+
+fn kernel_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap()
+}
+
+// Builds `tests/cfs_crash.rs` and copies it to `directory`
+fn build_kernel(directory: &Path) -> PathBuf {
+    let output = Command::new("cargo")
+        .args([
+            "test",
+            "--test",
+            "cfs_crash",
+            "--no-run",
+            "--message-format=json",
+        ])
+        .current_dir(kernel_dir())
+        .stderr(Stdio::inherit())
+        .output()
+        .expect("cargo failed to start");
+    assert!(output.status.success(), "building the kernel failed");
+
+    // The artifact message is the only one naming an executable, its path needs no unescaping
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let key = "\"executable\":\"";
+    let start = stdout.find(key).expect("cargo reported no executable") + key.len();
+    let built = &stdout[start..start + stdout[start..].find('"').unwrap()];
+
+    let kernel = directory.join("cfs_crash");
+    fs::copy(built, &kernel).unwrap();
+    kernel
+}
+
+// A copy of `tests/disk.img` that tells the kernel to format it and write until it is killed
+fn prepare_image(image: &Path) {
+    let mut content = fs::read(kernel_dir().join("tests/disk.img")).unwrap();
+    content[..MARKER.len()].copy_from_slice(MARKER);
+    fs::File::create(image)
+        .unwrap()
+        .write_all(&content)
+        .unwrap();
+}
+
+fn boot(kernel: &Path, image: &Path, pidfile: &Path) -> Child {
+    let drive = format!("file={},format=raw,index=1,media=disk", image.display());
+    Command::new("bootimage")
+        .arg("runner")
+        .arg(kernel)
+        .args(["-device", "isa-debug-exit,iobase=0xf4,iosize=0x04"])
+        .args(["-serial", "stdio", "-display", "none"])
+        .args(["-drive", &drive])
+        .arg("-pidfile")
+        .arg(pidfile)
+        .current_dir(kernel_dir())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("bootimage failed to start")
+}
+
+// The number of a `transaction` line, `None` for other lines
+fn transaction(line: &str) -> Option<usize> {
+    line.strip_prefix("transaction ")?.parse().ok()
+}
+
+// Reads the serial output until `wanted` transactions are committed, kills QEMU and returns the
+// last transaction reported
+//
+// `bootimage runner` waits for QEMU, so QEMU itself is killed through its pid file.
+fn kill_after(child: &mut Child, wanted: usize, pidfile: &Path) -> usize {
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut last = None;
+    while last.is_none_or(|last| last < wanted) {
+        let line = lines
+            .next()
+            .expect("the kernel stopped before being killed")
+            .unwrap();
+        last = transaction(&line).or(last);
+    }
+
+    let pid = fs::read_to_string(pidfile).unwrap();
+    let status = Command::new("kill")
+        .args(["-KILL", pid.trim()])
+        .status()
+        .unwrap();
+    assert!(status.success(), "QEMU could not be killed");
+    child.wait().unwrap();
+
+    // Lines printed before the kill landed
+    lines
+        .map_while(Result::ok)
+        .filter_map(|line| transaction(&line))
+        .fold(last.unwrap(), usize::max)
+}
+
+// The generation the second boot found, after checking that it ended successfully
+fn recovered(mut child: Child) -> usize {
+    let output: Vec<String> = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .map_while(Result::ok)
+        .collect();
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(SUCCESS), "{}", output.join("\n"));
+
+    output
+        .iter()
+        .find_map(|line| line.strip_prefix("recovered ")?.parse().ok())
+        .unwrap_or_else(|| panic!("no generation reported:\n{}", output.join("\n")))
+}
+
+#[test]
+#[ignore = "boots the kernel in QEMU, needs bootimage and qemu-system-x86_64"]
+fn recovers_after_qemu_is_killed() {
+    let directory = kernel_dir().join("target/crash");
+    fs::create_dir_all(&directory).unwrap();
+    let kernel = build_kernel(&directory);
+    let image = directory.join("disk.img");
+    let pidfile = directory.join("qemu.pid");
+
+    for wanted in ROUNDS {
+        prepare_image(&image);
+        let mut child = boot(&kernel, &image, &pidfile);
+        let last = kill_after(&mut child, wanted, &pidfile);
+
+        // The transaction being written when QEMU died may have been committed before reporting
+        let found = recovered(boot(&kernel, &image, &pidfile));
+        assert!(
+            found == last || found == last + 1,
+            "committed {} before the kill, recovered {}",
+            last,
+            found
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use charizard_host_tests::file_system::{vfs::NodeKind, Conflict, FileSystem, MAX_SYMLINK_HOPS};
+
+// `/docs/a.txt` and an empty directory `/docs/sub`
+fn with_docs() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_file("/docs/a.txt", b"hello").unwrap();
+    fs.create_directory("/docs/sub").unwrap();
+    fs
+}
+
+#[test]
+fn targets_are_relative_to_the_link() {
+    let mut fs = with_docs();
+    fs.symlink("a.txt", "/docs/relative").unwrap();
+    fs.symlink("../a.txt", "/docs/sub/up").unwrap();
+    fs.symlink("/docs/a.txt", "/docs/sub/absolute").unwrap();
+
+    for path in ["/docs/relative", "/docs/sub/up", "/docs/sub/absolute"] {
+        assert_eq!(fs.read_file(path).unwrap(), b"hello", "{}", path);
+    }
+    // Moving a link keeps its target as written, a relative one now points elsewhere
+    fs.rename_node("/docs/relative", "moved").unwrap();
+    assert_eq!(fs.readlink("/docs/moved").unwrap(), "a.txt");
+    fs.move_node("/docs/moved", "/moved", Conflict::Fail)
+        .unwrap();
+    assert!(fs.read_file("/moved").is_err());
+}
+
+#[test]
+fn links_in_the_middle_of_a_path_are_followed() {
+    let mut fs = with_docs();
+    fs.symlink("docs", "/d").unwrap();
+    fs.symlink("/docs/sub", "/docs/s").unwrap();
+
+    assert_eq!(fs.read_file("/d/a.txt").unwrap(), b"hello");
+    fs.create_file("/d/s/new.txt", b"new").unwrap();
+    assert_eq!(fs.read_file("/docs/sub/new.txt").unwrap(), b"new");
+    assert_eq!(fs.lookup("/d/s").unwrap(), NodeKind::Directory);
+    // `..` is taken lexically, before the link is followed
+    assert_eq!(fs.lookup("/d/s/..").unwrap(), NodeKind::Directory);
+    assert!(fs.lookup("/d/s/../a.txt").is_ok());
+}
+
+#[test]
+fn dangling_links_can_be_read_but_not_followed() {
+    let mut fs = with_docs();
+    fs.symlink("missing.txt", "/docs/dangling").unwrap();
+
+    assert!(fs.lookup("/docs/dangling").is_err());
+    assert!(fs.stat("/docs/dangling").is_err());
+    assert!(fs.read_file("/docs/dangling").is_err());
+    assert_eq!(fs.readlink("/docs/dangling").unwrap(), "missing.txt");
+    assert_eq!(fs.lstat("/docs/dangling").unwrap().kind, NodeKind::Symlink);
+
+    // Creating the target brings the link back to life
+    fs.create_file("/docs/missing.txt", b"found").unwrap();
+    assert_eq!(fs.read_file("/docs/dangling").unwrap(), b"found");
+
+    // Removing a link never touches its target
+    fs.delete_node("/docs/dangling").unwrap();
+    assert!(fs.lookup("/docs/missing.txt").is_ok());
+}
+
+#[test]
+fn lstat_describes_the_link_and_stat_its_target() {
+    let mut fs = with_docs();
+    fs.symlink("a.txt", "/docs/link").unwrap();
+
+    let target = fs.stat("/docs/a.txt").unwrap();
+    let followed = fs.stat("/docs/link").unwrap();
+    let link = fs.lstat("/docs/link").unwrap();
+
+    assert_eq!(
+        (followed.kind, followed.inode),
+        (NodeKind::File, target.inode)
+    );
+    assert_eq!(followed.size, 5);
+    assert_eq!(link.kind, NodeKind::Symlink);
+    assert_ne!(link.inode, target.inode);
+    assert_eq!(link.permissions(), "lrwxrwxrwx");
+
+    // Both agree on anything that is not a link
+    assert_eq!(fs.lstat("/docs/a.txt").unwrap().inode, target.inode);
+    assert!(fs.readlink("/docs/a.txt").is_err());
+}
+
+#[test]
+fn loops_are_reported_instead_of_followed() {
+    let mut fs = with_docs();
+    fs.symlink("b", "/a").unwrap();
+    fs.symlink("a", "/b").unwrap();
+    fs.symlink("itself", "/itself").unwrap();
+
+    assert_eq!(
+        fs.lookup("/a"),
+        Err(String::from("'/a': Too many levels of symbolic links"))
+    );
+    assert!(fs.read_file("/itself").is_err());
+    assert!(fs.create_file("/a/inside.txt", b"").is_err());
+    // The links themselves are still there
+    assert_eq!(fs.readlink("/a").unwrap(), "b");
+    fs.delete_node("/itself").unwrap();
+}
+
+#[test]
+fn long_chains_stop_at_the_hop_limit() {
+    let mut fs = with_docs();
+    // `/link0` takes exactly `MAX_SYMLINK_HOPS` hops to reach the file
+    for hop in 0..MAX_SYMLINK_HOPS {
+        let target = if hop + 1 == MAX_SYMLINK_HOPS {
+            String::from("/docs/a.txt")
+        } else {
+            format!("link{}", hop + 1)
+        };
+        fs.symlink(&target, &format!("/link{}", hop)).unwrap();
+    }
+
+    assert_eq!(fs.read_file("/link0").unwrap(), b"hello");
+    fs.symlink("link0", "/one_more").unwrap();
+    assert!(fs.read_file("/one_more").is_err());
+}
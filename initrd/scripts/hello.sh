@@ -0,0 +1,5 @@
+#!/bin/sh
+# Shell commands to try out the filesystem
+cat /etc/hostname
+ls -l /fixtures
+find / -name *.txt
@@ -1,3 +1,4 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
 use spin::{Mutex, MutexGuard};
 use x86_64::{
     structures::paging::{
@@ -15,6 +16,37 @@ pub mod linked_list_allocator;
 pub const HEAP_START: usize = 0x4444_4444_0000;
 pub const HEAP_SIZE: usize = 100 * 1024;
 
+// Bytes requested by live allocations and their number, kept by the global allocator
+static USED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    pub size: usize,
+    // Bytes requested by the allocations alive right now, blocks rounded up to their size class
+    // and allocator overhead are not included
+    pub used: usize,
+    pub allocations: usize,
+}
+
+pub fn stats() -> HeapStats {
+    HeapStats {
+        size: HEAP_SIZE,
+        used: USED.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+fn record_allocation(size: usize) {
+    USED.fetch_add(size, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+fn record_deallocation(size: usize) {
+    USED.fetch_sub(size, Ordering::Relaxed);
+    ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+}
+
 pub struct Locked<A> {
     inner: Mutex<A>,
 }
@@ -48,7 +48,7 @@ unsafe impl GlobalAlloc for Locked<FixedSizeBlockAllocator> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let mut allocator = self.lock();
 
-        match list_index(&layout) {
+        let ptr = match list_index(&layout) {
             Some(index) => {
                 match allocator.list_heads[index].take() {
                     Some(node) => {
@@ -67,11 +67,17 @@ unsafe impl GlobalAlloc for Locked<FixedSizeBlockAllocator> {
             }
 
             None => allocator.fallback_alloc(layout),
+        };
+
+        if !ptr.is_null() {
+            super::record_allocation(layout.size());
         }
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         let mut allocator = self.lock();
+        super::record_deallocation(layout.size());
 
         match list_index(&layout) {
             Some(index) => {
@@ -0,0 +1,160 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::port::Port;
+
+// Frequency the PIT is programmed to, every timer interrupt is one tick
+pub const TICKS_PER_SECOND: u64 = 100;
+
+const PIT_FREQUENCY: u64 = 1_193_182;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+// Wall-clock time read from the RTC at boot, in seconds since the Unix epoch
+static BOOT_TIME: AtomicU64 = AtomicU64::new(0);
+
+pub fn init() {
+    let divisor = (PIT_FREQUENCY / TICKS_PER_SECOND) as u16;
+
+    unsafe {
+        let mut command: Port<u8> = Port::new(0x43);
+        let mut channel_0: Port<u8> = Port::new(0x40);
+
+        // Channel 0, low byte then high byte, square wave generator
+        command.write(0x36);
+        channel_0.write((divisor & 0xFF) as u8);
+        channel_0.write((divisor >> 8) as u8);
+    }
+
+    BOOT_TIME.store(read_rtc().to_unix(), Ordering::Relaxed);
+}
+
+// Called from the timer interrupt handler
+pub fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+pub fn uptime_ms() -> u64 {
+    ticks() * 1000 / TICKS_PER_SECOND
+}
+
+// Current wall-clock time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    BOOT_TIME.load(Ordering::Relaxed) + ticks() / TICKS_PER_SECOND
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u64,
+    pub month: u64,
+    pub day: u64,
+    pub hour: u64,
+    pub minute: u64,
+    pub second: u64,
+}
+
+impl DateTime {
+    // Converts a Unix timestamp to a UTC date using the days-from-civil algorithm in reverse
+    pub fn from_unix(timestamp: u64) -> Self {
+        let days = timestamp / 86_400;
+        let seconds = timestamp % 86_400;
+
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: seconds / 3600,
+            minute: seconds % 3600 / 60,
+            second: seconds % 60,
+        }
+    }
+
+    pub fn to_unix(&self) -> u64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let month_index = (self.month + 9) % 12;
+        let day_of_year = (153 * month_index + 2) / 5 + self.day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        days * 86_400 + self.hour * 3600 + self.minute * 60 + self.second
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+fn read_cmos(register: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(0x70);
+    let mut data: Port<u8> = Port::new(0x71);
+
+    unsafe {
+        address.write(register);
+        data.read()
+    }
+}
+
+fn read_rtc() -> DateTime {
+    // Wait until the RTC is not in the middle of an update
+    while read_cmos(0x0A) & 0x80 != 0 {}
+
+    let status_b = read_cmos(0x0B);
+    let is_bcd = status_b & 0x04 == 0;
+    let is_12_hour = status_b & 0x02 == 0;
+
+    let decode = |value: u8| -> u64 {
+        if is_bcd {
+            u64::from((value & 0x0F) + (value >> 4) * 10)
+        } else {
+            u64::from(value)
+        }
+    };
+
+    let raw_hour = read_cmos(0x04);
+    let mut hour = decode(raw_hour & 0x7F);
+    if is_12_hour {
+        // Bit 7 marks PM, 12 AM is midnight
+        hour %= 12;
+        if raw_hour & 0x80 != 0 {
+            hour += 12;
+        }
+    }
+
+    DateTime {
+        year: 2000 + decode(read_cmos(0x09)),
+        month: decode(read_cmos(0x08)),
+        day: decode(read_cmos(0x07)),
+        hour,
+        minute: decode(read_cmos(0x02)),
+        second: decode(read_cmos(0x00)),
+    }
+}
@@ -1,62 +1,468 @@
 extern crate alloc;
 
-use crate::file_system::FileSystem;
+use crate::file_system::{
+    self, access,
+    descriptor::{FileHandle, OpenMode},
+    mount,
+    vfs::NodeKind,
+    watch::{Event, EventKind, WatchId},
+    FileSystem,
+};
 use alloc::format;
 use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use lexer::Token;
+use registry::Context;
+use spin::Once;
+use stream::{Stdin, Stdout, Streams};
 
-pub fn parse_and_execute_command(command: &str, fs: &mut FileSystem) -> Result<String, String> {
-    let parts: Vec<&str> = command.split_whitespace().collect();
+mod accounts;
+mod disks;
+mod files;
+pub mod glob;
+pub mod lexer;
+pub mod registry;
+pub mod stream;
+mod text;
+mod watches;
 
-    if parts.is_empty() {
-        return Err(String::from("No command entered"));
+// Set once the built-in commands are registered, to the errors of those that could not be
+static BUILTINS: Once<Vec<String>> = Once::new();
+
+// Registers the shell's own commands, runs at the latest before the first command line and does
+// nothing when called again
+//
+// A built-in whose name a module registered first is left out, every call returns why.
+pub fn init() -> &'static [String] {
+    BUILTINS.call_once(|| {
+        let groups = [
+            files::COMMANDS,
+            text::COMMANDS,
+            disks::COMMANDS,
+            accounts::COMMANDS,
+            watches::COMMANDS,
+            registry::COMMANDS,
+        ];
+
+        groups
+            .into_iter()
+            .flatten()
+            .filter_map(|&builtin| registry::register(Arc::new(builtin)).err())
+            .collect()
+    })
+}
+
+// State kept by the shell between commands
+pub struct Session {
+    cwd: String,
+    previous_dir: Option<String>,
+    // Watches started with `watch`, their events are printed after every command
+    watches: Vec<WatchId>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            cwd: String::from("/"),
+            previous_dir: None,
+            watches: Vec::new(),
+        }
     }
 
-    // Match the command
-    match parts[0] {
-        "mkdir" => {
-            if parts.len() < 2 {
-                return Err(String::from("Usage: mkdir <path>"));
-            }
-            fs.create_directory("/", parts[1])?;
-            Ok(format!("Directory '{}' created", parts[1]))
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    // Turns a path typed by the user into an absolute path rooted at the current directory
+    pub fn resolve(&self, path: &str) -> String {
+        file_system::normalize_path(&self.cwd, path)
+    }
+
+    pub fn change_directory(&mut self, fs: &FileSystem, path: &str) -> Result<(), String> {
+        let target = if path == "-" {
+            self.previous_dir
+                .clone()
+                .ok_or_else(|| String::from("No previous directory"))?
+        } else {
+            self.resolve(path)
+        };
+
+        let metadata = fs.stat(&target)?;
+        if metadata.kind != NodeKind::Directory {
+            return Err(format!("'{}' is not a directory", path));
+        }
+        if !fs.credentials().may(&metadata, access::EXECUTE) {
+            return Err(format!("'{}': Permission denied", path));
         }
-        "touch" => {
-            if parts.len() < 2 {
-                return Err(String::from("Usage: touch <path>"));
+
+        let previous = core::mem::replace(&mut self.cwd, target);
+        self.previous_dir = Some(previous);
+
+        Ok(())
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Where the output of a command goes instead of the console
+enum Redirect {
+    Truncate(String), // > file
+    Append(String),   // >> file
+}
+
+struct CommandLine {
+    // `Word` and `Glob` tokens, globs are expanded right before running the command
+    arguments: Vec<Token>,
+    // File read as standard input with `< file`
+    input: Option<String>,
+    redirect: Option<Redirect>,
+}
+
+// Runs a command line and returns what its last command returns when its output goes to the
+// screen, what commands write to the screen as they run is printed right away
+//
+// Commands joined with `|` form a pipeline and run from left to right, each one reading the
+// output of the previous one. The first command can read a file with `<` and the output of the
+// last one can be written to a file with `>` or `>>`.
+pub fn parse_and_execute_command(
+    command: &str,
+    fs: &mut FileSystem,
+    session: &mut Session,
+) -> Result<String, String> {
+    init();
+    let pipeline = parse_pipeline(lexer::tokenize(command)?)?;
+    let last = pipeline.len() - 1;
+    let mut stdin = Stdin::Terminal;
+
+    for (index, command_line) in pipeline.into_iter().enumerate() {
+        let mut arguments = Vec::new();
+        for argument in command_line.arguments {
+            match argument {
+                Token::Glob(pattern) => arguments.extend(expand_glob(&pattern, fs, session)),
+                Token::Word(word) => arguments.push(word),
+                operator => unreachable!("operator '{}' parsed as an argument", operator),
             }
-            let content: &str = if parts.len() > 2 { parts[2] } else { "" };
-            let _ = fs.create_file("/", parts[1], content);
-            Ok(format!("File '{}' created", parts[1]))
         }
-        "ls" => {
-            if parts.len() < 2 {
-                return Err(String::from("Usage: ls <path>"));
+
+        if let Some(input) = &command_line.input {
+            stdin = Stdin::File(fs.open(&session.resolve(input), OpenMode::Read)?);
+        }
+        // The file is opened first, so the command can write to it as it goes
+        let stdout = match (index == last, &command_line.redirect) {
+            (false, _) => Ok(Stdout::Pipe(Vec::new())),
+            (true, None) => Ok(Stdout::Terminal),
+            (true, Some(redirect)) => open_redirect(redirect, fs, session).map(Stdout::File),
+        };
+        let stdout = match stdout {
+            Ok(stdout) => stdout,
+            Err(error) => {
+                // The redirect is what failed, an error closing the input would hide it
+                if let Stdin::File(handle) = stdin {
+                    let _ = fs.close(handle);
+                }
+                return Err(error);
+            }
+        };
+
+        let mut streams = Streams { stdin, stdout };
+        // What the last command returns on the screen goes back to the caller, anything else is
+        // written to the stream
+        let result = execute_command(&arguments, &mut streams, fs, session).and_then(|output| {
+            if streams.stdout.is_terminal() {
+                return Ok(output);
             }
-            let contents = fs.list_directory(parts[1])?;
-            Ok(format!("Contents of '{}': {:?}", parts[1], contents))
+            streams.stdout.write(fs, output.as_bytes())?;
+            Ok(String::new())
+        });
+        // Both files are closed whatever happened, the first error wins
+        let closed_stdin = match streams.stdin {
+            Stdin::File(handle) => fs.close(handle),
+            _ => Ok(()),
+        };
+        let closed_stdout = match streams.stdout {
+            Stdout::File(handle) => fs.close(handle),
+            _ => Ok(()),
+        };
+        let output = result?;
+        closed_stdin?;
+        closed_stdout?;
+
+        match streams.stdout {
+            Stdout::Pipe(data) => stdin = Stdin::Pipe(data),
+            Stdout::Terminal | Stdout::File(_) => return Ok(output),
         }
-        "cat" => {
-            if parts.len() < 2 {
-                return Err(String::from("Usage: cat <path>"));
+    }
+
+    unreachable!("a pipeline has at least one command")
+}
+
+fn execute_command(
+    arguments: &[String],
+    streams: &mut Streams,
+    fs: &mut FileSystem,
+    session: &mut Session,
+) -> Result<String, String> {
+    let parts: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+    if parts.is_empty() {
+        return Err(String::from("No command entered"));
+    }
+
+    let command =
+        registry::find(parts[0]).ok_or_else(|| format!("Unknown command: {}", parts[0]))?;
+    command.execute(
+        &parts,
+        Context {
+            streams,
+            fs,
+            session,
+        },
+    )
+}
+
+// Describes what the watches started with `watch` saw since the last call, one line per event
+pub fn watch_events(fs: &mut FileSystem, session: &Session) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for &id in &session.watches {
+        for event in fs.read_events(id).unwrap_or_default() {
+            lines.push(format!(
+                "[watch {}] {}",
+                id.number(),
+                describe_event(&event)
+            ));
+        }
+    }
+
+    lines
+}
+
+fn describe_event(event: &Event) -> String {
+    match event.kind {
+        EventKind::Create => format!("created {}", event.path),
+        EventKind::Modify => format!("modified {}", event.path),
+        EventKind::Delete => format!("deleted {}", event.path),
+        EventKind::Rename => format!(
+            "renamed {} to {}",
+            event.path,
+            event.destination.as_deref().unwrap_or("?")
+        ),
+        EventKind::Overflow => {
+            format!("too many changes to {}, some were not recorded", event.path)
+        }
+    }
+}
+
+// Separates `-x` style flags from the operands, combined flags such as `-rf` are split up and
+// anything after `--` is an operand
+fn split_flags<'a>(
+    arguments: &[&'a str],
+    allowed: &str,
+) -> Result<(Vec<char>, Vec<&'a str>), String> {
+    let mut flags = Vec::new();
+    let mut operands = Vec::new();
+    let mut only_operands = false;
+
+    for &argument in arguments {
+        if only_operands || argument == "-" || !argument.starts_with('-') {
+            operands.push(argument);
+        } else if argument == "--" {
+            only_operands = true;
+        } else {
+            for flag in argument[1..].chars() {
+                if !allowed.contains(flag) {
+                    return Err(format!("Unknown option '-{}'", flag));
+                }
+                flags.push(flag);
             }
-            let content = fs.read_file("/", parts[1])?;
-            Ok(String::from(content))
         }
-        "rm" => {
-            if parts.len() < 2 {
-                return Err(String::from("Usage: rm <path>"));
+    }
+
+    Ok((flags, operands))
+}
+
+// Expands a glob pattern into the sorted list of existing paths it matches, or the pattern itself
+// when nothing matches
+//
+// Paths are written the way the pattern was, so a relative pattern gives relative paths. A `**`
+// component matches any number of directories, names starting with `.` are only matched by a
+// component starting with `.`.
+fn expand_glob(pattern: &str, fs: &FileSystem, session: &Session) -> Vec<String> {
+    // Partial matches as (path as written, absolute path)
+    let mut matches = if pattern.starts_with('/') {
+        Vec::from([(String::from("/"), String::from("/"))])
+    } else {
+        Vec::from([(String::new(), String::from(session.cwd()))])
+    };
+    let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+
+    for (index, &component) in components.iter().enumerate() {
+        let is_last = index == components.len() - 1;
+        let mut next = Vec::new();
+
+        for (written, absolute) in matches {
+            if component == "**" {
+                let Ok(walk) = fs.walk(&absolute) else {
+                    continue;
+                };
+
+                // Files only count when `**` ends the pattern, links are never followed and
+                // unreadable directories are skipped like shells do
+                for entry in walk.flatten() {
+                    if entry.depth > 0 && entry.name.starts_with('.') {
+                        continue;
+                    }
+                    if entry.kind != NodeKind::Directory && !is_last {
+                        continue;
+                    }
+
+                    let relative = mount::relative_to(&absolute, &entry.path).unwrap_or_default();
+                    next.push((
+                        join_written(&written, relative.trim_start_matches('/')),
+                        entry.path,
+                    ));
+                }
+            } else if glob::is_pattern(component) {
+                let Ok(entries) = fs.read_dir(&absolute) else {
+                    continue;
+                };
+
+                for entry in entries {
+                    if entry.name.starts_with('.') && !component.starts_with('.') {
+                        continue;
+                    }
+                    if !glob::matches(component, &entry.name) {
+                        continue;
+                    }
+
+                    let child = file_system::normalize_path(&absolute, &entry.name);
+                    if is_last || fs.lookup(&child) == Ok(NodeKind::Directory) {
+                        next.push((join_written(&written, &entry.name), child));
+                    }
+                }
+            } else {
+                let name = glob::unescape(component);
+                next.push((
+                    join_written(&written, &name),
+                    file_system::normalize_path(&absolute, &name),
+                ));
             }
-            fs.delete_node("/", parts[1])?;
-            Ok(format!("Node '{}' deleted", parts[1]))
         }
-        "rename" => {
-            if parts.len() < 3 {
-                return Err(String::from("Usage: rename <path> <new_name>"));
+
+        matches = next;
+    }
+
+    let mut paths: Vec<String> = matches
+        .into_iter()
+        .filter(|(written, absolute)| !written.is_empty() && fs.lstat(absolute).is_ok())
+        .map(|(written, _)| written)
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    if paths.is_empty() {
+        return Vec::from([glob::unescape(pattern)]);
+    }
+    paths
+}
+
+// Appends `name` to a path as written by the user, which is empty for the current directory
+fn join_written(base: &str, name: &str) -> String {
+    if base.is_empty() || name.is_empty() || base.ends_with('/') {
+        format!("{}{}", base, name)
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+// Opens the file named by `redirect` for writing, creating it when missing
+fn open_redirect(
+    redirect: &Redirect,
+    fs: &mut FileSystem,
+    session: &Session,
+) -> Result<FileHandle, String> {
+    let (target, mode) = match redirect {
+        Redirect::Truncate(target) => (target, OpenMode::Write),
+        Redirect::Append(target) => (target, OpenMode::Append),
+    };
+
+    fs.open(&session.resolve(target), mode)
+}
+
+// Splits the token list of a command line at the pipes into commands with their arguments and
+// redirections, which come after the arguments
+fn parse_pipeline(tokens: Vec<Token>) -> Result<Vec<CommandLine>, String> {
+    let mut pipeline = Vec::new();
+    let mut current = CommandLine {
+        arguments: Vec::new(),
+        input: None,
+        redirect: None,
+    };
+    let mut tokens = tokens.into_iter();
+
+    while let Some(token) = tokens.next() {
+        let redirected = current.input.is_some() || current.redirect.is_some();
+
+        match token {
+            Token::Word(_) | Token::Glob(_) if !redirected => current.arguments.push(token),
+            Token::Word(word) | Token::Glob(word) => {
+                return Err(format!("Unexpected '{}', redirection must come last", word))
+            }
+            Token::Pipe => {
+                if current.arguments.is_empty() {
+                    return Err(String::from("Expected a command before '|'"));
+                }
+                if current.redirect.is_some() {
+                    return Err(String::from(
+                        "Only the last command of a pipeline can write to a file",
+                    ));
+                }
+                pipeline.push(core::mem::replace(
+                    &mut current,
+                    CommandLine {
+                        arguments: Vec::new(),
+                        input: None,
+                        redirect: None,
+                    },
+                ));
+            }
+            Token::RedirectIn | Token::RedirectOut | Token::RedirectAppend => {
+                // Redirection targets are not expanded
+                let target = match tokens.next() {
+                    Some(Token::Word(target)) => target,
+                    Some(Token::Glob(pattern)) => glob::unescape(&pattern),
+                    _ => return Err(format!("Expected a file name after '{}'", token)),
+                };
+
+                match token {
+                    Token::RedirectIn if current.input.is_some() => {
+                        return Err(String::from("Standard input is redirected twice"))
+                    }
+                    Token::RedirectIn if !pipeline.is_empty() => {
+                        return Err(String::from(
+                            "Only the first command of a pipeline can read from a file",
+                        ))
+                    }
+                    Token::RedirectIn => current.input = Some(target),
+                    _ if current.redirect.is_some() => {
+                        return Err(String::from("Output is redirected twice"))
+                    }
+                    Token::RedirectAppend => current.redirect = Some(Redirect::Append(target)),
+                    _ => current.redirect = Some(Redirect::Truncate(target)),
+                }
             }
-            fs.rename_node("/", parts[1], parts[2])?;
-            Ok(format!("Node '{}' renamed to '{}'", parts[1], parts[2]))
         }
-        _ => Err(format!("Unknown command: {}", parts[0])),
     }
+
+    if current.arguments.is_empty() && !pipeline.is_empty() {
+        return Err(String::from("Expected a command after '|'"));
+    }
+    pipeline.push(current);
+
+    Ok(pipeline)
 }
@@ -0,0 +1,126 @@
+use super::registry::{usage_error, Builtin, Context};
+use crate::file_system::vfs::Attributes;
+use crate::users;
+use alloc::format;
+use alloc::string::String;
+
+// Commands for users and the permissions of the nodes they own
+pub const COMMANDS: &[Builtin] = &[
+    Builtin {
+        name: "whoami",
+        usage: "",
+        summary: "Print the name of the current user",
+        run: whoami,
+    },
+    Builtin {
+        name: "login",
+        usage: "<user> [password]",
+        summary: "Log in as a user and go to their home directory",
+        run: login,
+    },
+    Builtin {
+        name: "su",
+        usage: "[user] [password]",
+        summary: "Switch to another user, root by default",
+        run: su,
+    },
+    Builtin {
+        name: "chmod",
+        usage: "<mode> <path>...",
+        summary: "Change the permissions of nodes, the mode is octal",
+        run: chmod,
+    },
+    Builtin {
+        name: "chown",
+        usage: "<user>[:<group>] <path>...",
+        summary: "Change the owner and group of nodes",
+        run: chown,
+    },
+];
+
+fn whoami(_parts: &[&str], context: Context) -> Result<String, String> {
+    let fs = context.fs;
+    Ok(users::user_name(fs, fs.credentials().uid))
+}
+
+fn login(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let (name, password) = match parts[1..] {
+        [name] => (name, ""),
+        [name, password] => (name, password),
+        _ => return Err(usage_error(parts[0])),
+    };
+    let user = users::authenticate(fs, name, password)?;
+    let credentials = users::credentials(fs, &user)?;
+
+    users::create_home(fs, &user)?;
+    fs.set_credentials(credentials);
+    session.change_directory(fs, &user.home)?;
+    Ok(format!("Logged in as {}", user.name))
+}
+
+fn su(parts: &[&str], context: Context) -> Result<String, String> {
+    let fs = context.fs;
+    let (name, password) = match parts[1..] {
+        [] => ("root", ""),
+        [name] => (name, ""),
+        [name, password] => (name, password),
+        _ => return Err(usage_error(parts[0])),
+    };
+    // Root becomes anyone without a password
+    let user = if fs.credentials().is_root() {
+        users::find_user(fs, name)?
+    } else {
+        users::authenticate(fs, name, password)?
+    };
+    let credentials = users::credentials(fs, &user)?;
+
+    fs.set_credentials(credentials);
+    Ok(String::new())
+}
+
+fn chmod(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 3 {
+        return Err(usage_error(parts[0]));
+    }
+    let mode = u16::from_str_radix(parts[1], 8)
+        .ok()
+        .filter(|&mode| mode <= 0o7777)
+        .ok_or_else(|| format!("Invalid mode '{}', expected octal like 644", parts[1]))?;
+    let attributes = Attributes {
+        mode: Some(mode),
+        ..Attributes::default()
+    };
+    for path in &parts[2..] {
+        fs.set_attributes(&session.resolve(path), &attributes)?;
+    }
+    Ok(String::new())
+}
+
+fn chown(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 3 {
+        return Err(usage_error(parts[0]));
+    }
+    // `alice:staff`, `alice` or `:staff`
+    let (user, group) = match parts[1].split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (parts[1], None),
+    };
+    let attributes = Attributes {
+        uid: match user {
+            "" => None,
+            user => Some(users::parse_user(fs, user)?),
+        },
+        gid: match group {
+            None | Some("") => None,
+            Some(group) => Some(users::parse_group(fs, group)?),
+        },
+        ..Attributes::default()
+    };
+    for path in &parts[2..] {
+        fs.set_attributes(&session.resolve(path), &attributes)?;
+    }
+    Ok(String::new())
+}
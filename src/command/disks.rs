@@ -0,0 +1,162 @@
+use super::registry::{usage_error, Builtin, Context};
+use crate::devices::block;
+use crate::file_system::{self, cfs};
+use crate::storage;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Commands for disks and the filesystems mounted from them
+pub const COMMANDS: &[Builtin] = &[
+    Builtin {
+        name: "lsblk",
+        usage: "",
+        summary: "List the block devices",
+        run: lsblk,
+    },
+    Builtin {
+        name: "mkfs",
+        usage: "<type> <device>",
+        summary: "Format a block device, only cfs volumes can be created",
+        run: mkfs,
+    },
+    Builtin {
+        name: "fsck",
+        usage: "[path]",
+        summary: "Check the filesystem mounted at a path for problems",
+        run: fsck,
+    },
+    Builtin {
+        name: "sync",
+        usage: "",
+        summary: "Save the in-memory tree to the data disk (hdb)",
+        run: sync,
+    },
+    Builtin {
+        name: "mount",
+        usage: "[<type> <directory> [<device>]]",
+        summary: "Mount a filesystem, or list the mounts",
+        run: mount,
+    },
+    Builtin {
+        name: "umount",
+        usage: "<directory>",
+        summary: "Unmount the filesystem mounted at a directory",
+        run: umount,
+    },
+];
+
+fn lsblk(_parts: &[&str], _context: Context) -> Result<String, String> {
+    let mut lines = Vec::from([format!(
+        "{:<6} {:>7} {:>10} {}",
+        "NAME", "SIZE", "SECTORS", "MODEL"
+    )]);
+    for (name, device) in block::devices() {
+        let device = device.lock();
+        lines.push(format!(
+            "{:<6} {:>7} {:>10} {}",
+            name,
+            format_size(device.size()),
+            device.sector_count(),
+            device.model()
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn mkfs(parts: &[&str], context: Context) -> Result<String, String> {
+    let fs = context.fs;
+    if parts.len() != 3 {
+        return Err(usage_error(parts[0]));
+    }
+    if parts[1] != "cfs" {
+        return Err(format!("Cannot create {} volumes, only cfs", parts[1]));
+    }
+    if !fs.credentials().is_root() {
+        return Err(String::from("Only root can format disks"));
+    }
+    if parts[2] == storage::BOOT_DISK {
+        return Err(format!(
+            "{} is the boot disk, formatting it would leave nothing to boot",
+            parts[2]
+        ));
+    }
+    let device =
+        block::get(parts[2]).ok_or_else(|| format!("No block device named '{}'", parts[2]))?;
+    if fs.uses_device(&device) {
+        return Err(format!(
+            "{} is mounted, unmount it before formatting it",
+            parts[2]
+        ));
+    }
+    cfs::format(&device)?;
+    Ok(format!("Created a cfs volume on {}", parts[2]))
+}
+
+fn fsck(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let path = if parts.len() > 1 { parts[1] } else { "/" };
+    let mut lines = fs.fsck(&session.resolve(path))?;
+    lines.push(match lines.len() {
+        0 => String::from("No problems found"),
+        1 => String::from("1 problem found"),
+        count => format!("{} problems found", count),
+    });
+    Ok(lines.join("\n"))
+}
+
+fn sync(_parts: &[&str], context: Context) -> Result<String, String> {
+    let size = storage::sync(context.fs)?;
+    Ok(format!("Wrote a {} byte filesystem image to disk", size))
+}
+
+fn mount(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() == 1 {
+        let mounts: Vec<String> = fs
+            .mounts()
+            .map(|mount| format!("{} on {}", mount.backend.name(), mount.path))
+            .collect();
+        return Ok(mounts.join("\n"));
+    }
+    if parts.len() < 3 {
+        return Err(usage_error(parts[0]));
+    }
+    let device = match parts.get(3) {
+        Some(name) => {
+            Some(block::get(name).ok_or_else(|| format!("No block device named '{}'", name))?)
+        }
+        None => None,
+    };
+    let backend = file_system::new_backend(parts[1], device)?;
+    fs.mount(&session.resolve(parts[2]), backend)?;
+    Ok(format!("Mounted {} on '{}'", parts[1], parts[2]))
+}
+
+fn umount(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 2 {
+        return Err(usage_error(parts[0]));
+    }
+    fs.unmount(&session.resolve(parts[1]))?;
+    Ok(format!("Unmounted '{}'", parts[1]))
+}
+
+// Renders a byte count with a binary unit suffix the way `lsblk` does, e.g. `512`, `64K`, `1.5M`
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["", "K", "M", "G", "T"];
+
+    let mut unit = 0;
+    // Tenths of the current unit, to keep one decimal without floating point
+    let mut tenths = bytes * 10;
+    while tenths >= 10 * 1024 && unit < UNITS.len() - 1 {
+        tenths /= 1024;
+        unit += 1;
+    }
+
+    if tenths.is_multiple_of(10) {
+        format!("{}{}", tenths / 10, UNITS[unit])
+    } else {
+        format!("{}.{}{}", tenths / 10, tenths % 10, UNITS[unit])
+    }
+}
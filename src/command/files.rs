@@ -0,0 +1,592 @@
+use super::registry::{usage_error, Builtin, Context};
+use super::{glob, split_flags};
+use crate::clock::DateTime;
+use crate::file_system::{self, mount, vfs::NodeKind, Conflict, FileSystem};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Commands moving around the tree and managing its files
+pub const COMMANDS: &[Builtin] = &[
+    Builtin {
+        name: "cd",
+        usage: "[path|-]",
+        summary: "Change the current directory, `-` goes back to the previous one",
+        run: cd,
+    },
+    Builtin {
+        name: "pwd",
+        usage: "",
+        summary: "Print the current directory",
+        run: pwd,
+    },
+    Builtin {
+        name: "mkdir",
+        usage: "<path>",
+        summary: "Create a directory",
+        run: mkdir,
+    },
+    Builtin {
+        name: "touch",
+        usage: "<path> [content]",
+        summary: "Create a file unless it exists",
+        run: touch,
+    },
+    Builtin {
+        name: "write",
+        usage: "<path> [content]",
+        summary: "Replace the content of a file",
+        run: write,
+    },
+    Builtin {
+        name: "ls",
+        usage: "[-l] [path]",
+        summary: "List a directory",
+        run: ls,
+    },
+    Builtin {
+        name: "stat",
+        usage: "<path>",
+        summary: "Show the metadata of a node",
+        run: stat,
+    },
+    Builtin {
+        name: "rm",
+        usage: "[-r] <path>...",
+        summary: "Delete nodes, with -r whole directories",
+        run: rm,
+    },
+    Builtin {
+        name: "tree",
+        usage: "[directory]",
+        summary: "Draw a directory and everything below it",
+        run: tree,
+    },
+    Builtin {
+        name: "du",
+        usage: "[-s] [path]",
+        summary: "Show the bytes used by every directory below a path",
+        run: du,
+    },
+    Builtin {
+        name: "find",
+        usage: "[directory] [-name <pattern>] [-type f|d|l|c|b]",
+        summary: "Search a directory for nodes by name and type",
+        run: find,
+    },
+    Builtin {
+        name: "rename",
+        usage: "<path> <new_name>",
+        summary: "Rename a node within its directory",
+        run: rename,
+    },
+    Builtin {
+        name: "mv",
+        usage: "[-f|-n] <source>... <destination>",
+        summary: "Move nodes to another path or into a directory",
+        run: move_or_copy,
+    },
+    Builtin {
+        name: "cp",
+        usage: "[-r] [-f|-n] <source>... <destination>",
+        summary: "Copy nodes to another path or into a directory",
+        run: move_or_copy,
+    },
+    Builtin {
+        name: "ln",
+        usage: "[-s] <target> <link_name>",
+        summary: "Create a hard link, or with -s a symbolic link",
+        run: ln,
+    },
+    Builtin {
+        name: "readlink",
+        usage: "<path>",
+        summary: "Print the target of a symbolic link",
+        run: readlink,
+    },
+];
+
+fn cd(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let path = if parts.len() > 1 { parts[1] } else { "/" };
+    session.change_directory(fs, path)?;
+    Ok(String::from(session.cwd()))
+}
+
+fn pwd(_parts: &[&str], context: Context) -> Result<String, String> {
+    Ok(String::from(context.session.cwd()))
+}
+
+fn mkdir(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 2 {
+        return Err(usage_error(parts[0]));
+    }
+    fs.create_directory(&session.resolve(parts[1]))?;
+    Ok(format!("Directory '{}' created", parts[1]))
+}
+
+fn touch(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 2 {
+        return Err(usage_error(parts[0]));
+    }
+    let path = session.resolve(parts[1]);
+    // An existing node is left alone, any other failure is reported
+    if fs.lstat(&path).is_ok() {
+        return Ok(format!("'{}' already exists", parts[1]));
+    }
+    let content = parts[2..].join(" ");
+    fs.create_file(&path, content.as_bytes())?;
+    Ok(format!("File '{}' created", parts[1]))
+}
+
+fn write(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 2 {
+        return Err(usage_error(parts[0]));
+    }
+    let path = session.resolve(parts[1]);
+    let content = parts[2..].join(" ");
+    fs.truncate(&path, 0)?;
+    let written = fs.write_at(&path, 0, content.as_bytes())?;
+    Ok(format!("Wrote {} bytes to '{}'", written, parts[1]))
+}
+
+fn ls(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context {
+        streams,
+        fs,
+        session,
+    } = context;
+    let (flags, operands) = split_flags(&parts[1..], "l")?;
+    let long = flags.contains(&'l');
+    let path = operands.first().copied().unwrap_or(".");
+
+    if long {
+        return list_long(&session.resolve(path), fs);
+    }
+    // One name per line for the commands and files reading it, like Unix
+    if !streams.stdout.is_terminal() {
+        let names: Vec<String> = fs
+            .read_dir(&session.resolve(path))?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        return Ok(names.join("\n"));
+    }
+    let contents = fs.list_directory(&session.resolve(path))?;
+    Ok(format!("Contents of '{}': {:?}", path, contents))
+}
+
+fn stat(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 2 {
+        return Err(usage_error(parts[0]));
+    }
+    let path = session.resolve(parts[1]);
+    let metadata = fs.lstat(&path)?;
+    let (kind, name) = match metadata.kind {
+        NodeKind::File => ("regular file", path),
+        NodeKind::Directory => ("directory", path),
+        NodeKind::CharDevice => ("character special file", path),
+        NodeKind::BlockDevice => ("block special file", path),
+        NodeKind::Symlink => {
+            let target = fs.readlink(&path)?;
+            ("symbolic link", format!("{} -> {}", path, target))
+        }
+    };
+    Ok(format!(
+        "  File: {}\n  Type: {}\n  Size: {}\n Inode: {}  Links: {}\n\
+         Access: ({:04o}/{})  Uid: {}  Gid: {}\n\
+         Access: {}\nModify: {}\nCreate: {}",
+        name,
+        kind,
+        metadata.size,
+        metadata.inode,
+        metadata.links,
+        metadata.mode,
+        metadata.permissions(),
+        metadata.uid,
+        metadata.gid,
+        DateTime::from_unix(metadata.accessed),
+        DateTime::from_unix(metadata.modified),
+        DateTime::from_unix(metadata.created),
+    ))
+}
+
+fn rm(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let (flags, operands) = split_flags(&parts[1..], "r")?;
+    if operands.is_empty() {
+        return Err(usage_error(parts[0]));
+    }
+
+    // A failure does not stop the other operands, the error lists what was deleted as well
+    let mut messages = Vec::new();
+    let mut failed = false;
+    for path in operands {
+        let result = if flags.contains(&'r') {
+            fs.delete_tree(&session.resolve(path))
+        } else {
+            fs.delete_node(&session.resolve(path))
+        };
+        match result {
+            Ok(()) => messages.push(format!("Node '{}' deleted", path)),
+            Err(error) => {
+                messages.push(format!("Cannot remove '{}': {}", path, error));
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        return Err(messages.join("\n"));
+    }
+    Ok(messages.join("\n"))
+}
+
+fn tree(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let path = if parts.len() > 1 { parts[1] } else { "." };
+    let resolved = session.resolve(path);
+    if fs.lookup(&resolved)? != NodeKind::Directory {
+        return Err(format!("'{}' is not a directory", path));
+    }
+
+    let mut lines = Vec::from([String::from(path)]);
+    let mut errors = Vec::new();
+    let counts = render_tree(&resolved, "", fs, &mut lines, &mut errors)?;
+    lines.push(String::new());
+    lines.push(counts.summary());
+    with_errors(lines, errors)
+}
+
+fn du(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let (flags, operands) = split_flags(&parts[1..], "s")?;
+    let path = operands.first().copied().unwrap_or(".");
+
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    let total = disk_usage(&session.resolve(path), path, fs, &mut lines, &mut errors)?;
+    // A plain file has no directory lines, report it on its own
+    if flags.contains(&'s') || lines.is_empty() {
+        lines = Vec::from([format!("{:<8} {}", total, path)]);
+    }
+    with_errors(lines, errors)
+}
+
+fn rename(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 3 {
+        return Err(usage_error(parts[0]));
+    }
+    fs.rename_node(&session.resolve(parts[1]), parts[2])?;
+    Ok(format!("Node '{}' renamed to '{}'", parts[1], parts[2]))
+}
+
+// `mv` and `cp`, which only differ in what is done to each source
+fn move_or_copy(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let (flags, operands) = split_flags(&parts[1..], "rfn")?;
+    let recursive = flags.contains(&'r');
+
+    if operands.len() < 2 || (parts[0] == "mv" && recursive) {
+        return Err(usage_error(parts[0]));
+    }
+
+    // -n wins over -f, like GNU coreutils
+    let conflict = if flags.contains(&'n') {
+        Conflict::Skip
+    } else if flags.contains(&'f') {
+        Conflict::Overwrite
+    } else {
+        Conflict::Fail
+    };
+
+    let (sources, destination) = operands.split_at(operands.len() - 1);
+    let destination = session.resolve(destination[0]);
+    let into_directory = fs.lookup(&destination) == Ok(NodeKind::Directory);
+
+    if sources.len() > 1 && !into_directory {
+        return Err(format!("'{}' is not a directory", destination));
+    }
+
+    let mut messages = Vec::new();
+    for &source in sources {
+        let typed_source = source;
+        let source = session.resolve(source);
+        let target = if into_directory {
+            let (_, name) = file_system::split_path("/", &source)?;
+            file_system::normalize_path(&destination, &name)
+        } else {
+            destination.clone()
+        };
+
+        if parts[0] == "mv" {
+            fs.move_node(&source, &target, conflict)?;
+            messages.push(format!("Moved '{}' to '{}'", typed_source, target));
+        } else {
+            if !recursive && fs.lookup(&source)? == NodeKind::Directory {
+                return Err(format!("'{}' is a directory, use cp -r", typed_source));
+            }
+            fs.copy_node(&source, &target, conflict)?;
+            messages.push(format!("Copied '{}' to '{}'", typed_source, target));
+        }
+    }
+
+    Ok(messages.join("\n"))
+}
+
+fn ln(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let (flags, operands) = split_flags(&parts[1..], "s")?;
+    if operands.len() != 2 {
+        return Err(usage_error(parts[0]));
+    }
+
+    // A symbolic link stores its target as typed, relative to the link's directory
+    if flags.contains(&'s') {
+        fs.symlink(operands[0], &session.resolve(operands[1]))?;
+    } else {
+        fs.link(&session.resolve(operands[0]), &session.resolve(operands[1]))?;
+    }
+    Ok(format!("Linked '{}' to '{}'", operands[1], operands[0]))
+}
+
+fn readlink(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() < 2 {
+        return Err(usage_error(parts[0]));
+    }
+    fs.readlink(&session.resolve(parts[1]))
+}
+
+#[derive(Default)]
+struct TreeCounts {
+    directories: usize,
+    files: usize,
+}
+
+impl TreeCounts {
+    fn summary(&self) -> String {
+        format!(
+            "{} {}, {} {}",
+            self.directories,
+            if self.directories == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+            self.files,
+            if self.files == 1 { "file" } else { "files" },
+        )
+    }
+}
+
+// Appends the entries below the directory `path` to `lines` using box-drawing characters and
+// returns how many directories and files were found in the whole subtree, directories that
+// cannot be read are shown empty and the reason is added to `errors`
+fn render_tree(
+    path: &str,
+    prefix: &str,
+    fs: &FileSystem,
+    lines: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) -> Result<TreeCounts, String> {
+    let entries = fs.read_dir(path).unwrap_or_else(|error| {
+        errors.push(error);
+        Vec::new()
+    });
+    let mut counts = TreeCounts::default();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let is_last = index == entries.len() - 1;
+        let (branch, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        match entry.kind {
+            NodeKind::File | NodeKind::CharDevice | NodeKind::BlockDevice => {
+                counts.files += 1;
+                lines.push(format!("{}{}{}", prefix, branch, entry.name));
+            }
+            // Links are not followed, their target could be an ancestor
+            NodeKind::Symlink => {
+                counts.files += 1;
+                let target = fs.readlink(&file_system::normalize_path(path, &entry.name))?;
+                lines.push(format!("{}{}{} -> {}", prefix, branch, entry.name, target));
+            }
+            NodeKind::Directory => {
+                counts.directories += 1;
+
+                // The directory line shows the counts of its own subtree, so it is filled in
+                // once the children have been rendered
+                let line = lines.len();
+                lines.push(String::new());
+
+                let child_prefix = format!("{}{}", prefix, indent);
+                let child = file_system::normalize_path(path, &entry.name);
+                let child_counts = render_tree(&child, &child_prefix, fs, lines, errors)?;
+
+                lines[line] = format!(
+                    "{}{}{}/ ({})",
+                    prefix,
+                    branch,
+                    entry.name,
+                    child_counts.summary()
+                );
+                counts.directories += child_counts.directories;
+                counts.files += child_counts.files;
+            }
+        }
+    }
+
+    Ok(counts)
+}
+
+// Appends one `<bytes> <path>` line per directory in the subtree at `path`, children first like
+// `du`, and returns the number of bytes used by the whole subtree, directories that cannot be
+// read count as empty and the reason is added to `errors`
+fn disk_usage(
+    path: &str,
+    display: &str,
+    fs: &FileSystem,
+    lines: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) -> Result<usize, String> {
+    let metadata = fs.lstat(path)?;
+
+    let total = match metadata.kind {
+        NodeKind::File | NodeKind::Symlink => metadata.size,
+        // Devices take no space, a disk's size is not stored in the tree
+        NodeKind::CharDevice | NodeKind::BlockDevice => 0,
+        NodeKind::Directory => {
+            let mut total = 0;
+            let entries = fs.read_dir(path).unwrap_or_else(|error| {
+                errors.push(error);
+                Vec::new()
+            });
+            for entry in entries {
+                let child = file_system::normalize_path(path, &entry.name);
+                let child_display = format!("{}/{}", display.trim_end_matches('/'), entry.name);
+                total += disk_usage(&child, &child_display, fs, lines, errors)?;
+            }
+            total
+        }
+    };
+
+    if metadata.kind == NodeKind::Directory {
+        lines.push(format!("{:<8} {}", total, display));
+    }
+
+    Ok(total)
+}
+
+// Renders one line per directory entry with its metadata, in the column layout of `ls -l`
+fn list_long(path: &str, fs: &FileSystem) -> Result<String, String> {
+    let mut lines = Vec::new();
+
+    for entry in fs.read_dir(path)? {
+        let child = file_system::normalize_path(path, &entry.name);
+        let metadata = fs.lstat(&child)?;
+        let name = match metadata.kind {
+            NodeKind::Symlink => format!("{} -> {}", entry.name, fs.readlink(&child)?),
+            _ => entry.name,
+        };
+        lines.push(format!(
+            "{} {:>3} {:>5} {:>5} {:>8} {} {}",
+            metadata.permissions(),
+            metadata.links,
+            metadata.uid,
+            metadata.gid,
+            metadata.size,
+            DateTime::from_unix(metadata.modified),
+            name,
+        ));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+// Lists the nodes below `parts[1]` (the current directory by default) whose name matches the
+// `-name` glob and whose kind matches `-type`, in the order of a depth-first walk
+fn find(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let arguments = &parts[1..];
+    let usage = || usage_error(parts[0]);
+
+    let (start, mut options) = match arguments.split_first() {
+        Some((&first, rest)) if !first.starts_with('-') => (first, rest),
+        _ => (".", arguments),
+    };
+
+    let mut name = None;
+    let mut kind = None;
+    while let Some((&option, rest)) = options.split_first() {
+        let (&value, rest) = rest.split_first().ok_or_else(usage)?;
+        match option {
+            "-name" => name = Some(value),
+            "-type" => {
+                kind = Some(match value {
+                    "f" => NodeKind::File,
+                    "d" => NodeKind::Directory,
+                    "l" => NodeKind::Symlink,
+                    "c" => NodeKind::CharDevice,
+                    "b" => NodeKind::BlockDevice,
+                    _ => {
+                        return Err(format!(
+                            "Unknown type '{}', expected f, d, l, c or b",
+                            value
+                        ))
+                    }
+                })
+            }
+            _ => return Err(usage()),
+        }
+        options = rest;
+    }
+
+    let root = session.resolve(start);
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+
+    for entry in fs.walk(&root)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+
+        if name.is_some_and(|pattern| !glob::matches(pattern, &entry.name))
+            || kind.is_some_and(|kind| kind != entry.kind)
+        {
+            continue;
+        }
+
+        // Paths are printed below the directory as it was typed, like `find` does
+        lines.push(match mount::relative_to(&root, &entry.path) {
+            Some(relative) if entry.depth > 0 => {
+                format!("{}{}", start.trim_end_matches('/'), relative)
+            }
+            _ => String::from(start),
+        });
+    }
+
+    with_errors(lines, errors)
+}
+
+// Output of a command that goes on past directories it cannot read, the errors are listed after
+// what was found and make the command fail
+fn with_errors(mut lines: Vec<String>, errors: Vec<String>) -> Result<String, String> {
+    if errors.is_empty() {
+        return Ok(lines.join("\n"));
+    }
+    lines.extend(errors);
+    Err(lines.join("\n"))
+}
@@ -0,0 +1,138 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Whether a single path component of a glob pattern contains a wildcard
+pub fn is_pattern(component: &str) -> bool {
+    let mut chars = component.chars();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+// Removes the backslashes escaping special characters, turning a pattern back into the text it
+// matches literally
+pub fn unescape(pattern: &str) -> String {
+    let mut text = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => text.push(chars.next().unwrap_or('\\')),
+            c => text.push(c),
+        }
+    }
+
+    text
+}
+
+// Whether the whole of `name` matches `pattern`
+//
+// `*` matches any run of characters, `?` any single character and `[a-z]` one character out of a
+// set of characters and ranges, which is negated by a leading `!` or `^`. A backslash makes the
+// next character literal and a `[` without a closing `]` is literal too.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let mut p = 0;
+    let mut n = 0;
+    // Pattern position after the last `*` and the name position that star has matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, n));
+            continue;
+        }
+
+        if p < pattern.len() {
+            let (len, matched) = match_one(&pattern[p..], name[n]);
+            if matched {
+                p += len;
+                n += 1;
+                continue;
+            }
+        }
+
+        // Let the last star swallow one more character and retry from there
+        match star {
+            Some((star_p, star_n)) => {
+                p = star_p;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Matches `character` against the element at the start of `pattern`, which is not a `*`, and
+// returns how many pattern characters the element spans
+fn match_one(pattern: &[char], character: char) -> (usize, bool) {
+    match pattern[0] {
+        '?' => (1, true),
+        '\\' => match pattern.get(1) {
+            Some(&escaped) => (2, escaped == character),
+            None => (1, character == '\\'),
+        },
+        '[' => match match_class(&pattern[1..], character) {
+            Some((len, matched)) => (len + 1, matched),
+            None => (1, character == '['),
+        },
+        literal => (1, literal == character),
+    }
+}
+
+// Matches `character` against the body of a `[...]` class, `pattern` starts right after the `[`
+//
+// Returns the length of the body including the closing `]`, or `None` when it is never closed.
+fn match_class(pattern: &[char], character: char) -> Option<(usize, bool)> {
+    let mut i = 0;
+    let negated = matches!(pattern.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        let mut low = *pattern.get(i)?;
+        // A `]` right after the opening bracket is part of the set
+        if low == ']' && !first {
+            return Some((i + 1, matched != negated));
+        }
+        if low == '\\' {
+            i += 1;
+            low = *pattern.get(i)?;
+        }
+        i += 1;
+        first = false;
+
+        let mut high = low;
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&c| c != ']') {
+            high = pattern[i + 1];
+            i += 2;
+            if high == '\\' {
+                high = *pattern.get(i)?;
+                i += 1;
+            }
+        }
+
+        if low <= character && character <= high {
+            matched = true;
+        }
+    }
+}
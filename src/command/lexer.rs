@@ -0,0 +1,147 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(String),
+    // Word with unquoted `*`, `?` or `[`, quoted special characters in it are escaped with `\`
+    Glob(String),
+    Pipe,           // |
+    RedirectIn,     // <
+    RedirectOut,    // >
+    RedirectAppend, // >>
+}
+
+impl core::fmt::Display for Token {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Token::Word(word) | Token::Glob(word) => write!(f, "{}", word),
+            Token::Pipe => write!(f, "|"),
+            Token::RedirectIn => write!(f, "<"),
+            Token::RedirectOut => write!(f, ">"),
+            Token::RedirectAppend => write!(f, ">>"),
+        }
+    }
+}
+
+// Splits a command line into tokens
+//
+// Words are separated by unquoted whitespace. Inside single quotes every character is taken
+// literally, inside double quotes a backslash only escapes `"` and `\`, and outside of quotes a
+// backslash escapes any character. Quotes can appear in the middle of a word (`a"b c"d` is the
+// single word `ab cd`) and `''` or `""` produce an empty word. Unquoted `|`, `<`, `>` and `>>` are
+// returned as operators even when they are not surrounded by whitespace. Words with an unquoted
+// wildcard become `Glob` tokens for the shell to expand.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut word = WordBuilder::default();
+
+    while let Some(character) = chars.next() {
+        match character {
+            ' ' | '\t' | '\n' | '\r' => {
+                word.flush(&mut tokens);
+            }
+            '\'' => {
+                word.in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push_quoted(c),
+                        None => return Err(String::from("Unterminated single quote")),
+                    }
+                }
+            }
+            '"' => {
+                word.in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push_quoted(c),
+                            Some(c) => {
+                                word.push_quoted('\\');
+                                word.push_quoted(c);
+                            }
+                            None => return Err(String::from("Unterminated double quote")),
+                        },
+                        Some(c) => word.push_quoted(c),
+                        None => return Err(String::from("Unterminated double quote")),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| String::from("Trailing backslash"))?;
+                word.push_quoted(escaped);
+            }
+            '|' | '<' | '>' => {
+                word.flush(&mut tokens);
+
+                let token = match character {
+                    '|' => Token::Pipe,
+                    '<' => Token::RedirectIn,
+                    _ if chars.peek() == Some(&'>') => {
+                        chars.next();
+                        Token::RedirectAppend
+                    }
+                    _ => Token::RedirectOut,
+                };
+                tokens.push(token);
+            }
+            c => word.push(c),
+        }
+    }
+
+    word.flush(&mut tokens);
+
+    Ok(tokens)
+}
+
+// The word being read, kept both as typed text and as a glob pattern in case it turns out to
+// contain a wildcard
+#[derive(Default)]
+struct WordBuilder {
+    text: String,
+    pattern: String,
+    // Only set words are pushed, which lets quoted empty strings through
+    in_word: bool,
+    has_wildcard: bool,
+}
+
+impl WordBuilder {
+    fn push(&mut self, character: char) {
+        self.in_word = true;
+        self.has_wildcard |= matches!(character, '*' | '?' | '[');
+        self.text.push(character);
+        self.pattern.push(character);
+    }
+
+    // Pushes a quoted or escaped character, which never acts as a wildcard
+    fn push_quoted(&mut self, character: char) {
+        self.in_word = true;
+        self.text.push(character);
+        if matches!(character, '*' | '?' | '[' | ']' | '\\') {
+            self.pattern.push('\\');
+        }
+        self.pattern.push(character);
+    }
+
+    fn flush(&mut self, tokens: &mut Vec<Token>) {
+        if !self.in_word {
+            return;
+        }
+
+        let text = core::mem::take(&mut self.text);
+        let pattern = core::mem::take(&mut self.pattern);
+        tokens.push(if self.has_wildcard {
+            Token::Glob(pattern)
+        } else {
+            Token::Word(text)
+        });
+
+        self.in_word = false;
+        self.has_wildcard = false;
+    }
+}
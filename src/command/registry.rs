@@ -0,0 +1,145 @@
+use super::stream::Streams;
+use super::Session;
+use crate::file_system::FileSystem;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+// What a command works on besides its arguments
+pub struct Context<'a> {
+    pub streams: &'a mut Streams,
+    pub fs: &'a mut FileSystem,
+    pub session: &'a mut Session,
+}
+
+// A shell command, found by the shell through its name
+pub trait Command: Send + Sync {
+    fn name(&self) -> &str;
+
+    // What follows the name on the command line, e.g. `[-r] <path>...`
+    fn usage(&self) -> &str;
+
+    // One line description listed by `help`
+    fn summary(&self) -> &str;
+
+    // Runs the command, `arguments[0]` is the command's name
+    //
+    // Output can be written to `context.streams.stdout` while the command runs, what it returns
+    // is written there after it.
+    fn execute(&self, arguments: &[&str], context: Context) -> Result<String, String>;
+}
+
+// A command implemented by a plain function, which is how the shell's own commands are written
+#[derive(Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub summary: &'static str,
+    pub run: fn(&[&str], Context) -> Result<String, String>,
+}
+
+impl Command for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn usage(&self) -> &str {
+        self.usage
+    }
+
+    fn summary(&self) -> &str {
+        self.summary
+    }
+
+    fn execute(&self, arguments: &[&str], context: Context) -> Result<String, String> {
+        (self.run)(arguments, context)
+    }
+}
+
+// Commands the shell can run, in the order they were registered
+static REGISTERED: Mutex<Vec<Arc<dyn Command>>> = Mutex::new(Vec::new());
+
+pub fn register(command: Arc<dyn Command>) -> Result<(), String> {
+    let name = command.name();
+    // The shell splits command lines at spaces, such a name could never be typed
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("Invalid command name '{}'", name));
+    }
+
+    let mut commands = REGISTERED.lock();
+    if commands.iter().any(|registered| registered.name() == name) {
+        return Err(format!("A command named '{}' already exists", name));
+    }
+    commands.push(command);
+
+    Ok(())
+}
+
+// The registry is not locked while the command runs, so commands can look up others
+pub fn find(name: &str) -> Option<Arc<dyn Command>> {
+    REGISTERED
+        .lock()
+        .iter()
+        .find(|command| command.name() == name)
+        .cloned()
+}
+
+pub fn commands() -> Vec<Arc<dyn Command>> {
+    REGISTERED.lock().clone()
+}
+
+// The error a command returns when its arguments do not match its usage
+pub fn usage_error(name: &str) -> String {
+    match find(name) {
+        Some(command) => usage_line(command.as_ref()),
+        None => format!("Usage: {}", name),
+    }
+}
+
+fn usage_line(command: &dyn Command) -> String {
+    let line = format!("Usage: {} {}", command.name(), command.usage());
+    String::from(line.trim_end())
+}
+
+// `help` reads the registry when it runs, so it also covers commands registered after it
+pub const COMMANDS: &[Builtin] = &[Builtin {
+    name: "help",
+    usage: "[command]",
+    summary: "List the commands, or show how to use one",
+    run: help,
+}];
+
+fn help(parts: &[&str], _context: Context) -> Result<String, String> {
+    match parts[1..] {
+        [] => {
+            let mut commands = commands();
+            commands.sort_by(|a, b| a.name().cmp(b.name()));
+            let width = commands
+                .iter()
+                .map(|command| command.name().len())
+                .max()
+                .unwrap_or(0);
+
+            let mut lines: Vec<String> = commands
+                .iter()
+                .map(|command| format!("{:<width$}  {}", command.name(), command.summary()))
+                .collect();
+            lines.push(String::new());
+            lines.push(String::from(
+                "Run 'help <command>' to see how to use a command",
+            ));
+            Ok(lines.join("\n"))
+        }
+        [name] => {
+            let command = find(name).ok_or_else(|| format!("Unknown command: {}", name))?;
+            Ok(format!(
+                "{}\n{}",
+                usage_line(command.as_ref()),
+                command.summary()
+            ))
+        }
+        _ => Err(usage_error(parts[0])),
+    }
+}
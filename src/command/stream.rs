@@ -0,0 +1,84 @@
+use crate::file_system::{descriptor::FileHandle, FileSystem};
+use crate::print;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Bytes read from a file at once
+pub const CHUNK_SIZE: usize = 512;
+
+// Standard input of a command
+//
+// Commands of a pipeline run one after the other, so a pipe holds the whole output of the
+// previous command by the time the next one reads it.
+pub enum Stdin {
+    // The keyboard belongs to the shell, a command typed on its own has nothing to read
+    Terminal,
+    // Output of the previous command of a pipeline
+    Pipe(Vec<u8>),
+    // File opened for `< path`, closed by the shell once the command is done
+    File(FileHandle),
+}
+
+impl Stdin {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Stdin::Terminal)
+    }
+
+    // Reads everything that is left, later reads return nothing
+    pub fn read_to_end(&mut self, fs: &mut FileSystem) -> Result<Vec<u8>, String> {
+        match self {
+            Stdin::Terminal => Err(String::from("Nothing to read, use a pipe or '<'")),
+            Stdin::Pipe(data) => Ok(core::mem::take(data)),
+            Stdin::File(handle) => {
+                let mut data = Vec::new();
+                let mut chunk = vec![0; CHUNK_SIZE];
+                loop {
+                    let count = fs.read(*handle, &mut chunk)?;
+                    if count == 0 {
+                        return Ok(data);
+                    }
+                    data.extend_from_slice(&chunk[..count]);
+                }
+            }
+        }
+    }
+}
+
+// Standard output of a command
+//
+// Commands write to it as they go or return their output, which the shell writes once the
+// command is done. Writes to the screen show up right away, what the last command of a line
+// returns is handed back to the caller of the shell, which prints it.
+pub enum Stdout {
+    // The screen, bytes that are not UTF-8 are printed as replacement characters
+    Terminal,
+    // Read by the next command of a pipeline
+    Pipe(Vec<u8>),
+    // File opened for `> path` or `>> path`, closed by the shell once the command is done
+    File(FileHandle),
+}
+
+impl Stdout {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Stdout::Terminal)
+    }
+
+    pub fn write(&mut self, fs: &mut FileSystem, data: &[u8]) -> Result<(), String> {
+        match self {
+            Stdout::Terminal => print!("{}", String::from_utf8_lossy(data)),
+            Stdout::Pipe(buffer) => buffer.extend_from_slice(data),
+            Stdout::File(handle) => {
+                fs.write(*handle, data)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The standard streams of a running command
+pub struct Streams {
+    pub stdin: Stdin,
+    pub stdout: Stdout,
+}
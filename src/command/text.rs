@@ -0,0 +1,118 @@
+use super::registry::{usage_error, Builtin, Context};
+use super::split_flags;
+use super::stream::CHUNK_SIZE;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Commands printing and filtering text, the ones pipelines are made of
+pub const COMMANDS: &[Builtin] = &[
+    Builtin {
+        name: "echo",
+        usage: "[text]...",
+        summary: "Print the arguments",
+        run: echo,
+    },
+    Builtin {
+        name: "cat",
+        usage: "<path>...",
+        summary: "Print files, or the standard input without paths",
+        run: cat,
+    },
+    Builtin {
+        name: "grep",
+        usage: "[-i] [-v] <text> [path]...",
+        summary: "Print the lines containing a text, -v those that do not",
+        run: grep,
+    },
+];
+
+fn echo(parts: &[&str], _context: Context) -> Result<String, String> {
+    let mut text = parts[1..].join(" ");
+    text.push('\n');
+    Ok(text)
+}
+
+fn cat(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context {
+        streams,
+        fs,
+        session,
+    } = context;
+    if parts.len() < 2 && streams.stdin.is_terminal() {
+        return Err(usage_error(parts[0]));
+    }
+    // Without paths the standard input is copied
+    if parts.len() < 2 {
+        let content = streams.stdin.read_to_end(fs)?;
+        streams.stdout.write(fs, &content)?;
+    }
+    // Bytes are passed on unchanged, so binary files can be redirected to files and devices.
+    // Reads stop at the size, which keeps endless devices like `/dev/zero` from never ending.
+    for path in &parts[1..] {
+        let path = session.resolve(path);
+        let size = fs.size(&path)?;
+        let mut offset = 0;
+        while offset < size {
+            let chunk = fs.read_at(&path, offset, CHUNK_SIZE.min(size - offset))?;
+            if chunk.is_empty() {
+                break;
+            }
+            streams.stdout.write(fs, &chunk)?;
+            offset += chunk.len();
+        }
+    }
+    Ok(String::new())
+}
+
+fn grep(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context {
+        streams,
+        fs,
+        session,
+    } = context;
+    let (flags, operands) = split_flags(&parts[1..], "iv")?;
+    let (pattern, paths) = match operands.split_first() {
+        Some((pattern, paths)) if !paths.is_empty() || !streams.stdin.is_terminal() => {
+            (*pattern, paths)
+        }
+        _ => return Err(usage_error(parts[0])),
+    };
+
+    // Name and content of every input, the standard input has no name
+    let mut inputs = Vec::new();
+    if paths.is_empty() {
+        inputs.push(("", streams.stdin.read_to_end(fs)?));
+    }
+    for path in paths {
+        inputs.push((path, fs.read_file(&session.resolve(path))?));
+    }
+
+    let ignore_case = flags.contains(&'i');
+    let pattern = if ignore_case {
+        pattern.to_lowercase()
+    } else {
+        String::from(pattern)
+    };
+    let mut output = String::new();
+    for (name, content) in inputs {
+        for line in String::from_utf8_lossy(&content).lines() {
+            let found = if ignore_case {
+                line.to_lowercase().contains(&pattern)
+            } else {
+                line.contains(&pattern)
+            };
+            if found == flags.contains(&'v') {
+                continue;
+            }
+
+            // Lines are only labeled with their file when there are several
+            if paths.len() > 1 {
+                output.push_str(name);
+                output.push(':');
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,59 @@
+use super::registry::{usage_error, Builtin, Context};
+use super::split_flags;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Commands starting and stopping the watches whose events the shell prints
+pub const COMMANDS: &[Builtin] = &[
+    Builtin {
+        name: "watch",
+        usage: "[-r] [path]",
+        summary: "Report changes to a path, -r also below it, or list the watches",
+        run: watch,
+    },
+    Builtin {
+        name: "unwatch",
+        usage: "<number>",
+        summary: "Stop a watch started with watch",
+        run: unwatch,
+    },
+];
+
+fn watch(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    let (flags, operands) = split_flags(&parts[1..], "r")?;
+    match operands.as_slice() {
+        [] => {
+            let lines: Vec<String> = fs
+                .watches()
+                .filter(|(id, _)| session.watches.contains(id))
+                .map(|(id, watch)| {
+                    let scope = if watch.recursive { " and below" } else { "" };
+                    format!("{:>3}  {}{}", id.number(), watch.path, scope)
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        [path] => {
+            let id = fs.watch(&session.resolve(path), flags.contains(&'r'))?;
+            session.watches.push(id);
+            Ok(format!("Watching '{}' as {}", path, id.number()))
+        }
+        _ => Err(usage_error(parts[0])),
+    }
+}
+
+fn unwatch(parts: &[&str], context: Context) -> Result<String, String> {
+    let Context { fs, session, .. } = context;
+    if parts.len() != 2 {
+        return Err(usage_error(parts[0]));
+    }
+    let position = session
+        .watches
+        .iter()
+        .position(|id| parts[1].parse() == Ok(id.number()))
+        .ok_or_else(|| format!("No watch numbered '{}'", parts[1]))?;
+    fs.unwatch(session.watches.remove(position))?;
+    Ok(format!("Stopped watch {}", parts[1]))
+}
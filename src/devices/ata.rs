@@ -0,0 +1,378 @@
+use super::block::{self, BlockDevice};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+
+pub const SECTOR_SIZE: usize = 512;
+
+// Register offsets from the I/O base
+const DATA: u16 = 0;
+const SECTOR_COUNT: u16 = 2;
+const LBA_LOW: u16 = 3;
+const LBA_MID: u16 = 4;
+const LBA_HIGH: u16 = 5;
+const DRIVE_SELECT: u16 = 6;
+// Status when read, command when written
+const COMMAND: u16 = 7;
+
+const COMMAND_READ_SECTORS: u8 = 0x20;
+const COMMAND_READ_SECTORS_EXT: u8 = 0x24;
+const COMMAND_WRITE_SECTORS: u8 = 0x30;
+const COMMAND_WRITE_SECTORS_EXT: u8 = 0x34;
+const COMMAND_CACHE_FLUSH: u8 = 0xE7;
+const COMMAND_CACHE_FLUSH_EXT: u8 = 0xEA;
+const COMMAND_IDENTIFY: u8 = 0xEC;
+
+const STATUS_ERROR: u8 = 0x01;
+const STATUS_DATA_REQUEST: u8 = 0x08;
+const STATUS_DRIVE_FAULT: u8 = 0x20;
+const STATUS_BUSY: u8 = 0x80;
+
+// Polls of the status register before a drive is considered dead
+const TIMEOUT: usize = 1_000_000;
+
+// Sectors reachable with 28-bit LBA and the most sectors a command of either kind can transfer,
+// a count of 0 means the maximum
+const LBA28_LIMIT: u64 = 1 << 28;
+const LBA28_MAX_SECTORS: usize = 256;
+const LBA48_MAX_SECTORS: usize = 65_536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Primary,
+    Secondary,
+}
+
+impl Channel {
+    fn io_base(self) -> u16 {
+        match self {
+            Channel::Primary => 0x1F0,
+            Channel::Secondary => 0x170,
+        }
+    }
+
+    fn control_base(self) -> u16 {
+        match self {
+            Channel::Primary => 0x3F6,
+            Channel::Secondary => 0x376,
+        }
+    }
+}
+
+// A drive on one of the two legacy ATA channels driven with programmed I/O
+//
+// Completion is polled, the drive's interrupt is disabled since the IDT has no handler for it.
+// Transfers use 28-bit LBA when the addressed range allows it and 48-bit LBA otherwise.
+pub struct AtaDrive {
+    channel: Channel,
+    slave: bool,
+    sectors: u64,
+    lba48: bool,
+    model: String,
+}
+
+// Probes the master and slave of both channels and registers every drive found as a block
+// device, named `hda` to `hdd` in that order like Linux does
+pub fn init() {
+    let positions = [
+        ("hda", Channel::Primary, false),
+        ("hdb", Channel::Primary, true),
+        ("hdc", Channel::Secondary, false),
+        ("hdd", Channel::Secondary, true),
+    ];
+
+    for (name, channel, slave) in positions {
+        if let Some(drive) = AtaDrive::probe(channel, slave) {
+            block::register(name, Arc::new(Mutex::new(drive)));
+        }
+    }
+}
+
+impl AtaDrive {
+    // Identifies the drive attached to `channel` as master or slave, `None` when there is no ATA
+    // drive there
+    pub fn probe(channel: Channel, slave: bool) -> Option<Self> {
+        let mut drive = Self {
+            channel,
+            slave,
+            sectors: 0,
+            lba48: false,
+            model: String::new(),
+        };
+
+        // Set nIEN so the drive never raises its IRQ
+        unsafe { Port::<u8>::new(channel.control_base()).write(0x02) };
+
+        drive.identify()?;
+        Some(drive)
+    }
+
+    // Validates a transfer of `len` bytes at `lba` and returns the sector after its end
+    fn check_range(&self, lba: u64, len: usize) -> Result<u64, String> {
+        if !len.is_multiple_of(SECTOR_SIZE) {
+            return Err(format!(
+                "Transfer of {} bytes is not a whole number of sectors",
+                len
+            ));
+        }
+
+        let end = lba + (len / SECTOR_SIZE) as u64;
+        if end > self.sectors {
+            return Err(format!(
+                "Sectors {}..{} are past the end of the disk ({} sectors)",
+                lba, end, self.sectors
+            ));
+        }
+
+        Ok(end)
+    }
+
+    // Sends IDENTIFY DEVICE and fills in the size and description of the drive
+    fn identify(&mut self) -> Option<()> {
+        self.select(0x00);
+        unsafe {
+            self.register(SECTOR_COUNT).write(0);
+            self.register(LBA_LOW).write(0);
+            self.register(LBA_MID).write(0);
+            self.register(LBA_HIGH).write(0);
+            self.register(COMMAND).write(COMMAND_IDENTIFY);
+        }
+
+        // A status of 0 means nothing is attached, 0xFF a floating bus without any drives
+        let status = self.status();
+        if status == 0 || status == 0xFF {
+            return None;
+        }
+        self.wait_until_ready().ok()?;
+
+        // ATAPI and SATA devices set the LBA registers instead of answering IDENTIFY
+        let signature = unsafe {
+            (
+                self.register(LBA_MID).read(),
+                self.register(LBA_HIGH).read(),
+            )
+        };
+        if signature != (0, 0) {
+            return None;
+        }
+        self.wait_for_data().ok()?;
+
+        let mut identity = [0u16; 256];
+        for word in identity.iter_mut() {
+            *word = unsafe { self.port(DATA).read() };
+        }
+
+        // Word 83 bit 10 flags 48-bit LBA, whose sector count is in words 100-103, the 28-bit
+        // count is in words 60-61
+        self.lba48 = identity[83] & (1 << 10) != 0;
+        let words = if self.lba48 {
+            &identity[100..104]
+        } else {
+            &identity[60..62]
+        };
+        self.sectors = words
+            .iter()
+            .rev()
+            .fold(0, |sectors, &word| (sectors << 16) | u64::from(word));
+        self.model = identity_string(&identity[27..47]);
+
+        (self.sectors > 0).then_some(())
+    }
+
+    // Starts a read or write of `count` sectors at `lba`
+    fn start_command(
+        &mut self,
+        lba: u64,
+        count: usize,
+        lba48: bool,
+        command: u8,
+    ) -> Result<(), String> {
+        self.wait_until_ready()?;
+
+        if lba48 {
+            self.select(0x40);
+            unsafe {
+                // High bytes first, each register keeps the previous write as its upper half
+                self.register(SECTOR_COUNT).write((count >> 8) as u8);
+                self.register(LBA_LOW).write((lba >> 24) as u8);
+                self.register(LBA_MID).write((lba >> 32) as u8);
+                self.register(LBA_HIGH).write((lba >> 40) as u8);
+            }
+        } else {
+            self.select(0x40 | ((lba >> 24) & 0x0F) as u8);
+        }
+
+        unsafe {
+            // The maximum count wraps around to 0, which the drive reads as the maximum
+            self.register(SECTOR_COUNT).write(count as u8);
+            self.register(LBA_LOW).write(lba as u8);
+            self.register(LBA_MID).write((lba >> 8) as u8);
+            self.register(LBA_HIGH).write((lba >> 16) as u8);
+            self.register(COMMAND).write(command);
+        }
+
+        Ok(())
+    }
+
+    // Selects the drive, `flags` holds the LBA bit and for 28-bit commands bits 24-27 of the
+    // address
+    fn select(&self, flags: u8) {
+        let drive = if self.slave { 0xB0 } else { 0xA0 };
+        unsafe { self.register(DRIVE_SELECT).write(drive | flags) };
+
+        // The drive needs 400ns to switch, each read of the alternate status takes about 100ns
+        for _ in 0..4 {
+            self.alternate_status();
+        }
+    }
+
+    fn wait_until_ready(&self) -> Result<(), String> {
+        for _ in 0..TIMEOUT {
+            let status = self.status();
+            if status & STATUS_BUSY == 0 {
+                return self.check_error(status);
+            }
+        }
+
+        Err(String::from("Disk timed out"))
+    }
+
+    fn wait_for_data(&self) -> Result<(), String> {
+        for _ in 0..TIMEOUT {
+            let status = self.status();
+            if status & STATUS_BUSY != 0 {
+                continue;
+            }
+            self.check_error(status)?;
+            if status & STATUS_DATA_REQUEST != 0 {
+                return Ok(());
+            }
+        }
+
+        Err(String::from("Disk timed out"))
+    }
+
+    fn check_error(&self, status: u8) -> Result<(), String> {
+        if status & (STATUS_ERROR | STATUS_DRIVE_FAULT) != 0 {
+            return Err(format!("Disk reported an error (status {:#04x})", status));
+        }
+
+        Ok(())
+    }
+
+    fn status(&self) -> u8 {
+        unsafe { self.register(COMMAND).read() }
+    }
+
+    fn alternate_status(&self) -> u8 {
+        unsafe { Port::<u8>::new(self.channel.control_base()).read() }
+    }
+
+    fn register(&self, offset: u16) -> Port<u8> {
+        Port::new(self.channel.io_base() + offset)
+    }
+
+    fn port(&self, offset: u16) -> Port<u16> {
+        Port::new(self.channel.io_base() + offset)
+    }
+}
+
+impl BlockDevice for AtaDrive {
+    fn sector_size(&self) -> usize {
+        SECTOR_SIZE
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.sectors
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn read_sectors(&mut self, lba: u64, buffer: &mut [u8]) -> Result<(), String> {
+        let lba48 = self.check_range(lba, buffer.len())? > LBA28_LIMIT;
+        let (max_sectors, command) = if lba48 {
+            (LBA48_MAX_SECTORS, COMMAND_READ_SECTORS_EXT)
+        } else {
+            (LBA28_MAX_SECTORS, COMMAND_READ_SECTORS)
+        };
+
+        let mut lba = lba;
+        for chunk in buffer.chunks_mut(max_sectors * SECTOR_SIZE) {
+            let count = chunk.len() / SECTOR_SIZE;
+            self.start_command(lba, count, lba48, command)?;
+
+            for sector in chunk.chunks_exact_mut(SECTOR_SIZE) {
+                self.wait_for_data()?;
+                for word in sector.chunks_exact_mut(2) {
+                    let value: u16 = unsafe { self.port(DATA).read() };
+                    word.copy_from_slice(&value.to_le_bytes());
+                }
+            }
+
+            lba += count as u64;
+        }
+
+        Ok(())
+    }
+
+    // Flushes the drive's write cache once the data is transferred
+    fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), String> {
+        let lba48 = self.check_range(lba, data.len())? > LBA28_LIMIT;
+        let (max_sectors, command, flush) = if lba48 {
+            (
+                LBA48_MAX_SECTORS,
+                COMMAND_WRITE_SECTORS_EXT,
+                COMMAND_CACHE_FLUSH_EXT,
+            )
+        } else {
+            (
+                LBA28_MAX_SECTORS,
+                COMMAND_WRITE_SECTORS,
+                COMMAND_CACHE_FLUSH,
+            )
+        };
+
+        let mut lba = lba;
+        for chunk in data.chunks(max_sectors * SECTOR_SIZE) {
+            let count = chunk.len() / SECTOR_SIZE;
+            self.start_command(lba, count, lba48, command)?;
+
+            for sector in chunk.chunks_exact(SECTOR_SIZE) {
+                self.wait_for_data()?;
+                for word in sector.chunks_exact(2) {
+                    let value = u16::from_le_bytes([word[0], word[1]]);
+                    unsafe { self.port(DATA).write(value) };
+                }
+            }
+
+            lba += count as u64;
+        }
+
+        // The drive is still busy committing the last sector, a command written now is lost
+        self.wait_until_ready()?;
+        unsafe { self.register(COMMAND).write(flush) };
+        self.wait_until_ready()
+    }
+}
+
+// Decodes an IDENTIFY string, which holds two ASCII characters per word with the first one in
+// the high byte and is padded with spaces
+fn identity_string(words: &[u16]) -> String {
+    let text: String = words
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .map(|byte| {
+            if byte.is_ascii_graphic() {
+                char::from(byte)
+            } else {
+                ' '
+            }
+        })
+        .collect();
+
+    String::from(text.trim())
+}
@@ -0,0 +1,115 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+// A disk-like device read and written in whole sectors
+pub trait BlockDevice: Send {
+    // Bytes per sector
+    fn sector_size(&self) -> usize;
+
+    fn sector_count(&self) -> u64;
+
+    // Human readable description, e.g. the drive's model
+    fn model(&self) -> &str;
+
+    // Reads whole sectors starting at `lba` into `buffer`, whose length must be a multiple of the
+    // sector size
+    fn read_sectors(&mut self, lba: u64, buffer: &mut [u8]) -> Result<(), String>;
+
+    // Writes whole sectors starting at `lba`, the data is on the device once this returns
+    fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), String>;
+
+    fn size(&self) -> u64 {
+        self.sector_count() * self.sector_size() as u64
+    }
+}
+
+// Block devices are shared between the filesystems and commands using them
+pub type SharedBlockDevice = Arc<Mutex<dyn BlockDevice>>;
+
+// Block devices found at boot by name, e.g. `hda`, in the order they were registered
+static DEVICES: Mutex<Vec<(String, SharedBlockDevice)>> = Mutex::new(Vec::new());
+
+pub fn register(name: &str, device: SharedBlockDevice) {
+    DEVICES.lock().push((String::from(name), device));
+}
+
+pub fn get(name: &str) -> Option<SharedBlockDevice> {
+    DEVICES
+        .lock()
+        .iter()
+        .find(|(device_name, _)| device_name == name)
+        .map(|(_, device)| device.clone())
+}
+
+pub fn devices() -> Vec<(String, SharedBlockDevice)> {
+    DEVICES.lock().clone()
+}
+
+// Reads `buffer.len()` bytes starting at byte `offset`, which need not be sector aligned
+pub fn read_bytes(
+    device: &SharedBlockDevice,
+    offset: u64,
+    buffer: &mut [u8],
+) -> Result<(), String> {
+    let mut device = device.lock();
+    let sector_size = device.sector_size() as u64;
+
+    let first = offset / sector_size;
+    let last = (offset + buffer.len() as u64).div_ceil(sector_size);
+    let start = (offset - first * sector_size) as usize;
+
+    let mut sectors = vec![0; ((last - first) * sector_size) as usize];
+    device.read_sectors(first, &mut sectors)?;
+    buffer.copy_from_slice(&sectors[start..start + buffer.len()]);
+
+    Ok(())
+}
+
+// Writes `data` at byte `offset`, sectors only partly covered are read and merged first
+pub fn write_bytes(device: &SharedBlockDevice, offset: u64, data: &[u8]) -> Result<(), String> {
+    let mut device = device.lock();
+    let sector_size = device.sector_size() as u64;
+
+    let first = offset / sector_size;
+    let last = (offset + data.len() as u64).div_ceil(sector_size);
+    let start = (offset - first * sector_size) as usize;
+
+    let mut sectors = vec![0; ((last - first) * sector_size) as usize];
+    if start != 0 || data.len() != sectors.len() {
+        device.read_sectors(first, &mut sectors)?;
+    }
+    sectors[start..start + data.len()].copy_from_slice(data);
+
+    device.write_sectors(first, &sectors)
+}
+
+// A primary partition listed in a master boot record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Partition {
+    // Partition type, e.g. 0x0C for FAT32 or 0x83 for Linux
+    pub kind: u8,
+    // First sector and length in sectors
+    pub start: u64,
+    pub sectors: u64,
+}
+
+// Returns the used entries of the MBR partition table in `sector`, the first sector of a disk,
+// or nothing when it does not end with the boot signature
+pub fn mbr_partitions(sector: &[u8]) -> Vec<Partition> {
+    if sector.len() < 512 || sector[510..512] != [0x55, 0xAA] {
+        return Vec::new();
+    }
+
+    sector[446..510]
+        .chunks_exact(16)
+        .map(|entry| Partition {
+            kind: entry[4],
+            start: u64::from(u32::from_le_bytes(entry[8..12].try_into().unwrap())),
+            sectors: u64::from(u32::from_le_bytes(entry[12..16].try_into().unwrap())),
+        })
+        .filter(|partition| partition.kind != 0 && partition.start != 0)
+        .collect()
+}
@@ -1,10 +1,10 @@
-use crate::devices::console::CONSOLE;
+use crate::devices::console::{Console, CONSOLE};
 use crate::println;
 use alloc::{string::String, vec::Vec};
 use lazy_static::lazy_static;
 use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1};
 use spin::Mutex;
-use x86_64::instructions::{hlt, port::Port};
+use x86_64::instructions::{hlt, interrupts, port::Port};
 
 lazy_static! {
     pub static ref KEYBOARD: Mutex<Keyboard<layouts::Us104Key, ScancodeSet1>> =
@@ -70,11 +70,14 @@ pub fn read_char() -> char {
     }
 }
 
-pub fn read_line() -> String {
+// Prints `prompt` followed by `> ` and blocks until a full line has been typed
+//
+// `idle` runs every time the CPU wakes up in between, the lines it returns are printed above the
+// prompt, which is drawn again with what was typed so far.
+pub fn read_line(prompt: &str, mut idle: impl FnMut() -> Vec<String>) -> String {
     {
         let mut console = CONSOLE.lock();
-        console.print_char_and_move_cursor('>');
-        console.print_char_and_move_cursor(' ');
+        print_prompt(&mut console, prompt);
     }
 
     loop {
@@ -95,6 +98,31 @@ pub fn read_line() -> String {
             return String::from(trimmed_result);
         }
 
+        let lines = idle();
+        if !lines.is_empty() {
+            // The keyboard interrupt locks the buffer and the console too
+            interrupts::without_interrupts(|| {
+                let buffer = CHAR_BUFFER.lock();
+                let mut console = CONSOLE.lock();
+                console.print_char_and_move_cursor('\n');
+                for line in &lines {
+                    for character in line.chars().chain(['\n']) {
+                        console.print_char_and_move_cursor(character);
+                    }
+                }
+                print_prompt(&mut console, prompt);
+                for &character in buffer.iter() {
+                    console.print_char_and_move_cursor(character);
+                }
+            });
+        }
+
         hlt();
     }
 }
+
+fn print_prompt(console: &mut Console, prompt: &str) {
+    for character in prompt.chars().chain(['>', ' ']) {
+        console.print_char_and_move_cursor(character);
+    }
+}
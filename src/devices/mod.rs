@@ -1,3 +1,5 @@
+pub mod ata;
+pub mod block;
 pub mod console;
 pub mod cursor;
 pub mod keyboard;
@@ -106,11 +106,8 @@ impl Writer {
     }
 
     pub fn write_string(&mut self, s: &str) {
-        for byte in s.bytes() {
-            match byte {
-                0x20..=0x7e | b'\n' => self.write_byte(byte),
-                _ => self.write_byte(0xfe),
-            }
+        for character in s.chars() {
+            self.write_byte(to_code_page_437(character));
         }
     }
 
@@ -128,6 +125,18 @@ impl Writer {
     }
 }
 
+// Maps a character to the VGA font (code page 437), unsupported characters become a block
+fn to_code_page_437(character: char) -> u8 {
+    match character {
+        ' '..='~' | '\n' => character as u8,
+        '│' => 0xb3,
+        '├' => 0xc3,
+        '└' => 0xc0,
+        '─' => 0xc4,
+        _ => 0xfe,
+    }
+}
+
 impl fmt::Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.write_string(s);
@@ -1,214 +1,844 @@
 extern crate alloc;
 
+use crate::devices::block::SharedBlockDevice;
+use access::Credentials;
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
+use cfs::CfsFs;
+use descriptor::{DescriptorTable, FileHandle, OpenFile, OpenMode, SeekFrom};
+#[cfg(target_os = "none")]
+use devfs::DevFs;
+use ext2::Ext2Fs;
+use fat::FatFs;
+use mount::{Mount, MountTable};
+#[cfg(target_os = "none")]
+use procfs::ProcFs;
+use ramfs::RamFs;
+use vfs::{Attributes, DirEntry, Metadata, NodeKind, Vfs};
+use walk::{Walk, WalkEntry};
+use watch::{Event, EventKind, Watch, WatchId, WatchTable};
+
+pub mod access;
+pub mod cfs;
+pub mod descriptor;
+// Backed by the hardware and the kernel's own state, so left out of host builds
+#[cfg(target_os = "none")]
+pub mod devfs;
+pub mod ext2;
+pub mod fat;
+pub mod fsck;
+pub mod image;
+pub mod mount;
+#[cfg(target_os = "none")]
+pub mod procfs;
+pub mod ramfs;
+pub mod tar;
+pub mod vfs;
+pub mod walk;
+pub mod watch;
+
+// Resolves `path` against `cwd` into its normalized components
+//
+// Absolute paths ignore `cwd`, empty components and `.` are dropped and `..` walks up one level
+// (stopping at the root), so the result never contains `.` or `..`.
+pub fn resolve_path(cwd: &str, path: &str) -> Vec<String> {
+    let mut components: Vec<String> = Vec::new();
+
+    let base = if path.starts_with('/') { "" } else { cwd };
+
+    for part in base.split('/').chain(path.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(String::from(part)),
+        }
+    }
+
+    components
+}
+
+// Resolves `path` against `cwd` and returns it as an absolute path string
+pub fn normalize_path(cwd: &str, path: &str) -> String {
+    join_components(&resolve_path(cwd, path))
+}
+
+// Resolves `path` against `cwd` and splits it into the absolute parent path and the leaf name
+pub fn split_path(cwd: &str, path: &str) -> Result<(String, String), String> {
+    let mut components = resolve_path(cwd, path);
+
+    let leaf = components
+        .pop()
+        .ok_or_else(|| format!("'{}' does not name a file or directory", path))?;
+
+    Ok((join_components(&components), leaf))
+}
+
+fn join_components(components: &[String]) -> String {
+    if components.is_empty() {
+        return String::from("/");
+    }
 
-pub enum FSNode {
-    File { name: String, content: String },
-    Directory { name: String, children: Vec<FSNode> },
+    let mut path = String::new();
+    for component in components {
+        path.push('/');
+        path.push_str(component);
+    }
+    path
+}
+
+// Creates an instance of the filesystem type named `fs_type`, as accepted by `mount`
+//
+// `ramfs` starts out empty, `proc` shows kernel state and `dev` the devices. `fat`, the
+// read-only `ext2` and `cfs` use the volume already on `device`, `cfs` volumes are made with
+// `cfs::format`.
+pub fn new_backend(
+    fs_type: &str,
+    device: Option<SharedBlockDevice>,
+) -> Result<Box<dyn Vfs>, String> {
+    match (fs_type, device) {
+        ("ramfs", None) => Ok(Box::new(RamFs::new())),
+        #[cfg(target_os = "none")]
+        ("proc", None) => Ok(Box::new(ProcFs::new())),
+        #[cfg(target_os = "none")]
+        ("dev", None) => Ok(Box::new(DevFs::new())),
+        ("fat", Some(device)) => Ok(Box::new(FatFs::new(device)?)),
+        ("ext2", Some(device)) => Ok(Box::new(Ext2Fs::new(device)?)),
+        ("cfs", Some(device)) => Ok(Box::new(CfsFs::new(device)?)),
+        ("ramfs" | "proc" | "dev", Some(_)) => Err(format!("{} does not use a device", fs_type)),
+        ("fat" | "ext2" | "cfs", None) => Err(format!("{} needs a device to mount", fs_type)),
+        _ => Err(format!("Unknown filesystem type '{}'", fs_type)),
+    }
+}
+
+// Symbolic links followed while resolving a single path before giving up on a loop
+pub const MAX_SYMLINK_HOPS: usize = 40;
+
+// What to do when the destination of a move or copy already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    Fail,
+    Skip,
+    Overwrite,
 }
 
+// The directory tree seen by the rest of the kernel
+//
+// Paths passed to `FileSystem` are resolved against `/`, symbolic links in them are followed,
+// then they are routed through the mount table to the backend holding them and handed over
+// relative to that backend's root.
+//
+// Operations are checked against the permission bits of the nodes they use for the current
+// credentials, which start out as root: every directory in a path needs search permission,
+// reading and writing a node needs read or write permission on it and adding or removing a
+// directory entry needs write and search permission on the directory.
 pub struct FileSystem {
-    root: FSNode,
+    mounts: MountTable,
+    descriptors: DescriptorTable,
+    watches: WatchTable,
+    credentials: Credentials,
 }
 
 impl FileSystem {
     pub fn new() -> Self {
+        Self::with_root(Box::new(RamFs::new()))
+    }
+
+    // Starts from an existing root filesystem instead of an empty `ramfs`, e.g. one restored
+    // from disk
+    pub fn with_root(root: Box<dyn Vfs>) -> Self {
         Self {
-            root: FSNode::Directory {
-                name: String::from("root"),
-                children: Vec::new(),
-            },
+            mounts: MountTable::new(root),
+            descriptors: DescriptorTable::new(),
+            watches: WatchTable::new(),
+            credentials: Credentials::root(),
         }
     }
 
-    pub fn create_file(&mut self, path: &str, name: &str, content: &str) -> Result<(), String> {
-        let parent_dir = self.find_directory_mut(path)?;
+    // The filesystem mounted at `/`, without anything mounted on top of it
+    pub fn root(&self) -> &dyn Vfs {
+        self.mounts.resolve("/").0
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
 
-        if let FSNode::Directory { children, .. } = parent_dir {
-            if children.iter().any(|node| match node {
-                FSNode::File { name: n, .. } => n == name,
-                _ => false,
-            }) {
-                return Err(format!("A file with the name '{}' already exists", name));
+    // Switches the user operations are done as and returns the previous credentials
+    pub fn set_credentials(&mut self, credentials: Credentials) -> Credentials {
+        core::mem::replace(&mut self.credentials, credentials)
+    }
+
+    // Fails unless every directory above the canonical `path` may be searched
+    fn check_search(&self, path: &str) -> Result<(), String> {
+        if self.credentials.is_root() {
+            return Ok(());
+        }
+
+        let components = resolve_path("/", path);
+        for depth in 0..components.len() {
+            let (backend, relative) = self.mounts.resolve(&join_components(&components[..depth]));
+            if !self
+                .credentials
+                .may(&backend.stat(&relative)?, access::EXECUTE)
+            {
+                return Err(format!("'{}': Permission denied", path));
             }
+        }
 
-            children.push(FSNode::File {
-                name: String::from(name),
-                content: String::from(content),
-            });
+        Ok(())
+    }
 
-            Ok(())
-        } else {
-            Err(format!("'{}' is not a directory", path))
+    // Fails unless the node at the canonical `path` grants `access` and can be reached
+    fn check_access(&self, path: &str, access: u16) -> Result<(), String> {
+        self.check_search(path)?;
+        if self.credentials.is_root() {
+            return Ok(());
         }
+
+        let (backend, relative) = self.mounts.resolve(path);
+        if !self.credentials.may(&backend.stat(&relative)?, access) {
+            return Err(format!("'{}': Permission denied", path));
+        }
+
+        Ok(())
     }
 
-    pub fn create_directory(&mut self, path: &str, name: &str) -> Result<(), String> {
-        // Locate the parent directory where the new directory should be created
-        let parent_dir = self.find_directory_mut(path)?;
+    // Fails unless an entry can be added to or removed from the directory holding `path`
+    fn check_parent(&self, path: &str) -> Result<(), String> {
+        let (parent, _) = split_path("/", path)?;
+        self.check_access(&parent, access::WRITE | access::EXECUTE)
+    }
 
-        if let FSNode::Directory { children, .. } = parent_dir {
-            // Check if a file or directory with the same name already exists
-            if children.iter().any(|node| match node {
-                FSNode::Directory { name: n, .. } => n == name,
-                _ => false,
-            }) {
-                return Err(format!(
-                    "A directory with the name '{}' already exists",
-                    name
-                ));
+    // Hands the node just created at `path` to the current user, backends that do not store
+    // owners, like FAT, keep their own
+    fn take_ownership(&mut self, path: &str) {
+        if self.credentials.is_root() {
+            return;
+        }
+
+        let attributes = Attributes {
+            uid: Some(self.credentials.uid),
+            gid: Some(self.credentials.gid),
+            ..Attributes::default()
+        };
+        let (backend, relative) = self.mounts.resolve_mut(path);
+        let _ = backend.set_attributes(&relative, &attributes);
+    }
+
+    // Resolves symbolic links in `path` and returns the absolute path of the node it names
+    //
+    // Links in the middle of the path are always followed, the last component only when
+    // `follow_last` is set. Components that do not exist are kept as they are, so the result can
+    // name a node that is about to be created. `..` is resolved lexically before any link is.
+    fn canonicalize(&self, path: &str, follow_last: bool) -> Result<String, String> {
+        // Components still to walk, the next one is at the end
+        let mut pending = resolve_path("/", path);
+        pending.reverse();
+        let mut resolved = Vec::new();
+        let mut hops = 0;
+
+        while let Some(component) = pending.pop() {
+            resolved.push(component);
+            if pending.is_empty() && !follow_last {
+                break;
+            }
+
+            let (backend, relative) = self.mounts.resolve(&join_components(&resolved));
+            if backend.lookup(&relative) != Ok(NodeKind::Symlink) {
+                continue;
+            }
+
+            hops += 1;
+            if hops > MAX_SYMLINK_HOPS {
+                return Err(format!("'{}': Too many levels of symbolic links", path));
             }
 
-            // Add the new directory to the parent directory's children
-            children.push(FSNode::Directory {
-                name: String::from(name),
-                children: Vec::new(), // New directory starts empty
-            });
+            let target = backend.readlink(&relative)?;
+            resolved.pop();
+            let parent = join_components(&resolved);
+
+            // The target replaces the link and is walked again, it may contain links itself
+            pending.extend(resolve_path(&parent, &target).into_iter().rev());
+            resolved.clear();
+        }
+
+        Ok(join_components(&resolved))
+    }
+
+    // Attaches `backend` at the existing directory `path`, hiding what was there until unmounted
+    pub fn mount(&mut self, path: &str, backend: Box<dyn Vfs>) -> Result<(), String> {
+        if !self.credentials.is_root() {
+            return Err(String::from("Only root can mount filesystems"));
+        }
+        let path = self.canonicalize(path, true)?;
+
+        if self.lookup(&path)? != NodeKind::Directory {
+            return Err(format!("'{}' is not a directory", path));
+        }
+
+        self.mounts.mount(path, backend)
+    }
+
+    pub fn unmount(&mut self, path: &str) -> Result<Box<dyn Vfs>, String> {
+        if !self.credentials.is_root() {
+            return Err(String::from("Only root can unmount filesystems"));
+        }
+        let path = self.canonicalize(path, true)?;
+
+        if self
+            .descriptors
+            .iter()
+            .any(|file| mount::relative_to(&path, &file.path).is_some())
+        {
+            return Err(format!("'{}' has open files", path));
+        }
+
+        self.mounts.unmount(&path)
+    }
+
+    pub fn mounts(&self) -> impl Iterator<Item = &Mount> {
+        self.mounts.iter()
+    }
+
+    // Whether a mounted filesystem is stored on `device`, possibly in one of its partitions
+    pub fn uses_device(&self, device: &SharedBlockDevice) -> bool {
+        self.mounts().any(|mount| {
+            mount
+                .backend
+                .device()
+                .is_some_and(|used| Arc::ptr_eq(used, device))
+        })
+    }
 
-            Ok(())
-        } else {
-            Err(format!("'{}' is not a directory", path))
+    // Checks the filesystem holding `path` for broken invariants, see `fsck`, and returns the
+    // problems found
+    pub fn fsck(&self, path: &str) -> Result<Vec<String>, String> {
+        if !self.credentials.is_root() {
+            return Err(String::from("Only root can check filesystems"));
         }
+        let path = self.canonicalize(path, true)?;
+        self.lookup(&path)?;
+
+        let mount = self.mounts.find_mount(&path);
+        Ok(fsck::check(mount.backend.as_ref(), &mount.path))
+    }
+
+    // Starts queueing the changes to the node at `path` and its direct children, or to everything
+    // below it when `recursive` is set, see `Watch`
+    pub fn watch(&mut self, path: &str, recursive: bool) -> Result<WatchId, String> {
+        let path = self.canonicalize(path, true)?;
+        self.lookup(&path)?;
+        self.check_access(&path, access::READ)?;
+
+        self.watches.insert(path, recursive)
+    }
+
+    pub fn unwatch(&mut self, id: WatchId) -> Result<(), String> {
+        self.watches.remove(id).map(|_| ())
+    }
+
+    // Returns the changes seen by the watch since the last call, oldest first
+    pub fn read_events(&mut self, id: WatchId) -> Result<Vec<Event>, String> {
+        self.watches.take_events(id)
+    }
+
+    pub fn watches(&self) -> impl Iterator<Item = (WatchId, &Watch)> {
+        self.watches.iter()
     }
 
-    pub fn read_file(&self, path: &str, name: &str) -> Result<&str, String> {
-        let dir = self.find_directory(path)?;
+    pub fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        let path = self.canonicalize(path, true)?;
+        self.check_search(&path)?;
 
-        if let FSNode::Directory { children, .. } = dir {
-            if let Some(FSNode::File { content, .. }) = children
-                .iter()
-                .find(|node| matches!(node, FSNode::File{ name: n, ..} if n == name))
+        let (backend, relative) = self.mounts.resolve(&path);
+        backend.lookup(&relative)
+    }
+
+    pub fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let path = self.canonicalize(path, true)?;
+        self.check_search(&path)?;
+
+        let (backend, relative) = self.mounts.resolve(&path);
+        backend.stat(&relative)
+    }
+
+    // Like `stat` but describes a symbolic link at `path` itself rather than its target
+    pub fn lstat(&self, path: &str) -> Result<Metadata, String> {
+        let path = self.canonicalize(path, false)?;
+        self.check_search(&path)?;
+
+        let (backend, relative) = self.mounts.resolve(&path);
+        backend.stat(&relative)
+    }
+
+    // Changes the owner, permission bits or timestamps of the node at `path`
+    //
+    // Only root gives nodes away, the owner may change everything else and the group to one they
+    // are a member of.
+    pub fn set_attributes(&mut self, path: &str, attributes: &Attributes) -> Result<(), String> {
+        let path = self.canonicalize(path, true)?;
+        let metadata = self.stat(&path)?;
+
+        let credentials = &self.credentials;
+        if !credentials.is_root() {
+            if metadata.uid != credentials.uid {
+                return Err(format!("'{}': Operation not permitted", path));
+            }
+            if attributes.uid.is_some_and(|uid| uid != metadata.uid) {
+                return Err(format!("Only root can change the owner of '{}'", path));
+            }
+            if attributes
+                .gid
+                .is_some_and(|gid| gid != metadata.gid && !credentials.in_group(gid))
             {
-                Ok(content)
-            } else {
-                Err(format!("File '{}' not found in '{}'", name, path))
+                return Err(format!(
+                    "'{}' can only be given to a group you are a member of",
+                    path
+                ));
             }
-        } else {
-            Err(format!("Path '{}' is not a directory", path))
         }
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        backend.set_attributes(&relative, attributes)
+    }
+
+    pub fn create_file(&mut self, path: &str, content: &[u8]) -> Result<(), String> {
+        let path = self.canonicalize(path, false)?;
+        self.check_parent(&path)?;
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        backend.create(&relative, NodeKind::File)?;
+        backend.write(&relative, 0, content)?;
+        self.take_ownership(&path);
+
+        self.watches.notify(Event::new(EventKind::Create, &path));
+        Ok(())
+    }
+
+    pub fn create_directory(&mut self, path: &str) -> Result<(), String> {
+        let path = self.canonicalize(path, false)?;
+        self.check_parent(&path)?;
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        backend.create(&relative, NodeKind::Directory)?;
+        self.take_ownership(&path);
+
+        self.watches.notify(Event::new(EventKind::Create, &path));
+        Ok(())
+    }
+
+    // Creates a symbolic link at `path` pointing to `target`, which does not have to exist
+    pub fn symlink(&mut self, target: &str, path: &str) -> Result<(), String> {
+        if target.is_empty() {
+            return Err(String::from("Symbolic link target cannot be empty"));
+        }
+
+        let path = self.canonicalize(path, false)?;
+        self.check_parent(&path)?;
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        backend.symlink(target, &relative)?;
+        self.take_ownership(&path);
+
+        self.watches.notify(Event::new(EventKind::Create, &path));
+        Ok(())
+    }
+
+    // Returns the target of the symbolic link at `path` as it was given when creating it
+    pub fn readlink(&self, path: &str) -> Result<String, String> {
+        let path = self.canonicalize(path, false)?;
+        self.check_search(&path)?;
+
+        let (backend, relative) = self.mounts.resolve(&path);
+        backend.readlink(&relative)
+    }
+
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>, String> {
+        self.read_at(path, 0, self.size(path)?)
+    }
+
+    // Reads up to `len` bytes starting at `offset`, the result is shorter near the end of file
+    pub fn read_at(&self, path: &str, offset: usize, len: usize) -> Result<Vec<u8>, String> {
+        let path = self.canonicalize(path, true)?;
+        self.check_access(&path, access::READ)?;
+
+        let (backend, relative) = self.mounts.resolve(&path);
+
+        let mut buffer = vec![0; len];
+        let count = backend.read(&relative, offset, &mut buffer)?;
+        buffer.truncate(count);
+
+        Ok(buffer)
+    }
+
+    // Writes `data` at `offset`, growing the file and zero-filling any gap past the old end
+    pub fn write_at(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, String> {
+        let path = self.canonicalize(path, true)?;
+        self.check_access(&path, access::WRITE)?;
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        let written = backend.write(&relative, offset, data)?;
+
+        self.watches.notify(Event::new(EventKind::Modify, &path));
+        Ok(written)
+    }
+
+    pub fn append(&mut self, path: &str, data: &[u8]) -> Result<usize, String> {
+        let size = self.size(path)?;
+        self.write_at(path, size, data)
+    }
+
+    // Shrinks or zero-extends the file to exactly `len` bytes
+    pub fn truncate(&mut self, path: &str, len: usize) -> Result<(), String> {
+        let path = self.canonicalize(path, true)?;
+        self.check_access(&path, access::WRITE)?;
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        backend.truncate(&relative, len)?;
+
+        self.watches.notify(Event::new(EventKind::Modify, &path));
+        Ok(())
+    }
+
+    pub fn size(&self, path: &str) -> Result<usize, String> {
+        let metadata = self.stat(path)?;
+
+        if metadata.kind == NodeKind::Directory {
+            return Err(format!("'{}' is a directory", path));
+        }
+
+        Ok(metadata.size)
+    }
+
+    // Opens the file at `path` and returns a handle with its own cursor, see `OpenMode` for how
+    // missing and existing files are treated
+    //
+    // Handles refer to the file by path, so renaming or deleting an open file invalidates them.
+    pub fn open(&mut self, path: &str, mode: OpenMode) -> Result<FileHandle, String> {
+        // A link whose target is missing creates the target, like on Unix
+        let path = self.canonicalize(path, true)?;
+
+        match (mode, self.lookup(&path)) {
+            (_, Ok(NodeKind::Directory)) => return Err(format!("'{}' is a directory", path)),
+            (OpenMode::Read, Err(err)) => return Err(err),
+            (OpenMode::Write | OpenMode::Append, Err(_)) => self.create_file(&path, &[])?,
+            (OpenMode::Write, Ok(_)) => self.truncate(&path, 0)?,
+            (OpenMode::Read, Ok(_)) => self.check_access(&path, access::READ)?,
+            (OpenMode::Append, Ok(_)) => self.check_access(&path, access::WRITE)?,
+        }
+
+        self.descriptors.insert(OpenFile {
+            path,
+            mode,
+            cursor: 0,
+        })
+    }
+
+    pub fn close(&mut self, handle: FileHandle) -> Result<(), String> {
+        self.descriptors.remove(handle).map(|_| ())
+    }
+
+    // Reads into `buffer` from the handle's cursor and advances it, returns 0 at end of file
+    //
+    // Permissions were checked when the file was opened.
+    pub fn read(&mut self, handle: FileHandle, buffer: &mut [u8]) -> Result<usize, String> {
+        let file = self.descriptors.get(handle)?;
+        if file.mode != OpenMode::Read {
+            return Err(String::from("File was not opened for reading"));
+        }
+
+        let (backend, relative) = self.mounts.resolve(&file.path);
+        let count = backend.read(&relative, file.cursor, buffer)?;
+
+        self.descriptors.get_mut(handle)?.cursor += count;
+
+        Ok(count)
+    }
+
+    // Writes `data` at the handle's cursor, or at the end of file in append mode, and advances it
+    pub fn write(&mut self, handle: FileHandle, data: &[u8]) -> Result<usize, String> {
+        let file = self.descriptors.get(handle)?;
+        let path = file.path.clone();
+
+        let offset = match file.mode {
+            OpenMode::Read => return Err(String::from("File was not opened for writing")),
+            OpenMode::Write => file.cursor,
+            OpenMode::Append => self.size(&path)?,
+        };
+
+        let written = self.write_at(&path, offset, data)?;
+        self.descriptors.get_mut(handle)?.cursor = offset + written;
+
+        Ok(written)
+    }
+
+    // Moves the handle's cursor and returns its new position, seeking past the end is allowed
+    pub fn seek(&mut self, handle: FileHandle, position: SeekFrom) -> Result<usize, String> {
+        let file = self.descriptors.get(handle)?;
+
+        let cursor = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => file.cursor.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.size(&file.path)?.checked_add_signed(delta),
+        }
+        .ok_or_else(|| String::from("Seek to an invalid position"))?;
+        self.descriptors.get_mut(handle)?.cursor = cursor;
+
+        Ok(cursor)
+    }
+
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let path = self.canonicalize(path, true)?;
+        self.check_access(&path, access::READ)?;
+
+        let (backend, relative) = self.mounts.resolve(&path);
+        backend.readdir(&relative)
+    }
+
+    // Iterates over the node at `path` and everything below it, a symbolic link at `path` itself
+    // is followed
+    pub fn walk(&self, path: &str) -> Result<Walk<'_>, String> {
+        let path = normalize_path("/", path);
+        let kind = self.lookup(&path)?;
+        let name = match split_path("/", &path) {
+            Ok((_, name)) => name,
+            Err(_) => String::from("/"),
+        };
+
+        Ok(Walk::new(
+            self,
+            WalkEntry {
+                path,
+                name,
+                kind,
+                depth: 0,
+            },
+        ))
     }
 
     pub fn list_directory(&self, path: &str) -> Result<Vec<String>, String> {
-        let dir = self.find_directory(path)?;
-
-        if let FSNode::Directory { children, .. } = dir {
-            let names = children
-                .iter()
-                .map(|node| match node {
-                    FSNode::File { name, .. } => name.clone(),
-                    FSNode::Directory { name, .. } => format!("/{name}"),
-                })
-                .collect();
-
-            Ok(names)
-        } else {
-            Err(format!("Path '{}' is not a directory", path))
-        }
-    }
-
-    pub fn rename_node(
-        &mut self,
-        path: &str,
-        old_name: &str,
-        new_name: &str,
-    ) -> Result<(), String> {
-        let parent_dir = self.find_directory_mut(path)?;
-
-        if let FSNode::Directory { children, .. } = parent_dir {
-            if children.iter().any(|node| match node {
-                FSNode::File { name, .. } | FSNode::Directory { name, .. } => name == new_name,
-            }) {
-                return Err(format!("A node with the name '{}' already exist", new_name));
-            }
+        let names = self
+            .read_dir(path)?
+            .into_iter()
+            .map(|entry| match entry.kind {
+                NodeKind::File
+                | NodeKind::Symlink
+                | NodeKind::CharDevice
+                | NodeKind::BlockDevice => entry.name,
+                NodeKind::Directory => format!("/{}", entry.name),
+            })
+            .collect();
+
+        Ok(names)
+    }
 
-            if let Some(node) = children.iter_mut().find(|node| match node {
-                FSNode::File { name, .. } | FSNode::Directory { name, .. } => name == old_name,
-            }) {
-                match node {
-                    FSNode::Directory { name, .. } | FSNode::File { name, .. } => {
-                        *name = String::from(new_name)
-                    }
+    // Renames the node at `path` in place, `new_name` is a plain name and not a path
+    pub fn rename_node(&mut self, path: &str, new_name: &str) -> Result<(), String> {
+        if new_name.is_empty() || new_name.contains('/') || new_name == "." || new_name == ".." {
+            return Err(format!("'{}' is not a valid name", new_name));
+        }
+
+        let (parent, _) = split_path("/", path)?;
+        self.move_node(path, &normalize_path(&parent, new_name), Conflict::Fail)
+    }
+
+    // Moves the node at `from` to exactly `to`, which may be in another directory of the same
+    // filesystem, `conflict` decides what happens when `to` already exists
+    //
+    // Symbolic links named by `from` or `to` are moved or replaced themselves, not their targets.
+    pub fn move_node(&mut self, from: &str, to: &str, conflict: Conflict) -> Result<(), String> {
+        let from = self.canonicalize(from, false)?;
+        let to = self.canonicalize(to, false)?;
+
+        let source = self.lstat(&from)?;
+        if from == to {
+            return Ok(());
+        }
+        if mount::relative_to(&from, &to).is_some() {
+            return Err(format!("Cannot move '{}' into itself", from));
+        }
+        if self.mounts.is_busy(&from) {
+            return Err(format!("'{}' is busy, a filesystem is mounted there", from));
+        }
+        self.check_parent(&from)?;
+        self.check_parent(&to)?;
+        if !self.mounts.same_filesystem(&from, &to) {
+            return Err(String::from("Paths are on different filesystems"));
+        }
+
+        // On a case-insensitive filesystem `to` names the source itself when only the case of
+        // the name changes, a node with a single link cannot be anything else
+        let existing = self
+            .lstat(&to)
+            .ok()
+            .filter(|existing| existing.inode != source.inode || existing.links > 1);
+        if let Some(existing) = existing {
+            let is_directory = existing.kind == NodeKind::Directory;
+            match conflict {
+                Conflict::Fail => return Err(format!("'{}' already exists", to)),
+                Conflict::Skip => return Ok(()),
+                // Like `rename(2)` only a node of the same sort is replaced
+                Conflict::Overwrite if source.kind == NodeKind::Directory && !is_directory => {
+                    return Err(format!("'{}' is not a directory", to))
+                }
+                Conflict::Overwrite if source.kind != NodeKind::Directory && is_directory => {
+                    return Err(format!("'{}' is a directory", to))
                 }
+                Conflict::Overwrite => self.delete_node(&to)?,
             }
+        }
+
+        let (backend, relative_from, relative_to) = self.mounts.resolve_pair_mut(&from, &to)?;
+        backend.rename(&relative_from, &relative_to)?;
 
-            Ok(())
-        } else {
-            Err(format!("'{}' is not a directory", path))
+        self.watches.notify(Event::rename(&from, &to));
+        Ok(())
+    }
+
+    // Copies the file or the whole directory tree at `from` to exactly `to`, possibly on another
+    // filesystem
+    //
+    // An existing directory at `to` is merged into unless `conflict` is `Fail`, existing files
+    // are handled according to `conflict`. Links named by `from` and `to` are followed, symbolic
+    // links inside a copied tree are copied as links.
+    pub fn copy_node(&mut self, from: &str, to: &str, conflict: Conflict) -> Result<(), String> {
+        let from = self.canonicalize(from, true)?;
+        let to = self.canonicalize(to, true)?;
+
+        if mount::relative_to(&from, &to).is_some() {
+            return Err(format!("Cannot copy '{}' into itself", from));
         }
+
+        self.copy_tree(&from, &to, conflict)
     }
 
-    pub fn delete_node(&mut self, path: &str, name: &str) -> Result<(), String> {
-        // Locate the parent directory of the node to delete
-        let parent_dir = self.find_directory_mut(path)?;
+    // Does the work of `copy_node` on paths that are already canonical
+    fn copy_tree(&mut self, from: &str, to: &str, conflict: Conflict) -> Result<(), String> {
+        let existing = self.lstat(to).ok().map(|metadata| metadata.kind);
+
+        match self.lstat(from)?.kind {
+            NodeKind::File => {
+                // Read first, `to` may be a hard link to the same file
+                let content = self.read_file(from)?;
 
-        if let FSNode::Directory { children, .. } = parent_dir {
-            if let Some(index) = children.iter().position(|node| match node {
-                FSNode::File { name: n, .. } | FSNode::Directory { name: n, .. } => n == name,
-            }) {
-                if let FSNode::Directory {
-                    children: sub_children,
-                    ..
-                } = &children[index]
-                {
-                    if !sub_children.is_empty() {
-                        return Err(format!("Directory '{}' is not empty", name));
+                match (existing, conflict) {
+                    (Some(NodeKind::Directory), _) => {
+                        return Err(format!("'{}' is a directory", to))
                     }
+                    (Some(_), Conflict::Fail) => return Err(format!("'{}' already exists", to)),
+                    (Some(_), Conflict::Skip) => return Ok(()),
+                    (Some(NodeKind::File), Conflict::Overwrite) => self.truncate(to, 0)?,
+                    (Some(_), Conflict::Overwrite) => {
+                        self.delete_node(to)?;
+                        self.create_file(to, &[])?;
+                    }
+                    (None, _) => self.create_file(to, &[])?,
                 }
 
-                children.remove(index);
-
-                Ok(())
-            } else {
-                Err(format!("Node '{}' not found in '{}'", name, path))
+                self.write_at(to, 0, &content)?;
             }
-        } else {
-            Err(format!("'{}' is not a directory", path))
-        }
-    }
+            NodeKind::Symlink => {
+                let target = self.readlink(from)?;
 
-    pub fn find_directory(&self, path: &str) -> Result<&FSNode, String> {
-        let mut current = &self.root;
+                match (existing, conflict) {
+                    (Some(NodeKind::Directory), _) => {
+                        return Err(format!("'{}' is a directory", to))
+                    }
+                    (Some(_), Conflict::Fail) => return Err(format!("'{}' already exists", to)),
+                    (Some(_), Conflict::Skip) => return Ok(()),
+                    (Some(_), Conflict::Overwrite) => self.delete_node(to)?,
+                    (None, _) => {}
+                }
 
-        for part in path.split('/') {
-            if part.is_empty() {
-                continue;
+                self.symlink(&target, to)?;
             }
+            // Only devfs can create device nodes
+            NodeKind::CharDevice | NodeKind::BlockDevice => {
+                return Err(format!("Cannot copy device '{}'", from))
+            }
+            NodeKind::Directory => {
+                match (existing, conflict) {
+                    (Some(NodeKind::Directory), Conflict::Fail) => {
+                        return Err(format!("'{}' already exists", to))
+                    }
+                    (Some(NodeKind::Directory), _) => {}
+                    (Some(_), _) => return Err(format!("'{}' is not a directory", to)),
+                    (None, _) => self.create_directory(to)?,
+                }
 
-            if let FSNode::Directory { children, .. } = current {
-                current = children
-                    .iter()
-                    .find(|node| match node {
-                        FSNode::Directory { name, .. } => name == part,
-                        _ => false,
-                    })
-                    .ok_or_else(|| format!("Direvtory '{}' not found", path))?;
-            } else {
-                return Err(format!("'{}' is not a directory", part));
+                for entry in self.read_dir(from)? {
+                    self.copy_tree(
+                        &normalize_path(from, &entry.name),
+                        &normalize_path(to, &entry.name),
+                        conflict,
+                    )?;
+                }
             }
         }
 
-        Ok(current)
+        Ok(())
     }
 
-    pub fn find_directory_mut(&mut self, path: &str) -> Result<&mut FSNode, String> {
-        let mut current = &mut self.root;
+    // Makes `new` another name for the file at `existing`, both must be on the same filesystem
+    pub fn link(&mut self, existing: &str, new: &str) -> Result<(), String> {
+        let existing = self.canonicalize(existing, false)?;
+        let new = self.canonicalize(new, false)?;
+        self.check_search(&existing)?;
+        self.check_parent(&new)?;
 
-        for part in path.split('/') {
-            if part.is_empty() {
-                continue;
-            }
+        let (backend, relative_existing, relative_new) =
+            self.mounts.resolve_pair_mut(&existing, &new)?;
+        backend.link(&relative_existing, &relative_new)?;
+
+        self.watches.notify(Event::new(EventKind::Create, &new));
+        Ok(())
+    }
+
+    // Deletes the node at `path` and, for directories, everything below it, symbolic links are
+    // removed without touching their targets
+    pub fn delete_tree(&mut self, path: &str) -> Result<(), String> {
+        let path = self.canonicalize(path, false)?;
 
-            current = match current {
-                FSNode::Directory { children, .. } => children
-                    .iter_mut()
-                    .find(|node| matches!( node, FSNode::Directory { name, .. } if name == part))
-                    .ok_or_else(|| format!("Direvtory '{}' not found", path))?,
-                _ => return Err(format!("'{}' is not a directory", part)),
+        if path == "/" {
+            return Err(String::from("Refusing to remove the root directory"));
+        }
+        if self.mounts.is_busy(&path) {
+            return Err(format!("'{}' is busy, a filesystem is mounted there", path));
+        }
+
+        if self.lstat(&path)?.kind == NodeKind::Directory {
+            for entry in self.read_dir(&path)? {
+                self.delete_tree(&normalize_path(&path, &entry.name))?;
             }
         }
 
-        Ok(current)
+        self.delete_node(&path)
+    }
+
+    pub fn delete_node(&mut self, path: &str) -> Result<(), String> {
+        let path = self.canonicalize(path, false)?;
+
+        if self.mounts.is_busy(&path) {
+            return Err(format!("'{}' is busy, a filesystem is mounted there", path));
+        }
+        self.check_parent(&path)?;
+
+        let (backend, relative) = self.mounts.resolve_mut(&path);
+        backend.unlink(&relative)?;
+
+        self.watches.notify(Event::new(EventKind::Delete, &path));
+        Ok(())
+    }
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,64 @@
+use super::vfs::Metadata;
+use alloc::vec::Vec;
+
+// Permission bits of one class (owner, group or others), combined with `|`
+pub const READ: u16 = 0o4;
+pub const WRITE: u16 = 0o2;
+// Searching a directory, i.e. using it in a path
+pub const EXECUTE: u16 = 0o1;
+
+pub const ROOT_UID: u32 = 0;
+
+// Identity file operations are checked against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    // Primary group, given to the nodes the user creates
+    pub gid: u32,
+    // Further groups the user is a member of
+    pub groups: Vec<u32>,
+}
+
+impl Credentials {
+    pub fn root() -> Self {
+        Self {
+            uid: ROOT_UID,
+            gid: 0,
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == ROOT_UID
+    }
+
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+
+    // Whether the node described by `metadata` grants all of `access`, using the owner bits for
+    // its owner, the group bits for members of its group and the other bits for everyone else
+    //
+    // Root is granted everything.
+    pub fn may(&self, metadata: &Metadata, access: u16) -> bool {
+        if self.is_root() {
+            return true;
+        }
+
+        let shift = if metadata.uid == self.uid {
+            6
+        } else if self.in_group(metadata.gid) {
+            3
+        } else {
+            0
+        };
+
+        (metadata.mode >> shift) & access == access
+    }
+}
+
+impl Default for Credentials {
+    fn default() -> Self {
+        Self::root()
+    }
+}
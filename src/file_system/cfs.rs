@@ -0,0 +1,1106 @@
+use super::image::crc32;
+use super::vfs::{
+    Attributes, DirEntry, Metadata, NodeKind, Vfs, DEFAULT_DIRECTORY_MODE, DEFAULT_FILE_MODE,
+};
+use super::{resolve_path, split_path};
+use crate::clock;
+use crate::devices::block::{self, SharedBlockDevice};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Native disk format of the kernel, with a write-ahead journal so that a crash at any point
+// leaves the volume as it was after the last completed change
+//
+// The volume is a sequence of 1 KiB blocks:
+//
+//   superblock   block 0, the layout below followed by its CRC-32
+//   journal      header block, then room for the copies of the blocks of one change
+//   bitmap       one bit per block of the volume, set when the block is in use
+//   inode table  128-byte inodes, numbered from 1, the root directory is inode 1
+//   data         file content, directories, symbolic link targets and indirect blocks
+//
+// An inode holds 12 direct block pointers, a single and a double indirect one, so files reach
+// 64 MiB. Directories are arrays of 64-byte entries (inode, kind, name length, name) without
+// `.` and `..`, symbolic links keep their target as content. All integers are little endian.
+//
+// Every operation of the `Vfs` is one transaction. Changed metadata blocks (inodes, bitmap,
+// directories, indirect blocks) are kept in memory until the operation is done, then written to
+// the journal, committed by writing the journal header with a checksum over the whole
+// transaction, copied to their place and released by clearing the header. Mounting replays a
+// committed transaction that was not released. File content never goes through the journal:
+// changed blocks are written to newly allocated blocks, which nothing committed points to, and
+// the pointers to them are switched in the transaction, so content is never half old and half
+// new either. Blocks freed by a transaction are only reused once it committed.
+
+const BLOCK_SIZE: usize = 1024;
+const MAGIC: &[u8; 8] = b"CHZCFS\0\0";
+const VERSION: u32 = 1;
+// Bytes of the superblock covered by its checksum, which follows them
+const SUPERBLOCK_SIZE: usize = 48;
+
+// The journal header is the magic, the number of blocks in the transaction and a checksum of
+// the header, followed by the block number and the checksum of each copy
+const JOURNAL_MAGIC: &[u8; 8] = b"CHZJRNL\0";
+const JOURNAL_HEADER_SIZE: usize = 16;
+const JOURNAL_ENTRY_SIZE: usize = 8;
+const MAX_TRANSACTION_BLOCKS: usize = (BLOCK_SIZE - JOURNAL_HEADER_SIZE) / JOURNAL_ENTRY_SIZE;
+
+const INODE_SIZE: usize = 128;
+const INODES_PER_BLOCK: u32 = (BLOCK_SIZE / INODE_SIZE) as u32;
+const ROOT_INODE: u32 = 1;
+
+const KIND_FREE: u8 = 0;
+const KIND_FILE: u8 = 1;
+const KIND_DIRECTORY: u8 = 2;
+const KIND_SYMLINK: u8 = 3;
+
+const DIRECT_BLOCKS: u64 = 12;
+const POINTERS_PER_BLOCK: u64 = (BLOCK_SIZE / 4) as u64;
+// Direct pointers, then the single and the double indirect one
+const POINTERS: usize = DIRECT_BLOCKS as usize + 2;
+const INDIRECT: usize = DIRECT_BLOCKS as usize;
+const DOUBLE_INDIRECT: usize = DIRECT_BLOCKS as usize + 1;
+
+const ENTRY_SIZE: usize = 64;
+const MAX_NAME_LENGTH: usize = ENTRY_SIZE - 6;
+
+// Where the areas of a volume start and how large they are, in blocks
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    block_count: u32,
+    inode_count: u32,
+    journal_start: u32,
+    journal_blocks: u32,
+    bitmap_start: u32,
+    bitmap_blocks: u32,
+    inode_start: u32,
+    data_start: u32,
+}
+
+impl Layout {
+    // Splits a device of `block_count` blocks, the journal takes an eighth of small volumes
+    // and 32 blocks of larger ones, and there is one inode for every four blocks
+    fn new(block_count: u32) -> Result<Self, String> {
+        if block_count < 32 {
+            return Err(String::from("Device is too small for cfs"));
+        }
+
+        let journal_blocks = (block_count / 8).clamp(8, 32);
+        let bitmap_blocks = block_count.div_ceil(8 * BLOCK_SIZE as u32);
+        let inode_count = (block_count / 4).div_ceil(INODES_PER_BLOCK) * INODES_PER_BLOCK;
+
+        let journal_start = 1;
+        let bitmap_start = journal_start + journal_blocks;
+        let inode_start = bitmap_start + bitmap_blocks;
+
+        Ok(Self {
+            block_count,
+            inode_count,
+            journal_start,
+            journal_blocks,
+            bitmap_start,
+            bitmap_blocks,
+            inode_start,
+            data_start: inode_start + inode_count / INODES_PER_BLOCK,
+        })
+    }
+
+    fn read(superblock: &[u8]) -> Result<Self, String> {
+        if &superblock[..8] != MAGIC {
+            return Err(String::from("Device does not hold a cfs volume"));
+        }
+        if u32_at(superblock, 8) != VERSION {
+            return Err(format!(
+                "Unsupported cfs version {}, expected {}",
+                u32_at(superblock, 8),
+                VERSION
+            ));
+        }
+        if crc32(&superblock[..SUPERBLOCK_SIZE]) != u32_at(superblock, SUPERBLOCK_SIZE) {
+            return Err(String::from("cfs superblock is corrupted"));
+        }
+
+        let layout = Self {
+            block_count: u32_at(superblock, 12),
+            inode_count: u32_at(superblock, 16),
+            journal_start: u32_at(superblock, 20),
+            journal_blocks: u32_at(superblock, 24),
+            bitmap_start: u32_at(superblock, 28),
+            bitmap_blocks: u32_at(superblock, 32),
+            inode_start: u32_at(superblock, 36),
+            data_start: u32_at(superblock, 44),
+        };
+        if layout.journal_blocks < 2 || layout.data_start > layout.block_count {
+            return Err(String::from("cfs superblock is corrupted"));
+        }
+
+        Ok(layout)
+    }
+
+    fn write(&self, superblock: &mut [u8]) {
+        superblock[..8].copy_from_slice(MAGIC);
+        let fields = [
+            VERSION,
+            self.block_count,
+            self.inode_count,
+            self.journal_start,
+            self.journal_blocks,
+            self.bitmap_start,
+            self.bitmap_blocks,
+            self.inode_start,
+            self.inode_count / INODES_PER_BLOCK,
+            self.data_start,
+        ];
+        for (index, field) in fields.iter().enumerate() {
+            put_u32(superblock, 8 + index * 4, *field);
+        }
+
+        let checksum = crc32(&superblock[..SUPERBLOCK_SIZE]);
+        put_u32(superblock, SUPERBLOCK_SIZE, checksum);
+    }
+
+    // Blocks one transaction may change, the journal also holds its header
+    fn transaction_capacity(&self) -> usize {
+        (self.journal_blocks as usize - 1).min(MAX_TRANSACTION_BLOCKS)
+    }
+}
+
+struct Inode {
+    number: u32,
+    kind: u8,
+    mode: u16,
+    links: u32,
+    uid: u32,
+    gid: u32,
+    size: u64,
+    created: u64,
+    modified: u64,
+    accessed: u64,
+    pointers: [u32; POINTERS],
+}
+
+impl Inode {
+    fn new(number: u32, kind: u8, mode: u16) -> Self {
+        let now = clock::now();
+
+        Self {
+            number,
+            kind,
+            mode,
+            links: 1,
+            uid: 0,
+            gid: 0,
+            size: 0,
+            created: now,
+            modified: now,
+            accessed: now,
+            pointers: [0; POINTERS],
+        }
+    }
+
+    fn parse(number: u32, raw: &[u8]) -> Self {
+        let mut pointers = [0; POINTERS];
+        for (index, pointer) in pointers.iter_mut().enumerate() {
+            *pointer = u32_at(raw, 48 + index * 4);
+        }
+
+        Self {
+            number,
+            kind: raw[0],
+            mode: u16::from_le_bytes([raw[2], raw[3]]),
+            links: u32_at(raw, 4),
+            uid: u32_at(raw, 8),
+            gid: u32_at(raw, 12),
+            size: u64_at(raw, 16),
+            created: u64_at(raw, 24),
+            modified: u64_at(raw, 32),
+            accessed: u64_at(raw, 40),
+            pointers,
+        }
+    }
+
+    fn serialize(&self, raw: &mut [u8]) {
+        raw.fill(0);
+        raw[0] = self.kind;
+        raw[2..4].copy_from_slice(&self.mode.to_le_bytes());
+        put_u32(raw, 4, self.links);
+        put_u32(raw, 8, self.uid);
+        put_u32(raw, 12, self.gid);
+        raw[16..24].copy_from_slice(&self.size.to_le_bytes());
+        raw[24..32].copy_from_slice(&self.created.to_le_bytes());
+        raw[32..40].copy_from_slice(&self.modified.to_le_bytes());
+        raw[40..48].copy_from_slice(&self.accessed.to_le_bytes());
+        for (index, pointer) in self.pointers.iter().enumerate() {
+            put_u32(raw, 48 + index * 4, *pointer);
+        }
+    }
+
+    fn node_kind(&self) -> NodeKind {
+        match self.kind {
+            KIND_DIRECTORY => NodeKind::Directory,
+            KIND_SYMLINK => NodeKind::Symlink,
+            _ => NodeKind::File,
+        }
+    }
+}
+
+struct Entry {
+    name: String,
+    inode: u32,
+    kind: u8,
+}
+
+// Creates an empty cfs volume on the whole of `device`, everything on it is lost
+pub fn format(device: &SharedBlockDevice) -> Result<(), String> {
+    let size = device.lock().size();
+    let block_count = u32::try_from(size / BLOCK_SIZE as u64).unwrap_or(u32::MAX);
+    let layout = Layout::new(block_count)?;
+    let write = |number: u32, data: &[u8]| {
+        block::write_bytes(device, u64::from(number) * BLOCK_SIZE as u64, data)
+    };
+
+    // A stale superblock would make a half formatted device look valid, so it goes first
+    write(0, &[0; BLOCK_SIZE])?;
+    write(layout.journal_start, &[0; BLOCK_SIZE])?;
+
+    // The blocks in front of the data area and the bits past the end of the device are taken
+    for index in 0..layout.bitmap_blocks {
+        let mut bitmap = vec![0; BLOCK_SIZE];
+        for bit in 0..BLOCK_SIZE as u32 * 8 {
+            let number = index * BLOCK_SIZE as u32 * 8 + bit;
+            if number < layout.data_start || number >= layout.block_count {
+                bitmap[bit as usize / 8] |= 1 << (bit % 8);
+            }
+        }
+        write(layout.bitmap_start + index, &bitmap)?;
+    }
+
+    for index in 0..layout.inode_count / INODES_PER_BLOCK {
+        let mut table = vec![0; BLOCK_SIZE];
+        if index == 0 {
+            Inode::new(ROOT_INODE, KIND_DIRECTORY, DEFAULT_DIRECTORY_MODE)
+                .serialize(&mut table[..INODE_SIZE]);
+        }
+        write(layout.inode_start + index, &table)?;
+    }
+
+    let mut superblock = vec![0; BLOCK_SIZE];
+    layout.write(&mut superblock);
+    write(0, &superblock)
+}
+
+// Returns whether `first_block`, the start of a device, belongs to a cfs volume
+pub fn is_volume(first_block: &[u8]) -> bool {
+    first_block.starts_with(MAGIC)
+}
+
+// cfs volume on a block device, see the top of this file for the format
+//
+// Nothing is cached between operations. Reads do not update access times, so only changes
+// write to the device.
+pub struct CfsFs {
+    device: SharedBlockDevice,
+    layout: Layout,
+    // Metadata blocks changed by the running operation, written when it commits
+    dirty: BTreeMap<u32, Vec<u8>>,
+    // Blocks allocated by the running operation, nothing committed points to them yet
+    fresh: BTreeSet<u32>,
+}
+
+impl CfsFs {
+    // Opens the volume on `device`, first finishing a transaction that was committed but not
+    // copied to its place before the system went down
+    pub fn new(device: SharedBlockDevice) -> Result<Self, String> {
+        let mut superblock = vec![0; BLOCK_SIZE];
+        block::read_bytes(&device, 0, &mut superblock)?;
+        let layout = Layout::read(&superblock)?;
+        if u64::from(layout.block_count) * BLOCK_SIZE as u64 > device.lock().size() {
+            return Err(String::from("cfs volume is larger than the device"));
+        }
+
+        let fs = Self {
+            device,
+            layout,
+            dirty: BTreeMap::new(),
+            fresh: BTreeSet::new(),
+        };
+        fs.recover()?;
+
+        Ok(fs)
+    }
+
+    fn recover(&self) -> Result<(), String> {
+        let journal = self.layout.journal_start;
+        let header = self.read_disk_block(journal)?;
+        if &header[..8] != JOURNAL_MAGIC {
+            return Ok(());
+        }
+
+        // Anything that does not match its checksum was torn while it was written, so the
+        // transaction never committed and the blocks in place are still the old ones
+        let count = u32_at(&header, 8) as usize;
+        if count <= self.layout.transaction_capacity()
+            && header_checksum(&header) == u32_at(&header, 12)
+        {
+            let mut copies = Vec::with_capacity(count);
+            for index in 0..count {
+                let copy = self.read_disk_block(journal + 1 + index as u32)?;
+                let entry = JOURNAL_HEADER_SIZE + index * JOURNAL_ENTRY_SIZE;
+                if crc32(&copy) != u32_at(&header, entry + 4) {
+                    return self.write_disk_block(journal, &[0; BLOCK_SIZE]);
+                }
+                copies.push((u32_at(&header, entry), copy));
+            }
+
+            for (target, copy) in copies {
+                self.write_disk_block(target, &copy)?;
+            }
+        }
+
+        self.write_disk_block(journal, &[0; BLOCK_SIZE])
+    }
+
+    // Runs `operation` as one transaction, committing its changes when it succeeds and
+    // dropping them when it fails
+    fn transaction<T>(
+        &mut self,
+        operation: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let result = operation(self).and_then(|value| self.commit().map(|_| value));
+
+        self.dirty.clear();
+        self.fresh.clear();
+
+        result
+    }
+
+    fn commit(&mut self) -> Result<(), String> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+        if self.dirty.len() > self.layout.transaction_capacity() {
+            return Err(format!(
+                "Change touches {} blocks, more than the journal holds ({})",
+                self.dirty.len(),
+                self.layout.transaction_capacity()
+            ));
+        }
+
+        let journal = self.layout.journal_start;
+        let mut header = vec![0; BLOCK_SIZE];
+        header[..8].copy_from_slice(JOURNAL_MAGIC);
+        put_u32(&mut header, 8, self.dirty.len() as u32);
+
+        for (index, (&target, content)) in self.dirty.iter().enumerate() {
+            let entry = JOURNAL_HEADER_SIZE + index * JOURNAL_ENTRY_SIZE;
+            put_u32(&mut header, entry, target);
+            put_u32(&mut header, entry + 4, crc32(content));
+            self.write_disk_block(journal + 1 + index as u32, content)?;
+        }
+        let checksum = header_checksum(&header);
+        put_u32(&mut header, 12, checksum);
+
+        // The transaction counts from here on
+        self.write_disk_block(journal, &header)?;
+
+        for (&target, content) in &self.dirty {
+            self.write_disk_block(target, content)?;
+        }
+
+        self.write_disk_block(journal, &[0; BLOCK_SIZE])
+    }
+
+    fn read_disk_block(&self, number: u32) -> Result<Vec<u8>, String> {
+        let mut content = vec![0; BLOCK_SIZE];
+        block::read_bytes(
+            &self.device,
+            u64::from(number) * BLOCK_SIZE as u64,
+            &mut content,
+        )?;
+
+        Ok(content)
+    }
+
+    fn write_disk_block(&self, number: u32, content: &[u8]) -> Result<(), String> {
+        block::write_bytes(&self.device, u64::from(number) * BLOCK_SIZE as u64, content)
+    }
+
+    // Reads a block as the running operation sees it
+    fn read_block(&self, number: u32) -> Result<Vec<u8>, String> {
+        match self.dirty.get(&number) {
+            Some(content) => Ok(content.clone()),
+            None => self.read_disk_block(number),
+        }
+    }
+
+    // Changes a metadata block as part of the running operation
+    fn write_block(&mut self, number: u32, content: Vec<u8>) {
+        self.dirty.insert(number, content);
+    }
+
+    // Takes a free block, which must not be free on disk either: a block freed by the running
+    // operation is still in use until it commits
+    fn allocate_block(&mut self) -> Result<u32, String> {
+        for index in 0..self.layout.bitmap_blocks {
+            let number = self.layout.bitmap_start + index;
+            let mut bitmap = self.read_block(number)?;
+            let committed = if self.dirty.contains_key(&number) {
+                self.read_disk_block(number)?
+            } else {
+                bitmap.clone()
+            };
+
+            let Some(byte) = (0..BLOCK_SIZE).find(|&byte| bitmap[byte] | committed[byte] != 0xFF)
+            else {
+                continue;
+            };
+            let bit = (bitmap[byte] | committed[byte]).trailing_ones();
+
+            bitmap[byte] |= 1 << bit;
+            self.write_block(number, bitmap);
+
+            let block = (index * BLOCK_SIZE as u32 + byte as u32) * 8 + bit;
+            self.fresh.insert(block);
+            return Ok(block);
+        }
+
+        Err(String::from("No space left on the cfs volume"))
+    }
+
+    fn free_block(&mut self, block: u32) -> Result<(), String> {
+        let per_bitmap = BLOCK_SIZE as u32 * 8;
+        let number = self.layout.bitmap_start + block / per_bitmap;
+        let bit = block % per_bitmap;
+
+        let mut bitmap = self.read_block(number)?;
+        bitmap[bit as usize / 8] &= !(1 << (bit % 8));
+        self.write_block(number, bitmap);
+
+        self.dirty.remove(&block);
+        self.fresh.remove(&block);
+
+        Ok(())
+    }
+
+    fn inode_location(&self, number: u32) -> Result<(u32, usize), String> {
+        if number == 0 || number > self.layout.inode_count {
+            return Err(format!("Inode {} does not exist", number));
+        }
+
+        let index = number - 1;
+        Ok((
+            self.layout.inode_start + index / INODES_PER_BLOCK,
+            (index % INODES_PER_BLOCK) as usize * INODE_SIZE,
+        ))
+    }
+
+    fn read_inode(&self, number: u32) -> Result<Inode, String> {
+        let (block, offset) = self.inode_location(number)?;
+        let table = self.read_block(block)?;
+
+        let inode = Inode::parse(number, &table[offset..offset + INODE_SIZE]);
+        if inode.kind == KIND_FREE {
+            return Err(format!("Inode {} is not in use", number));
+        }
+
+        Ok(inode)
+    }
+
+    fn write_inode(&mut self, inode: &Inode) -> Result<(), String> {
+        let (block, offset) = self.inode_location(inode.number)?;
+        let mut table = self.read_block(block)?;
+        inode.serialize(&mut table[offset..offset + INODE_SIZE]);
+        self.write_block(block, table);
+
+        Ok(())
+    }
+
+    fn allocate_inode(&mut self) -> Result<u32, String> {
+        let blocks = self.layout.inode_count / INODES_PER_BLOCK;
+
+        for index in 0..blocks {
+            let table = self.read_block(self.layout.inode_start + index)?;
+            if let Some(slot) =
+                (0..INODES_PER_BLOCK).find(|&slot| table[slot as usize * INODE_SIZE] == KIND_FREE)
+            {
+                return Ok(index * INODES_PER_BLOCK + slot + 1);
+            }
+        }
+
+        Err(String::from("No free inode left on the cfs volume"))
+    }
+
+    fn free_inode(&mut self, number: u32) -> Result<(), String> {
+        let (block, offset) = self.inode_location(number)?;
+        let mut table = self.read_block(block)?;
+        table[offset..offset + INODE_SIZE].fill(0);
+        self.write_block(block, table);
+
+        Ok(())
+    }
+
+    // Returns the block holding block `index` of the content of `inode`, 0 for a hole
+    fn content_block(&self, inode: &Inode, index: u64) -> Result<u32, String> {
+        if index < DIRECT_BLOCKS {
+            return Ok(inode.pointers[index as usize]);
+        }
+
+        let index = index - DIRECT_BLOCKS;
+        if index < POINTERS_PER_BLOCK {
+            return self.pointer_in(inode.pointers[INDIRECT], index);
+        }
+
+        let index = index - POINTERS_PER_BLOCK;
+        if index < POINTERS_PER_BLOCK * POINTERS_PER_BLOCK {
+            let table =
+                self.pointer_in(inode.pointers[DOUBLE_INDIRECT], index / POINTERS_PER_BLOCK)?;
+            return self.pointer_in(table, index % POINTERS_PER_BLOCK);
+        }
+
+        Err(String::from("File is too large for cfs"))
+    }
+
+    // Reads entry `index` of the indirect block `table`, a missing table is a hole
+    fn pointer_in(&self, table: u32, index: u64) -> Result<u32, String> {
+        if table == 0 {
+            return Ok(0);
+        }
+
+        Ok(u32_at(&self.read_block(table)?, index as usize * 4))
+    }
+
+    // Points block `index` of the content of `inode` to `block`, allocating the indirect blocks
+    // on the way unless `block` is 0
+    fn set_content_block(
+        &mut self,
+        inode: &mut Inode,
+        index: u64,
+        block: u32,
+    ) -> Result<(), String> {
+        if index < DIRECT_BLOCKS {
+            inode.pointers[index as usize] = block;
+            return Ok(());
+        }
+
+        let index = index - DIRECT_BLOCKS;
+        if index < POINTERS_PER_BLOCK {
+            let table = self.indirect_table(&mut inode.pointers[INDIRECT], block)?;
+            return self.set_pointer_in(table, index, block);
+        }
+
+        let index = index - POINTERS_PER_BLOCK;
+        if index < POINTERS_PER_BLOCK * POINTERS_PER_BLOCK {
+            let Some(outer) = self.indirect_table(&mut inode.pointers[DOUBLE_INDIRECT], block)?
+            else {
+                return Ok(());
+            };
+
+            let mut inner = self.pointer_in(outer, index / POINTERS_PER_BLOCK)?;
+            let existed = inner != 0;
+            let table = self.indirect_table(&mut inner, block)?;
+            if !existed && inner != 0 {
+                self.set_pointer_in(Some(outer), index / POINTERS_PER_BLOCK, inner)?;
+            }
+            return self.set_pointer_in(table, index % POINTERS_PER_BLOCK, block);
+        }
+
+        Err(String::from("File is too large for cfs"))
+    }
+
+    // Returns the indirect block `pointer` refers to, allocating an empty one when it is missing
+    // and `block` is about to be stored in it
+    fn indirect_table(&mut self, pointer: &mut u32, block: u32) -> Result<Option<u32>, String> {
+        if *pointer == 0 {
+            if block == 0 {
+                return Ok(None);
+            }
+            *pointer = self.allocate_block()?;
+            self.write_block(*pointer, vec![0; BLOCK_SIZE]);
+        }
+
+        Ok(Some(*pointer))
+    }
+
+    fn set_pointer_in(&mut self, table: Option<u32>, index: u64, block: u32) -> Result<(), String> {
+        let Some(table) = table else {
+            return Ok(());
+        };
+
+        let mut content = self.read_block(table)?;
+        put_u32(&mut content, index as usize * 4, block);
+        self.write_block(table, content);
+
+        Ok(())
+    }
+
+    fn read_content(&self, inode: &Inode, offset: u64, buffer: &mut [u8]) -> Result<usize, String> {
+        let start = offset.min(inode.size);
+        let count = buffer.len().min((inode.size - start) as usize);
+
+        let mut done = 0;
+        while done < count {
+            let position = start + done as u64;
+            let index = position / BLOCK_SIZE as u64;
+            let within = (position % BLOCK_SIZE as u64) as usize;
+            let length = (BLOCK_SIZE - within).min(count - done);
+
+            match self.content_block(inode, index)? {
+                0 => buffer[done..done + length].fill(0),
+                block => {
+                    let content = self.read_block(block)?;
+                    buffer[done..done + length].copy_from_slice(&content[within..within + length]);
+                }
+            }
+            done += length;
+        }
+
+        Ok(count)
+    }
+
+    // Writes `data` at `offset` of the content of `inode` and grows it as needed, the caller
+    // saves the inode
+    //
+    // Files get new blocks for everything they had before, directories and symbolic links are
+    // metadata and change in place through the journal.
+    fn write_content(&mut self, inode: &mut Inode, offset: u64, data: &[u8]) -> Result<(), String> {
+        let end = offset
+            .checked_add(data.len() as u64)
+            .ok_or_else(|| String::from("Write past the maximum file size"))?;
+
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done as u64;
+            let index = position / BLOCK_SIZE as u64;
+            let within = (position % BLOCK_SIZE as u64) as usize;
+            let length = (BLOCK_SIZE - within).min(data.len() - done);
+            let old = self.content_block(inode, index)?;
+
+            let mut content = match old {
+                0 => vec![0; BLOCK_SIZE],
+                // Fully overwritten blocks of files are not worth reading
+                _ if inode.kind == KIND_FILE && length == BLOCK_SIZE => vec![0; BLOCK_SIZE],
+                _ => self.read_block(old)?,
+            };
+            content[within..within + length].copy_from_slice(&data[done..done + length]);
+
+            if inode.kind != KIND_FILE {
+                let block = match old {
+                    0 => self.allocate_block()?,
+                    _ => old,
+                };
+                self.write_block(block, content);
+                if old == 0 {
+                    self.set_content_block(inode, index, block)?;
+                }
+            } else if old != 0 && self.fresh.contains(&old) {
+                self.write_disk_block(old, &content)?;
+            } else {
+                let block = self.allocate_block()?;
+                self.write_disk_block(block, &content)?;
+                self.set_content_block(inode, index, block)?;
+                if old != 0 {
+                    self.free_block(old)?;
+                }
+            }
+
+            done += length;
+        }
+
+        inode.size = inode.size.max(end);
+        Ok(())
+    }
+
+    // Shrinks or grows the content of `inode` to `len` bytes, the caller saves the inode
+    fn resize_content(&mut self, inode: &mut Inode, len: u64) -> Result<(), String> {
+        if len >= inode.size {
+            // Bytes past the end are always zero, so growing only moves the end
+            inode.size = len;
+            return Ok(());
+        }
+
+        // Keeps the bytes past the end zero for when the content grows again
+        let block_end = len.next_multiple_of(BLOCK_SIZE as u64).min(inode.size);
+        if block_end > len {
+            self.write_content(inode, len, &vec![0; (block_end - len) as usize])?;
+        }
+
+        let keep = len.div_ceil(BLOCK_SIZE as u64);
+        for index in keep..inode.size.div_ceil(BLOCK_SIZE as u64) {
+            let block = self.content_block(inode, index)?;
+            if block != 0 {
+                self.free_block(block)?;
+                self.set_content_block(inode, index, 0)?;
+            }
+        }
+        self.free_indirect_blocks(inode, keep)?;
+
+        inode.size = len;
+        Ok(())
+    }
+
+    // Frees the indirect blocks that only point past the first `keep` blocks of the content
+    fn free_indirect_blocks(&mut self, inode: &mut Inode, keep: u64) -> Result<(), String> {
+        if keep <= DIRECT_BLOCKS && inode.pointers[INDIRECT] != 0 {
+            self.free_block(inode.pointers[INDIRECT])?;
+            inode.pointers[INDIRECT] = 0;
+        }
+
+        let outer = inode.pointers[DOUBLE_INDIRECT];
+        if outer == 0 {
+            return Ok(());
+        }
+
+        let first = DIRECT_BLOCKS + POINTERS_PER_BLOCK;
+        let mut table = self.read_block(outer)?;
+        for index in 0..POINTERS_PER_BLOCK {
+            let inner = u32_at(&table, index as usize * 4);
+            if inner != 0 && first + index * POINTERS_PER_BLOCK >= keep {
+                self.free_block(inner)?;
+                put_u32(&mut table, index as usize * 4, 0);
+            }
+        }
+
+        if keep <= first {
+            self.free_block(outer)?;
+            inode.pointers[DOUBLE_INDIRECT] = 0;
+        } else {
+            self.write_block(outer, table);
+        }
+
+        Ok(())
+    }
+
+    // Walks `path` from the root directory
+    fn find(&self, path: &str) -> Result<Inode, String> {
+        let mut inode = self.read_inode(ROOT_INODE)?;
+
+        for part in resolve_path("/", path) {
+            if inode.kind != KIND_DIRECTORY {
+                return Err(format!("'{}' is not a directory", part));
+            }
+
+            let entry = self
+                .entries(&inode)?
+                .into_iter()
+                .find(|entry| entry.name == part)
+                .ok_or_else(|| format!("'{}' not found", path))?;
+            inode = self.read_inode(entry.inode)?;
+        }
+
+        Ok(inode)
+    }
+
+    fn find_directory(&self, path: &str) -> Result<Inode, String> {
+        let inode = self.find(path)?;
+        if inode.kind != KIND_DIRECTORY {
+            return Err(format!("'{}' is not a directory", path));
+        }
+
+        Ok(inode)
+    }
+
+    fn entries(&self, directory: &Inode) -> Result<Vec<Entry>, String> {
+        let mut content = vec![0; directory.size as usize];
+        self.read_content(directory, 0, &mut content)?;
+
+        content
+            .chunks_exact(ENTRY_SIZE)
+            .map(|raw| {
+                let length = usize::from(raw[5]).min(MAX_NAME_LENGTH);
+                let name = core::str::from_utf8(&raw[6..6 + length])
+                    .map_err(|_| String::from("cfs directory holds an invalid name"))?;
+
+                Ok(Entry {
+                    name: String::from(name),
+                    inode: u32_at(raw, 0),
+                    kind: raw[4],
+                })
+            })
+            .collect()
+    }
+
+    // Adds an entry named after the last component of `path` for `inode` to its directory
+    fn add_entry(&mut self, path: &str, inode: &Inode) -> Result<(), String> {
+        let (parent, name) = split_path("/", path)?;
+        let mut directory = self.find_directory(&parent)?;
+
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(format!(
+                "'{}' is longer than the {} bytes cfs allows in a name",
+                name, MAX_NAME_LENGTH
+            ));
+        }
+        if self
+            .entries(&directory)?
+            .iter()
+            .any(|entry| entry.name == name)
+        {
+            return Err(format!("A node with the name '{}' already exists", name));
+        }
+
+        let mut raw = [0; ENTRY_SIZE];
+        put_u32(&mut raw, 0, inode.number);
+        raw[4] = inode.kind;
+        raw[5] = name.len() as u8;
+        raw[6..6 + name.len()].copy_from_slice(name.as_bytes());
+
+        let end = directory.size;
+        self.write_content(&mut directory, end, &raw)?;
+        directory.modified = clock::now();
+        self.write_inode(&directory)
+    }
+
+    // Removes the entry at `path` from its directory and returns the inode it named, the last
+    // entry takes the place of the removed one
+    fn remove_entry(&mut self, path: &str) -> Result<u32, String> {
+        let (parent, name) = split_path("/", path)?;
+        let mut directory = self.find_directory(&parent)?;
+        let entries = self.entries(&directory)?;
+
+        let position = entries
+            .iter()
+            .position(|entry| entry.name == name)
+            .ok_or_else(|| format!("Node '{}' not found in '{}'", name, parent))?;
+        let last = entries.len() - 1;
+
+        if position != last {
+            let mut raw = vec![0; ENTRY_SIZE];
+            self.read_content(&directory, (last * ENTRY_SIZE) as u64, &mut raw)?;
+            self.write_content(&mut directory, (position * ENTRY_SIZE) as u64, &raw)?;
+        }
+        self.resize_content(&mut directory, (last * ENTRY_SIZE) as u64)?;
+        directory.modified = clock::now();
+        self.write_inode(&directory)?;
+
+        Ok(entries[position].inode)
+    }
+
+    fn create_node(&mut self, path: &str, kind: u8, mode: u16) -> Result<Inode, String> {
+        let inode = Inode::new(self.allocate_inode()?, kind, mode);
+        self.write_inode(&inode)?;
+        self.add_entry(path, &inode)?;
+
+        Ok(inode)
+    }
+
+    // Drops a link to `number` and frees the inode and its blocks once nothing points at it
+    fn release(&mut self, number: u32) -> Result<(), String> {
+        let mut inode = self.read_inode(number)?;
+        inode.links -= 1;
+
+        if inode.links > 0 {
+            return self.write_inode(&inode);
+        }
+
+        self.resize_content(&mut inode, 0)?;
+        self.free_inode(number)
+    }
+
+    fn find_file(&self, path: &str) -> Result<Inode, String> {
+        let inode = self.find(path)?;
+
+        match inode.kind {
+            KIND_FILE => Ok(inode),
+            KIND_DIRECTORY => Err(format!("'{}' is a directory", path)),
+            _ => Err(format!("'{}' is a symbolic link", path)),
+        }
+    }
+}
+
+impl Vfs for CfsFs {
+    fn name(&self) -> &str {
+        "cfs"
+    }
+
+    fn device(&self) -> Option<&SharedBlockDevice> {
+        Some(&self.device)
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        Ok(self.find(path)?.node_kind())
+    }
+
+    fn create(&mut self, path: &str, kind: NodeKind) -> Result<(), String> {
+        let (kind, mode) = match kind {
+            NodeKind::File => (KIND_FILE, DEFAULT_FILE_MODE),
+            NodeKind::Directory => (KIND_DIRECTORY, DEFAULT_DIRECTORY_MODE),
+            NodeKind::Symlink => {
+                return Err(String::from("Symbolic links are created with symlink"))
+            }
+            NodeKind::CharDevice | NodeKind::BlockDevice => {
+                return Err(format!("{} does not support device nodes", self.name()))
+            }
+        };
+
+        self.transaction(|fs| fs.create_node(path, kind, mode).map(|_| ()))
+    }
+
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        let inode = self.find_file(path)?;
+        self.read_content(&inode, offset as u64, buffer)
+    }
+
+    fn write(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, String> {
+        self.transaction(|fs| {
+            let mut inode = fs.find_file(path)?;
+            // Writing past the end leaves a hole, which reads as zeros
+            fs.write_content(&mut inode, offset as u64, data)?;
+            inode.modified = clock::now();
+            fs.write_inode(&inode)?;
+
+            Ok(data.len())
+        })
+    }
+
+    fn truncate(&mut self, path: &str, len: usize) -> Result<(), String> {
+        self.transaction(|fs| {
+            let mut inode = fs.find_file(path)?;
+            fs.resize_content(&mut inode, len as u64)?;
+            inode.modified = clock::now();
+            fs.write_inode(&inode)
+        })
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let mut entries: Vec<DirEntry> = self
+            .entries(&self.find_directory(path)?)?
+            .into_iter()
+            .map(|entry| DirEntry {
+                name: entry.name,
+                kind: match entry.kind {
+                    KIND_DIRECTORY => NodeKind::Directory,
+                    KIND_SYMLINK => NodeKind::Symlink,
+                    _ => NodeKind::File,
+                },
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, path: &str) -> Result<(), String> {
+        self.transaction(|fs| {
+            let inode = fs.find(path)?;
+            if inode.number == ROOT_INODE {
+                return Err(String::from("Cannot remove the root directory"));
+            }
+            if inode.kind == KIND_DIRECTORY && inode.size > 0 {
+                let (_, name) = split_path("/", path)?;
+                return Err(format!("Directory '{}' is not empty", name));
+            }
+
+            let number = fs.remove_entry(path)?;
+            fs.release(number)
+        })
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), String> {
+        // A directory moved below itself would be detached from the tree
+        if to.starts_with(from) && to[from.len()..].starts_with('/') {
+            return Err(format!("Cannot move '{}' into itself", from));
+        }
+
+        self.transaction(|fs| {
+            let inode = fs.find(from)?;
+            if inode.number == ROOT_INODE {
+                return Err(String::from("Cannot move the root directory"));
+            }
+
+            fs.remove_entry(from)?;
+            fs.add_entry(to, &inode)
+        })
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<(), String> {
+        self.transaction(|fs| {
+            let mut inode = fs.find(existing)?;
+            if inode.kind == KIND_DIRECTORY {
+                return Err(format!("'{}' is a directory", existing));
+            }
+
+            inode.links += 1;
+            fs.write_inode(&inode)?;
+            fs.add_entry(new, &inode)
+        })
+    }
+
+    fn symlink(&mut self, target: &str, path: &str) -> Result<(), String> {
+        self.transaction(|fs| {
+            // Permissions of a symbolic link are never checked, only those of its target
+            let mut inode = fs.create_node(path, KIND_SYMLINK, 0o777)?;
+            fs.write_content(&mut inode, 0, target.as_bytes())?;
+            fs.write_inode(&inode)
+        })
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, String> {
+        let inode = self.find(path)?;
+        if inode.kind != KIND_SYMLINK {
+            return Err(format!("'{}' is not a symbolic link", path));
+        }
+
+        let mut target = vec![0; inode.size as usize];
+        self.read_content(&inode, 0, &mut target)?;
+        String::from_utf8(target).map_err(|_| format!("'{}' has an invalid target", path))
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let inode = self.find(path)?;
+        let size = match inode.kind {
+            KIND_DIRECTORY => inode.size as usize / ENTRY_SIZE,
+            _ => inode.size as usize,
+        };
+
+        Ok(Metadata {
+            kind: inode.node_kind(),
+            size,
+            inode: u64::from(inode.number),
+            links: inode.links as usize,
+            uid: inode.uid,
+            gid: inode.gid,
+            mode: inode.mode,
+            created: inode.created,
+            modified: inode.modified,
+            accessed: inode.accessed,
+        })
+    }
+
+    fn set_attributes(&mut self, path: &str, attributes: &Attributes) -> Result<(), String> {
+        self.transaction(|fs| {
+            let mut inode = fs.find(path)?;
+
+            inode.uid = attributes.uid.unwrap_or(inode.uid);
+            inode.gid = attributes.gid.unwrap_or(inode.gid);
+            inode.mode = attributes.mode.unwrap_or(inode.mode);
+            inode.created = attributes.created.unwrap_or(inode.created);
+            inode.modified = attributes.modified.unwrap_or(inode.modified);
+            inode.accessed = attributes.accessed.unwrap_or(inode.accessed);
+
+            fs.write_inode(&inode)
+        })
+    }
+}
+
+// Checksum of a journal header with its checksum field taken as zero
+fn header_checksum(header: &[u8]) -> u32 {
+    let mut covered = Vec::from(header);
+    covered[12..16].fill(0);
+
+    crc32(&covered)
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
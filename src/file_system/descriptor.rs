@@ -0,0 +1,88 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+
+// Upper bound on the number of files that can be open at the same time
+pub const MAX_OPEN_FILES: usize = 64;
+
+// Opaque reference to an open file, only meaningful to the `FileSystem` that returned it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileHandle(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    // Reading only, the file must exist
+    Read,
+    // Writing from the start, the file is created when missing and truncated otherwise
+    Write,
+    // Every write goes to the end of the file, which is created when missing
+    Append,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(usize),
+    Current(isize),
+    End(isize),
+}
+
+pub struct OpenFile {
+    pub path: String,
+    pub mode: OpenMode,
+    pub cursor: usize,
+}
+
+// Maps handles to open files, handle numbers are reused once closed
+#[derive(Default)]
+pub struct DescriptorTable {
+    files: BTreeMap<usize, OpenFile>,
+}
+
+impl DescriptorTable {
+    pub fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, file: OpenFile) -> Result<FileHandle, String> {
+        // Pick the lowest free slot, like Unix file descriptors
+        let slot = (0..MAX_OPEN_FILES)
+            .find(|slot| !self.files.contains_key(slot))
+            .ok_or_else(|| String::from("Too many open files"))?;
+
+        self.files.insert(slot, file);
+
+        Ok(FileHandle(slot))
+    }
+
+    pub fn get(&self, handle: FileHandle) -> Result<&OpenFile, String> {
+        self.files
+            .get(&handle.0)
+            .ok_or_else(|| format!("Invalid file handle {}", handle.0))
+    }
+
+    pub fn get_mut(&mut self, handle: FileHandle) -> Result<&mut OpenFile, String> {
+        self.files
+            .get_mut(&handle.0)
+            .ok_or_else(|| format!("Invalid file handle {}", handle.0))
+    }
+
+    pub fn remove(&mut self, handle: FileHandle) -> Result<OpenFile, String> {
+        self.files
+            .remove(&handle.0)
+            .ok_or_else(|| format!("Invalid file handle {}", handle.0))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OpenFile> {
+        self.files.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
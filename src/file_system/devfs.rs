@@ -0,0 +1,289 @@
+use super::vfs::{Attributes, DirEntry, Metadata, NodeKind, Vfs};
+use crate::devices::block::{self, SharedBlockDevice};
+use crate::devices::console::CONSOLE;
+use crate::{clock, serial::SERIAL1};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::{interrupts, port::Port, random::RdRand};
+
+// Character devices of the devfs, block devices are added after them in registration order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharDevice {
+    Console,
+    Null,
+    Random,
+    SerialPort,
+    Zero,
+}
+
+const CHAR_DEVICES: [(&str, CharDevice); 5] = [
+    ("console", CharDevice::Console),
+    ("null", CharDevice::Null),
+    ("random", CharDevice::Random),
+    ("ttyS0", CharDevice::SerialPort),
+    ("zero", CharDevice::Zero),
+];
+
+// Registers of the first serial port, the one behind `SERIAL1`
+const SERIAL_DATA: u16 = 0x3F8;
+const SERIAL_LINE_STATUS: u16 = 0x3FD;
+const SERIAL_DATA_READY: u8 = 1;
+
+const DEVICE_MODE: u16 = 0o666;
+// Writing to a disk goes around the filesystems mounted from it, so it is kept from other users
+const DISK_MODE: u16 = 0o660;
+const DIRECTORY_MODE: u16 = 0o755;
+
+// State of the generator behind `random` when the CPU has no RDRAND, seeded on first use
+static RANDOM_STATE: AtomicU64 = AtomicU64::new(0);
+
+enum Node {
+    Root,
+    Char(usize, CharDevice),
+    Block(usize, SharedBlockDevice),
+}
+
+// Device files, usually mounted at `/dev`
+//
+// The character devices are fixed, the block devices are the ones in the `block` registry at the
+// time of each lookup. Devices cannot be created, removed or renamed, and writing past the end of a
+// disk fails instead of growing it.
+pub struct DevFs;
+
+impl DevFs {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn find(&self, path: &str) -> Result<Node, String> {
+        let name = path.trim_start_matches('/');
+        if name.is_empty() {
+            return Ok(Node::Root);
+        }
+
+        if let Some(index) = CHAR_DEVICES.iter().position(|(device, _)| *device == name) {
+            return Ok(Node::Char(index, CHAR_DEVICES[index].1));
+        }
+
+        block::devices()
+            .into_iter()
+            .enumerate()
+            .find(|(_, (device, _))| device == name)
+            .map(|(index, (_, device))| Node::Block(index, device))
+            .ok_or_else(|| format!("'{}' not found", path))
+    }
+
+    fn fixed(&self) -> Result<(), String> {
+        Err(String::from(
+            "Devices in dev cannot be created, removed or renamed",
+        ))
+    }
+}
+
+impl Default for DevFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vfs for DevFs {
+    fn name(&self) -> &str {
+        "dev"
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        Ok(match self.find(path)? {
+            Node::Root => NodeKind::Directory,
+            Node::Char(..) => NodeKind::CharDevice,
+            Node::Block(..) => NodeKind::BlockDevice,
+        })
+    }
+
+    fn create(&mut self, _path: &str, _kind: NodeKind) -> Result<(), String> {
+        self.fixed()
+    }
+
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        match self.find(path)? {
+            Node::Root => Err(format!("'{}' is a directory", path)),
+            // Keyboard input belongs to the shell, so the console is always at end of file
+            Node::Char(_, CharDevice::Console | CharDevice::Null) => Ok(0),
+            Node::Char(_, CharDevice::Zero) => {
+                buffer.fill(0);
+                Ok(buffer.len())
+            }
+            Node::Char(_, CharDevice::Random) => {
+                fill_random(buffer);
+                Ok(buffer.len())
+            }
+            Node::Char(_, CharDevice::SerialPort) => Ok(read_serial(buffer)),
+            Node::Block(_, device) => {
+                let size = device.lock().size();
+                let start = (offset as u64).min(size);
+                let count = buffer.len().min((size - start) as usize);
+                if count > 0 {
+                    block::read_bytes(&device, start, &mut buffer[..count])?;
+                }
+                Ok(count)
+            }
+        }
+    }
+
+    fn write(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, String> {
+        match self.find(path)? {
+            Node::Root => Err(format!("'{}' is a directory", path)),
+            Node::Char(_, CharDevice::Console) => {
+                interrupts::without_interrupts(|| {
+                    let mut console = CONSOLE.lock();
+                    for character in String::from_utf8_lossy(data).chars() {
+                        console.print_char_and_move_cursor(character);
+                    }
+                });
+                Ok(data.len())
+            }
+            Node::Char(_, CharDevice::SerialPort) => {
+                interrupts::without_interrupts(|| {
+                    let mut serial = SERIAL1.lock();
+                    // `send` turns backspace and delete into erase sequences, files must arrive
+                    // byte for byte
+                    for &byte in data {
+                        serial.send_raw(byte);
+                    }
+                });
+                Ok(data.len())
+            }
+            // Everything written to the others is thrown away
+            Node::Char(..) => Ok(data.len()),
+            Node::Block(_, device) => {
+                let size = device.lock().size();
+                if offset as u64 + data.len() as u64 > size {
+                    return Err(format!("'{}' ends at byte {}", path, size));
+                }
+                block::write_bytes(&device, offset as u64, data)?;
+                Ok(data.len())
+            }
+        }
+    }
+
+    // Devices have no length to cut, so truncating succeeds and does nothing, which lets `>`
+    // redirect into them
+    fn truncate(&mut self, path: &str, _len: usize) -> Result<(), String> {
+        match self.find(path)? {
+            Node::Root => Err(format!("'{}' is a directory", path)),
+            _ => Ok(()),
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        if !matches!(self.find(path)?, Node::Root) {
+            return Err(format!("'{}' is not a directory", path));
+        }
+
+        let mut entries: Vec<DirEntry> = CHAR_DEVICES
+            .iter()
+            .map(|(name, _)| DirEntry {
+                name: String::from(*name),
+                kind: NodeKind::CharDevice,
+            })
+            .collect();
+        entries.extend(block::devices().into_iter().map(|(name, _)| DirEntry {
+            name,
+            kind: NodeKind::BlockDevice,
+        }));
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), String> {
+        self.fixed()
+    }
+
+    fn rename(&mut self, _from: &str, _to: &str) -> Result<(), String> {
+        self.fixed()
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let (kind, size, inode, mode) = match self.find(path)? {
+            Node::Root => (
+                NodeKind::Directory,
+                CHAR_DEVICES.len() + block::devices().len(),
+                1,
+                DIRECTORY_MODE,
+            ),
+            Node::Char(index, _) => (NodeKind::CharDevice, 0, index as u64 + 2, DEVICE_MODE),
+            Node::Block(index, device) => (
+                NodeKind::BlockDevice,
+                device.lock().size() as usize,
+                (CHAR_DEVICES.len() + index) as u64 + 2,
+                DISK_MODE,
+            ),
+        };
+
+        // The devices exist from boot on
+        let boot = clock::now().saturating_sub(clock::uptime_ms() / 1000);
+        Ok(Metadata {
+            kind,
+            size,
+            inode,
+            links: 1,
+            uid: 0,
+            gid: 0,
+            mode,
+            created: boot,
+            modified: boot,
+            accessed: boot,
+        })
+    }
+
+    fn set_attributes(&mut self, path: &str, _attributes: &Attributes) -> Result<(), String> {
+        self.find(path)?;
+        Err(String::from("Attributes of devices in dev are fixed"))
+    }
+}
+
+// Takes the bytes the serial port already received, without waiting for more
+fn read_serial(buffer: &mut [u8]) -> usize {
+    interrupts::without_interrupts(|| {
+        // Held so that nothing else uses the port meanwhile
+        let _serial = SERIAL1.lock();
+        let mut line_status: Port<u8> = Port::new(SERIAL_LINE_STATUS);
+        let mut data: Port<u8> = Port::new(SERIAL_DATA);
+
+        let mut count = 0;
+        while count < buffer.len() && unsafe { line_status.read() } & SERIAL_DATA_READY != 0 {
+            buffer[count] = unsafe { data.read() };
+            count += 1;
+        }
+
+        count
+    })
+}
+
+// Uses RDRAND when the CPU has it and falls back to xorshift64, which is not fit for cryptography
+fn fill_random(buffer: &mut [u8]) {
+    let rdrand = RdRand::new();
+
+    for chunk in buffer.chunks_mut(8) {
+        let value = rdrand
+            .and_then(|rdrand| rdrand.get_u64())
+            .unwrap_or_else(next_xorshift);
+        chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+    }
+}
+
+fn next_xorshift() -> u64 {
+    let mut state = RANDOM_STATE.load(Ordering::Relaxed);
+    if state == 0 {
+        // The cycle counter differs from boot to boot, the zero state would never leave zero
+        state = unsafe { core::arch::x86_64::_rdtsc() } | 1;
+    }
+
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    RANDOM_STATE.store(state, Ordering::Relaxed);
+
+    state
+}
@@ -0,0 +1,472 @@
+use super::vfs::{Attributes, DirEntry, Metadata, NodeKind, Vfs};
+use crate::devices::block::{self, SharedBlockDevice};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// The superblock always sits 1024 bytes into the volume, whatever the block size
+const SUPERBLOCK_OFFSET: u64 = 1024;
+const SUPERBLOCK_SIZE: usize = 1024;
+const MAGIC: u16 = 0xEF53;
+
+const ROOT_INODE: u32 = 2;
+// Size of the inodes of revision 0 volumes and of the fields read from larger ones
+const INODE_BASE_SIZE: usize = 128;
+const GROUP_DESCRIPTOR_SIZE: u64 = 32;
+
+// Incompatible features that change how the volume has to be read, mounting fails on any other
+const INCOMPAT_FILETYPE: u32 = 0x0002;
+const INCOMPAT_RECOVER: u32 = 0x0004;
+// Flexible block groups only move the bitmaps and inode tables, which are found through the
+// group descriptors either way
+const INCOMPAT_FLEX_BG: u32 = 0x0200;
+const SUPPORTED_INCOMPAT: u32 = INCOMPAT_FILETYPE | INCOMPAT_FLEX_BG;
+
+const MODE_TYPE: u16 = 0xF000;
+const MODE_DIRECTORY: u16 = 0x4000;
+const MODE_REGULAR: u16 = 0x8000;
+const MODE_SYMLINK: u16 = 0xA000;
+
+// File type stored in directory entries with the filetype feature
+const TYPE_DIRECTORY: u8 = 2;
+const TYPE_SYMLINK: u8 = 7;
+
+// Block pointers in an inode: 12 direct ones, then a single, double and triple indirect one
+const DIRECT_BLOCKS: u64 = 12;
+// Symbolic links with a shorter target keep it in the block pointers instead of a block
+const FAST_SYMLINK_SIZE: u64 = 60;
+
+// Partition type of Linux filesystems in an MBR partition table
+const LINUX_PARTITION_TYPE: u8 = 0x83;
+
+struct Inode {
+    number: u32,
+    mode: u16,
+    uid: u32,
+    gid: u32,
+    size: u64,
+    accessed: u32,
+    changed: u32,
+    modified: u32,
+    links: u16,
+    // 512-byte sectors allocated to the inode, including its extended attribute block
+    sectors: u32,
+    attribute_block: u32,
+    // The 15 block pointers, or the target of a fast symbolic link
+    block: [u8; 60],
+}
+
+impl Inode {
+    fn kind(&self) -> NodeKind {
+        match self.mode & MODE_TYPE {
+            MODE_DIRECTORY => NodeKind::Directory,
+            MODE_SYMLINK => NodeKind::Symlink,
+            _ => NodeKind::File,
+        }
+    }
+
+    fn pointer(&self, index: usize) -> u32 {
+        u32_at(&self.block, index * 4)
+    }
+}
+
+// Returns whether `start`, the first bytes of a device, hold the superblock of an ext2 volume
+pub fn is_volume(start: &[u8]) -> bool {
+    let magic = SUPERBLOCK_OFFSET as usize + 56;
+    start.len() >= magic + 2 && u16_at(start, magic) == MAGIC
+}
+
+// Read-only ext2 volume on a block device, either the whole device or the first Linux partition
+// of its MBR partition table
+//
+// Like the FAT driver nothing is cached, every lookup reads the inodes and directory blocks it
+// passes through. Files are read through their direct and indirect block pointers, so ext4
+// volumes using extents are refused at mount time. Devices, FIFOs and sockets are listed as files
+// but cannot be read.
+pub struct Ext2Fs {
+    device: SharedBlockDevice,
+    // Byte offset of the volume on the device
+    start: u64,
+    block_size: u64,
+    inode_size: u64,
+    inode_count: u32,
+    inodes_per_group: u32,
+    // Block holding the first group descriptor
+    descriptor_block: u64,
+    // Directory entries carry the type of the node, older volumes only have it in the inode
+    has_file_types: bool,
+}
+
+impl Ext2Fs {
+    // Reads the superblock and mounts the volume found on `device`
+    pub fn new(device: SharedBlockDevice) -> Result<Self, String> {
+        let mut superblock = vec![0; SUPERBLOCK_SIZE];
+        block::read_bytes(&device, SUPERBLOCK_OFFSET, &mut superblock)?;
+
+        let mut start = 0;
+        if u16_at(&superblock, 56) != MAGIC {
+            let mut mbr = vec![0; 512];
+            block::read_bytes(&device, 0, &mut mbr)?;
+
+            let sector_size = device.lock().sector_size() as u64;
+            let partition = block::mbr_partitions(&mbr)
+                .into_iter()
+                .find(|partition| partition.kind == LINUX_PARTITION_TYPE)
+                .ok_or_else(|| String::from("No ext2 filesystem found on the device"))?;
+
+            start = partition.start * sector_size;
+            block::read_bytes(&device, start + SUPERBLOCK_OFFSET, &mut superblock)?;
+            if u16_at(&superblock, 56) != MAGIC {
+                return Err(String::from("Partition does not hold an ext2 filesystem"));
+            }
+        }
+
+        let incompatible = u32_at(&superblock, 96);
+        if incompatible & INCOMPAT_RECOVER != 0 {
+            return Err(String::from(
+                "Journal needs recovery, mount the volume on Linux first",
+            ));
+        }
+        if incompatible & !SUPPORTED_INCOMPAT != 0 {
+            return Err(format!(
+                "Unsupported ext2 features {:#x}",
+                incompatible & !SUPPORTED_INCOMPAT
+            ));
+        }
+
+        let log_block_size = u32_at(&superblock, 24);
+        if log_block_size > 6 {
+            return Err(String::from("Corrupted ext2 superblock"));
+        }
+        let block_size = 1024 << log_block_size;
+
+        // Revision 0 volumes have fixed size inodes
+        let inode_size = match u32_at(&superblock, 76) {
+            0 => INODE_BASE_SIZE as u64,
+            _ => u64::from(u16_at(&superblock, 88)),
+        };
+        let inodes_per_group = u32_at(&superblock, 40);
+        if inode_size < INODE_BASE_SIZE as u64
+            || !inode_size.is_power_of_two()
+            || inode_size > block_size
+            || inodes_per_group == 0
+        {
+            return Err(String::from("Corrupted ext2 superblock"));
+        }
+
+        Ok(Self {
+            device,
+            start,
+            block_size,
+            inode_size,
+            inode_count: u32_at(&superblock, 0),
+            inodes_per_group,
+            descriptor_block: u64::from(u32_at(&superblock, 20)) + 1,
+            has_file_types: incompatible & INCOMPAT_FILETYPE != 0,
+        })
+    }
+
+    fn read_bytes(&self, offset: u64, buffer: &mut [u8]) -> Result<(), String> {
+        block::read_bytes(&self.device, self.start + offset, buffer)
+    }
+
+    fn read_inode(&self, number: u32) -> Result<Inode, String> {
+        if number == 0 || number > self.inode_count {
+            return Err(format!("Inode {} does not exist", number));
+        }
+
+        let group = u64::from((number - 1) / self.inodes_per_group);
+        let index = u64::from((number - 1) % self.inodes_per_group);
+
+        let mut descriptor = [0; GROUP_DESCRIPTOR_SIZE as usize];
+        self.read_bytes(
+            self.descriptor_block * self.block_size + group * GROUP_DESCRIPTOR_SIZE,
+            &mut descriptor,
+        )?;
+        let table = u64::from(u32_at(&descriptor, 8));
+
+        let mut raw = [0; INODE_BASE_SIZE];
+        self.read_bytes(table * self.block_size + index * self.inode_size, &mut raw)?;
+
+        let mode = u16_at(&raw, 0);
+        // Regular files keep the upper half of their size where directories keep their ACL
+        let size_high = if mode & MODE_TYPE == MODE_REGULAR {
+            u64::from(u32_at(&raw, 108))
+        } else {
+            0
+        };
+
+        Ok(Inode {
+            number,
+            mode,
+            // The upper halves of the owner ids are in the Linux specific part of the inode
+            uid: u32::from(u16_at(&raw, 2)) | u32::from(u16_at(&raw, 120)) << 16,
+            gid: u32::from(u16_at(&raw, 24)) | u32::from(u16_at(&raw, 122)) << 16,
+            size: u64::from(u32_at(&raw, 4)) | size_high << 32,
+            accessed: u32_at(&raw, 8),
+            changed: u32_at(&raw, 12),
+            modified: u32_at(&raw, 16),
+            links: u16_at(&raw, 26),
+            sectors: u32_at(&raw, 28),
+            attribute_block: u32_at(&raw, 104),
+            block: raw[40..100].try_into().unwrap(),
+        })
+    }
+
+    // Returns the block holding block `index` of the inode's data, 0 for a hole
+    fn data_block(&self, inode: &Inode, index: u64) -> Result<u64, String> {
+        let per_block = self.block_size / 4;
+
+        if index < DIRECT_BLOCKS {
+            return Ok(u64::from(inode.pointer(index as usize)));
+        }
+
+        // Walk down the indirect tree, `span` is the number of data blocks below each pointer
+        let mut index = index - DIRECT_BLOCKS;
+        let mut span = 1;
+        for level in 0..3 {
+            let span_total = span * per_block;
+            if index < span_total {
+                let mut block = u64::from(inode.pointer(DIRECT_BLOCKS as usize + level));
+                let mut span = span;
+                while block != 0 {
+                    let mut pointer = [0; 4];
+                    self.read_bytes(block * self.block_size + index / span * 4, &mut pointer)?;
+                    block = u64::from(u32::from_le_bytes(pointer));
+
+                    if span == 1 {
+                        break;
+                    }
+                    index %= span;
+                    span /= per_block;
+                }
+                return Ok(block);
+            }
+            index -= span_total;
+            span = span_total;
+        }
+
+        Err(format!(
+            "Block {} of inode {} is out of range",
+            index, inode.number
+        ))
+    }
+
+    // Reads the inode's data at `offset` into `buffer`, which must not extend past its size
+    fn read_data(&self, inode: &Inode, offset: u64, buffer: &mut [u8]) -> Result<(), String> {
+        let mut done = 0;
+
+        while done < buffer.len() {
+            let position = offset + done as u64;
+            let within = position % self.block_size;
+            let count = ((self.block_size - within) as usize).min(buffer.len() - done);
+            let piece = &mut buffer[done..done + count];
+
+            match self.data_block(inode, position / self.block_size)? {
+                0 => piece.fill(0),
+                block => self.read_bytes(block * self.block_size + within, piece)?,
+            }
+            done += count;
+        }
+
+        Ok(())
+    }
+
+    // Lists the entries of a directory except `.` and `..` as name, inode and kind
+    fn read_directory(&self, inode: &Inode) -> Result<Vec<(String, u32, NodeKind)>, String> {
+        let mut data = vec![0; inode.size as usize];
+        self.read_data(inode, 0, &mut data)?;
+
+        let mut entries = Vec::new();
+        let mut position = 0;
+        while position + 8 <= data.len() {
+            let number = u32_at(&data, position);
+            let record_length = usize::from(u16_at(&data, position + 4));
+            // Without file types the name length takes both bytes
+            let name_length = match self.has_file_types {
+                true => usize::from(data[position + 6]),
+                false => usize::from(u16_at(&data, position + 6)),
+            };
+
+            if record_length < 8
+                || position + record_length > data.len()
+                || 8 + name_length > record_length
+            {
+                return Err(format!(
+                    "Corrupted directory entry in inode {}",
+                    inode.number
+                ));
+            }
+
+            let name = &data[position + 8..position + 8 + name_length];
+            if number != 0 && name != b"." && name != b".." {
+                let kind = match (self.has_file_types, data[position + 7]) {
+                    (true, TYPE_DIRECTORY) => NodeKind::Directory,
+                    (true, TYPE_SYMLINK) => NodeKind::Symlink,
+                    (true, _) => NodeKind::File,
+                    (false, _) => self.read_inode(number)?.kind(),
+                };
+                entries.push((String::from_utf8_lossy(name).into_owned(), number, kind));
+            }
+
+            position += record_length;
+        }
+
+        Ok(entries)
+    }
+
+    // Walks `path` from the root directory
+    fn find(&self, path: &str) -> Result<Inode, String> {
+        let mut inode = self.read_inode(ROOT_INODE)?;
+
+        for part in path.split('/').filter(|part| !part.is_empty()) {
+            if inode.kind() != NodeKind::Directory {
+                return Err(format!("'{}' is not a directory", part));
+            }
+
+            let (_, number, _) = self
+                .read_directory(&inode)?
+                .into_iter()
+                .find(|(name, _, _)| name == part)
+                .ok_or_else(|| format!("'{}' not found", path))?;
+            inode = self.read_inode(number)?;
+        }
+
+        Ok(inode)
+    }
+
+    fn read_only(&self) -> Result<(), String> {
+        Err(String::from("ext2 is mounted read-only"))
+    }
+}
+
+impl Vfs for Ext2Fs {
+    fn name(&self) -> &str {
+        "ext2"
+    }
+
+    fn device(&self) -> Option<&SharedBlockDevice> {
+        Some(&self.device)
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        Ok(self.find(path)?.kind())
+    }
+
+    fn create(&mut self, _path: &str, _kind: NodeKind) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        let inode = self.find(path)?;
+        match inode.mode & MODE_TYPE {
+            MODE_REGULAR => {}
+            MODE_DIRECTORY => return Err(format!("'{}' is a directory", path)),
+            MODE_SYMLINK => return Err(format!("'{}' is a symbolic link", path)),
+            _ => return Err(format!("'{}' is not a regular file", path)),
+        }
+
+        let start = (offset as u64).min(inode.size);
+        let count = (buffer.len() as u64).min(inode.size - start) as usize;
+        self.read_data(&inode, start, &mut buffer[..count])?;
+
+        Ok(count)
+    }
+
+    fn write(&mut self, _path: &str, _offset: usize, _data: &[u8]) -> Result<usize, String> {
+        self.read_only().map(|_| 0)
+    }
+
+    fn truncate(&mut self, _path: &str, _len: usize) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let inode = self.find(path)?;
+        if inode.kind() != NodeKind::Directory {
+            return Err(format!("'{}' is not a directory", path));
+        }
+
+        let entries = self
+            .read_directory(&inode)?
+            .into_iter()
+            .map(|(name, _, kind)| DirEntry { name, kind })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn rename(&mut self, _from: &str, _to: &str) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn link(&mut self, _existing: &str, _new: &str) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn symlink(&mut self, _target: &str, _path: &str) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, String> {
+        let inode = self.find(path)?;
+        if inode.kind() != NodeKind::Symlink {
+            return Err(format!("'{}' is not a symbolic link", path));
+        }
+
+        // A fast link has no data blocks, apart from a possible extended attribute block
+        let attribute_sectors = match inode.attribute_block {
+            0 => 0,
+            _ => (self.block_size / 512) as u32,
+        };
+        let target = if inode.sectors == attribute_sectors && inode.size < FAST_SYMLINK_SIZE {
+            inode.block[..inode.size as usize].to_vec()
+        } else {
+            let mut target = vec![0; inode.size as usize];
+            self.read_data(&inode, 0, &mut target)?;
+            target
+        };
+
+        Ok(String::from_utf8_lossy(&target).into_owned())
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let inode = self.find(path)?;
+
+        let size = match inode.kind() {
+            NodeKind::Directory => self.read_directory(&inode)?.len(),
+            _ => inode.size as usize,
+        };
+
+        // ext2 has no creation time, the inode change time is the closest there is
+        Ok(Metadata {
+            kind: inode.kind(),
+            size,
+            inode: u64::from(inode.number),
+            links: usize::from(inode.links),
+            uid: inode.uid,
+            gid: inode.gid,
+            mode: inode.mode & 0o777,
+            created: u64::from(inode.changed),
+            modified: u64::from(inode.modified),
+            accessed: u64::from(inode.accessed),
+        })
+    }
+
+    fn set_attributes(&mut self, _path: &str, _attributes: &Attributes) -> Result<(), String> {
+        self.read_only()
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
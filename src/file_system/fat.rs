@@ -0,0 +1,1293 @@
+use super::split_path;
+use super::vfs::{
+    Attributes, DirEntry, Metadata, NodeKind, Vfs, DEFAULT_DIRECTORY_MODE, DEFAULT_FILE_MODE,
+};
+use crate::clock::{self, DateTime};
+use crate::devices::block::{self, SharedBlockDevice};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const ENTRY_SIZE: usize = 32;
+
+const ATTRIBUTE_READ_ONLY: u8 = 0x01;
+const ATTRIBUTE_VOLUME_ID: u8 = 0x08;
+const ATTRIBUTE_DIRECTORY: u8 = 0x10;
+const ATTRIBUTE_ARCHIVE: u8 = 0x20;
+// Read-only, hidden, system and volume id together mark a long name entry
+const ATTRIBUTE_LONG_NAME: u8 = 0x0F;
+
+// First name byte of a deleted entry and of the entry ending the directory
+const ENTRY_DELETED: u8 = 0xE5;
+const ENTRY_END: u8 = 0x00;
+
+// Ordinal flag of the long name entry holding the end of the name, which is stored first
+const LAST_LONG_ENTRY: u8 = 0x40;
+const CHARS_PER_LONG_ENTRY: usize = 13;
+// Byte offsets of the UTF-16 characters inside a long name entry
+const LONG_NAME_OFFSETS: [usize; CHARS_PER_LONG_ENTRY] =
+    [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+const MAX_NAME_LENGTH: usize = 255;
+
+// Windows NT flags in the reserved byte, set when the base or extension of a short name is
+// displayed in lowercase
+const LOWERCASE_BASE: u8 = 0x08;
+const LOWERCASE_EXTENSION: u8 = 0x10;
+
+// Partition types of FAT16 and FAT32 volumes in an MBR partition table
+const FAT_PARTITION_TYPES: [u8; 5] = [0x04, 0x06, 0x0B, 0x0C, 0x0E];
+
+// FAT32 volumes keep a hint of the free cluster count in the FSInfo sector
+const FSINFO_SIGNATURE: u32 = 0x4161_5252;
+const FSINFO_FREE_COUNT: u64 = 488;
+
+// Stands in for the root directory, which has no entry of its own
+const ROOT_INODE: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FatKind {
+    Fat16,
+    Fat32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directory {
+    // The FAT16 root directory, a fixed region in front of the data area
+    FixedRoot,
+    // A directory stored in a cluster chain starting at the given cluster
+    Clusters(u32),
+}
+
+// A file or directory as found in its parent directory
+#[derive(Debug, Clone)]
+struct FatEntry {
+    name: String,
+    // The short entry as stored on disk
+    raw: [u8; ENTRY_SIZE],
+    // Device offsets of the slots holding the long name followed by the one of the short entry
+    slots: Vec<u64>,
+}
+
+impl FatEntry {
+    fn attributes(&self) -> u8 {
+        self.raw[11]
+    }
+
+    fn kind(&self) -> NodeKind {
+        if self.attributes() & ATTRIBUTE_DIRECTORY != 0 {
+            NodeKind::Directory
+        } else {
+            NodeKind::File
+        }
+    }
+
+    fn first_cluster(&self) -> u32 {
+        entry_cluster(&self.raw)
+    }
+
+    fn set_first_cluster(&mut self, cluster: u32) {
+        set_entry_cluster(&mut self.raw, cluster);
+    }
+
+    fn size(&self) -> usize {
+        u32_at(&self.raw, 28) as usize
+    }
+
+    fn set_size(&mut self, size: usize) {
+        self.raw[28..32].copy_from_slice(&(size as u32).to_le_bytes());
+    }
+
+    // Device offset of the short entry, which identifies the node while it keeps its name
+    fn offset(&self) -> u64 {
+        *self.slots.last().unwrap()
+    }
+
+    // Names are compared like Windows does, ignoring ASCII case and accepting the short name
+    fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || display_short_name(&self.raw).eq_ignore_ascii_case(name)
+    }
+
+    // Records a change to the file content
+    fn touch(&mut self) {
+        let (date, time) = fat_date_time(clock::now());
+        self.raw[11] |= ATTRIBUTE_ARCHIVE;
+        self.raw[22..24].copy_from_slice(&time.to_le_bytes());
+        self.raw[24..26].copy_from_slice(&date.to_le_bytes());
+        self.raw[18..20].copy_from_slice(&date.to_le_bytes());
+    }
+}
+
+enum Node {
+    Root,
+    Entry(FatEntry),
+}
+
+// FAT16 or FAT32 volume on a block device, either the whole device or the first FAT partition
+// of its MBR partition table
+//
+// Nothing is cached, every operation reads the directories and the allocation table it needs
+// from the device and writes changes back before returning. Names are matched ignoring ASCII
+// case, new names that do not fit 8.3 get a long name with a generated `NAME~N.EXT` short name.
+// FAT has no owners and only a read-only flag, so every node belongs to root and the permission
+// bits are derived from that flag. Inode numbers are the device offsets of the short entries,
+// so unlike on other backends they change when a node is renamed or moved and are reused once
+// it is deleted.
+pub struct FatFs {
+    device: SharedBlockDevice,
+    kind: FatKind,
+    // Byte offsets on the device of the first allocation table, the FAT16 root directory and
+    // the first cluster
+    fat_start: u64,
+    root_start: u64,
+    data_start: u64,
+    // Bytes per allocation table, each copy gets every update
+    fat_size: u64,
+    fat_count: u64,
+    root_entries: usize,
+    root_cluster: u32,
+    cluster_size: usize,
+    // Clusters are numbered from 2, so the valid ones are 2..cluster_count + 2
+    cluster_count: u32,
+    // Where the search for a free cluster continues
+    next_free: u32,
+    // The FSInfo sector until its free cluster count was marked as unknown
+    fsinfo: Option<u64>,
+}
+
+impl FatFs {
+    // Reads the boot sector and mounts the volume found on `device`
+    pub fn new(device: SharedBlockDevice) -> Result<Self, String> {
+        let (sector_size, device_size) = {
+            let device = device.lock();
+            (device.sector_size(), device.size())
+        };
+        if sector_size < 512 {
+            return Err(format!("Unsupported sector size {}", sector_size));
+        }
+
+        let mut boot = vec![0; sector_size];
+        device.lock().read_sectors(0, &mut boot)?;
+
+        let mut volume_start = 0;
+        if !is_boot_sector(&boot) {
+            let partition = block::mbr_partitions(&boot)
+                .into_iter()
+                .find(|partition| FAT_PARTITION_TYPES.contains(&partition.kind))
+                .ok_or_else(|| String::from("No FAT filesystem found on the device"))?;
+            volume_start = partition.start * sector_size as u64;
+            block::read_bytes(&device, volume_start, &mut boot)?;
+            if !is_boot_sector(&boot) {
+                return Err(String::from("Partition does not hold a FAT filesystem"));
+            }
+        }
+
+        let bytes_per_sector = u64::from(u16_at(&boot, 11));
+        let sectors_per_cluster = u64::from(boot[13]);
+        let reserved_sectors = u64::from(u16_at(&boot, 14));
+        let fat_count = u64::from(boot[16]);
+        let root_entries = usize::from(u16_at(&boot, 17));
+        let total_sectors = match u16_at(&boot, 19) {
+            0 => u64::from(u32_at(&boot, 32)),
+            count => u64::from(count),
+        };
+
+        // FAT32 volumes leave the 16-bit table size at 0, like Linux we go by that rather than
+        // by the cluster count so small FAT32 images work too
+        let (kind, fat_sectors) = match u16_at(&boot, 22) {
+            0 => (FatKind::Fat32, u64::from(u32_at(&boot, 36))),
+            sectors => (FatKind::Fat16, u64::from(sectors)),
+        };
+
+        let root_sectors = (root_entries * ENTRY_SIZE).div_ceil(bytes_per_sector as usize) as u64;
+        let data_sector = reserved_sectors + fat_count * fat_sectors + root_sectors;
+        if fat_sectors == 0 || data_sector >= total_sectors {
+            return Err(String::from("Corrupted FAT boot sector"));
+        }
+
+        let entry_size = match kind {
+            FatKind::Fat16 => 2,
+            FatKind::Fat32 => 4,
+        };
+        let fat_size = fat_sectors * bytes_per_sector;
+        let cluster_count = ((total_sectors - data_sector) / sectors_per_cluster)
+            .min(fat_size / entry_size - 2)
+            .min(0x0FFF_FFF5) as u32;
+
+        if kind == FatKind::Fat16 && cluster_count < 4085 {
+            return Err(String::from("FAT12 is not supported"));
+        }
+        if volume_start + total_sectors * bytes_per_sector > device_size {
+            return Err(String::from("FAT filesystem is larger than the device"));
+        }
+
+        let root_cluster = match kind {
+            FatKind::Fat16 => 0,
+            FatKind::Fat32 => u32_at(&boot, 44),
+        };
+        let fsinfo = match (kind, u16_at(&boot, 48)) {
+            (FatKind::Fat32, sector) if sector != 0 && sector != 0xFFFF => {
+                Some(volume_start + u64::from(sector) * bytes_per_sector)
+            }
+            _ => None,
+        };
+
+        let fs = Self {
+            device,
+            kind,
+            fat_start: volume_start + reserved_sectors * bytes_per_sector,
+            root_start: volume_start
+                + (reserved_sectors + fat_count * fat_sectors) * bytes_per_sector,
+            data_start: volume_start + data_sector * bytes_per_sector,
+            fat_size,
+            fat_count,
+            root_entries,
+            root_cluster,
+            cluster_size: (sectors_per_cluster * bytes_per_sector) as usize,
+            cluster_count,
+            next_free: 2,
+            fsinfo,
+        };
+
+        if kind == FatKind::Fat32 && !fs.is_valid_cluster(root_cluster) {
+            return Err(String::from("Corrupted FAT boot sector"));
+        }
+
+        Ok(fs)
+    }
+
+    fn read_bytes(&self, offset: u64, buffer: &mut [u8]) -> Result<(), String> {
+        block::read_bytes(&self.device, offset, buffer)
+    }
+
+    fn write_bytes(&self, offset: u64, data: &[u8]) -> Result<(), String> {
+        block::write_bytes(&self.device, offset, data)
+    }
+
+    fn is_valid_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && cluster < self.cluster_count + 2
+    }
+
+    fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_start + u64::from(cluster - 2) * self.cluster_size as u64
+    }
+
+    fn end_of_chain(&self) -> u32 {
+        match self.kind {
+            FatKind::Fat16 => 0xFFFF,
+            FatKind::Fat32 => 0x0FFF_FFFF,
+        }
+    }
+
+    fn fat_entry_size(&self) -> u64 {
+        match self.kind {
+            FatKind::Fat16 => 2,
+            FatKind::Fat32 => 4,
+        }
+    }
+
+    // Decodes an allocation table entry, FAT32 entries only use their low 28 bits
+    fn decode_fat_entry(&self, bytes: &[u8]) -> u32 {
+        match self.kind {
+            FatKind::Fat16 => u32::from(u16_at(bytes, 0)),
+            FatKind::Fat32 => u32_at(bytes, 0) & 0x0FFF_FFFF,
+        }
+    }
+
+    fn fat_entry(&self, cluster: u32) -> Result<u32, String> {
+        let mut bytes = [0; 4];
+        let size = self.fat_entry_size();
+        self.read_bytes(
+            self.fat_start + u64::from(cluster) * size,
+            &mut bytes[..size as usize],
+        )?;
+
+        Ok(self.decode_fat_entry(&bytes))
+    }
+
+    // Updates the entry of `cluster` in every copy of the allocation table
+    fn set_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), String> {
+        let size = self.fat_entry_size();
+        let offset = u64::from(cluster) * size;
+
+        for copy in 0..self.fat_count {
+            let position = self.fat_start + copy * self.fat_size + offset;
+            match self.kind {
+                FatKind::Fat16 => self.write_bytes(position, &(value as u16).to_le_bytes())?,
+                FatKind::Fat32 => {
+                    // The top 4 bits are reserved and kept as they are
+                    let mut bytes = [0; 4];
+                    self.read_bytes(position, &mut bytes)?;
+                    let value = (u32::from_le_bytes(bytes) & 0xF000_0000) | value;
+                    self.write_bytes(position, &value.to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns the clusters of the chain starting at `first`, which is empty for cluster 0
+    fn chain(&self, first: u32) -> Result<Vec<u32>, String> {
+        let mut chain = Vec::new();
+        let mut cluster = first;
+
+        while cluster != 0 {
+            if !self.is_valid_cluster(cluster) || chain.len() >= self.cluster_count as usize {
+                return Err(String::from("Corrupted cluster chain"));
+            }
+            chain.push(cluster);
+
+            cluster = self.fat_entry(cluster)?;
+            // Values from 0x...FFF8 on end the chain
+            if cluster >= self.end_of_chain() - 7 {
+                break;
+            }
+        }
+
+        Ok(chain)
+    }
+
+    // Takes a free cluster, zeroes it and appends it to the chain ending at `last`
+    fn allocate_cluster(&mut self, last: Option<u32>) -> Result<u32, String> {
+        let cluster = self.find_free_cluster()?;
+        self.invalidate_free_count()?;
+
+        self.set_fat_entry(cluster, self.end_of_chain())?;
+        if let Some(last) = last {
+            self.set_fat_entry(last, cluster)?;
+        }
+        self.write_bytes(self.cluster_offset(cluster), &vec![0; self.cluster_size])?;
+        self.next_free = cluster + 1;
+
+        Ok(cluster)
+    }
+
+    // Scans the allocation table a sector at a time from `next_free` on, wrapping around once
+    fn find_free_cluster(&self) -> Result<u32, String> {
+        const BATCH: u32 = 128;
+        let size = self.fat_entry_size() as usize;
+        let end = self.cluster_count + 2;
+        let start = self.next_free.clamp(2, end);
+
+        for (from, to) in [(start, end), (2, start)] {
+            let mut cluster = from;
+            while cluster < to {
+                let count = BATCH.min(to - cluster);
+                let mut bytes = vec![0; count as usize * size];
+                self.read_bytes(
+                    self.fat_start + u64::from(cluster) * size as u64,
+                    &mut bytes,
+                )?;
+
+                if let Some(index) = bytes
+                    .chunks_exact(size)
+                    .position(|entry| self.decode_fat_entry(entry) == 0)
+                {
+                    return Ok(cluster + index as u32);
+                }
+                cluster += count;
+            }
+        }
+
+        Err(String::from("No space left on the FAT filesystem"))
+    }
+
+    fn free_clusters(&mut self, clusters: &[u32]) -> Result<(), String> {
+        self.invalidate_free_count()?;
+
+        for &cluster in clusters {
+            self.set_fat_entry(cluster, 0)?;
+            self.next_free = self.next_free.min(cluster);
+        }
+
+        Ok(())
+    }
+
+    // Marks the FSInfo free cluster count as unknown before the first allocation changes it
+    fn invalidate_free_count(&mut self) -> Result<(), String> {
+        if let Some(fsinfo) = self.fsinfo.take() {
+            let mut signature = [0; 4];
+            self.read_bytes(fsinfo, &mut signature)?;
+            if u32::from_le_bytes(signature) == FSINFO_SIGNATURE {
+                self.write_bytes(fsinfo + FSINFO_FREE_COUNT, &u32::MAX.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Makes `chain` long enough to hold `len` bytes, or leaves it as it was when the volume
+    // runs out of space
+    fn grow_chain(&mut self, chain: &mut Vec<u32>, len: usize) -> Result<(), String> {
+        let old_len = chain.len();
+
+        while chain.len() < len.div_ceil(self.cluster_size) {
+            match self.allocate_cluster(chain.last().copied()) {
+                Ok(cluster) => chain.push(cluster),
+                Err(err) => {
+                    let added = chain.split_off(old_len);
+                    if let Some(&last) = chain.last() {
+                        self.set_fat_entry(last, self.end_of_chain())?;
+                    }
+                    self.free_clusters(&added)?;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Reads or writes the bytes at `position` of the data stored in `chain`
+    fn transfer(
+        &self,
+        chain: &[u32],
+        position: usize,
+        len: usize,
+        mut f: impl FnMut(u64, usize, usize) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut done = 0;
+
+        while done < len {
+            let offset = position + done;
+            let within = offset % self.cluster_size;
+            let count = (self.cluster_size - within).min(len - done);
+
+            let cluster = chain[offset / self.cluster_size];
+            f(self.cluster_offset(cluster) + within as u64, done, count)?;
+            done += count;
+        }
+
+        Ok(())
+    }
+
+    fn root_directory(&self) -> Directory {
+        match self.kind {
+            FatKind::Fat16 => Directory::FixedRoot,
+            FatKind::Fat32 => Directory::Clusters(self.root_cluster),
+        }
+    }
+
+    // Cluster number other entries use to refer to `directory`, which is 0 for the root
+    fn directory_cluster(&self, directory: Directory) -> u32 {
+        match directory {
+            Directory::Clusters(cluster) if cluster != self.root_cluster => cluster,
+            _ => 0,
+        }
+    }
+
+    // Reads a whole directory and returns its slots along with their device offsets
+    fn read_slots(&self, directory: Directory) -> Result<(Vec<u8>, Vec<u64>), String> {
+        let extents = match directory {
+            Directory::FixedRoot => vec![(self.root_start, self.root_entries * ENTRY_SIZE)],
+            Directory::Clusters(first) => self
+                .chain(first)?
+                .into_iter()
+                .map(|cluster| (self.cluster_offset(cluster), self.cluster_size))
+                .collect(),
+        };
+
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for (offset, len) in extents {
+            let start = data.len();
+            data.resize(start + len, 0);
+            self.read_bytes(offset, &mut data[start..])?;
+            offsets.extend((0..len / ENTRY_SIZE).map(|slot| offset + (slot * ENTRY_SIZE) as u64));
+        }
+
+        Ok((data, offsets))
+    }
+
+    // Lists a directory, skipping `.`, `..`, the volume label and deleted entries
+    fn read_directory(&self, directory: Directory) -> Result<Vec<FatEntry>, String> {
+        let (data, offsets) = self.read_slots(directory)?;
+        let mut entries = Vec::new();
+        let mut long_name = LongName::default();
+
+        for (slot, &offset) in data.chunks_exact(ENTRY_SIZE).zip(offsets.iter()) {
+            match slot[0] {
+                ENTRY_END => break,
+                ENTRY_DELETED => {
+                    long_name = LongName::default();
+                    continue;
+                }
+                _ => {}
+            }
+
+            let attributes = slot[11];
+            if attributes & 0x3F == ATTRIBUTE_LONG_NAME {
+                long_name.push(slot, offset);
+                continue;
+            }
+            if attributes & ATTRIBUTE_VOLUME_ID != 0 || slot[0] == b'.' {
+                long_name = LongName::default();
+                continue;
+            }
+
+            let raw: [u8; ENTRY_SIZE] = slot.try_into().unwrap();
+            let (name, mut slots) = match long_name.finish(&raw) {
+                Some(found) => found,
+                None => (display_short_name(&raw), Vec::new()),
+            };
+            slots.push(offset);
+            entries.push(FatEntry { name, raw, slots });
+            long_name = LongName::default();
+        }
+
+        Ok(entries)
+    }
+
+    // Walks `path` from the root directory
+    fn find(&self, path: &str) -> Result<Node, String> {
+        let mut node = Node::Root;
+
+        for part in path.split('/').filter(|part| !part.is_empty()) {
+            let directory = self.node_directory(&node, part)?;
+            let entry = self
+                .read_directory(directory)?
+                .into_iter()
+                .find(|entry| entry.matches(part))
+                .ok_or_else(|| format!("'{}' not found", path))?;
+            node = Node::Entry(entry);
+        }
+
+        Ok(node)
+    }
+
+    fn node_directory(&self, node: &Node, path: &str) -> Result<Directory, String> {
+        match node {
+            Node::Root => Ok(self.root_directory()),
+            Node::Entry(entry) if entry.kind() == NodeKind::Directory => {
+                Ok(Directory::Clusters(entry.first_cluster()))
+            }
+            Node::Entry(_) => Err(format!("'{}' is not a directory", path)),
+        }
+    }
+
+    fn find_directory(&self, path: &str) -> Result<Directory, String> {
+        self.node_directory(&self.find(path)?, path)
+    }
+
+    fn find_file(&self, path: &str) -> Result<FatEntry, String> {
+        match self.find(path)? {
+            Node::Entry(entry) if entry.kind() == NodeKind::File => Ok(entry),
+            _ => Err(format!("'{}' is a directory", path)),
+        }
+    }
+
+    fn write_entry(&self, entry: &FatEntry) -> Result<(), String> {
+        self.write_bytes(entry.offset(), &entry.raw)
+    }
+
+    // Finds `count` consecutive free slots in `directory`, growing it when it is full
+    fn free_slots(&mut self, directory: Directory, count: usize) -> Result<Vec<u64>, String> {
+        loop {
+            let (data, offsets) = self.read_slots(directory)?;
+            let mut run = 0;
+
+            for (index, slot) in data.chunks_exact(ENTRY_SIZE).enumerate() {
+                if slot[0] == ENTRY_END || slot[0] == ENTRY_DELETED {
+                    run += 1;
+                    if run == count {
+                        return Ok(offsets[index + 1 - count..=index].to_vec());
+                    }
+                } else {
+                    run = 0;
+                }
+            }
+
+            match directory {
+                Directory::FixedRoot => return Err(String::from("Root directory is full")),
+                Directory::Clusters(first) => {
+                    let last = self.chain(first)?.last().copied();
+                    if last.is_none() {
+                        return Err(String::from("Corrupted directory without clusters"));
+                    }
+                    self.allocate_cluster(last)?;
+                }
+            }
+        }
+    }
+
+    // Adds an entry named `name` to `directory`, `raw` provides everything but the short name
+    fn add_entry(
+        &mut self,
+        directory: Directory,
+        name: &str,
+        mut raw: [u8; ENTRY_SIZE],
+    ) -> Result<FatEntry, String> {
+        let existing = self.read_directory(directory)?;
+        let (short_name, case_flags, needs_long_name) = short_name_for(name, &existing);
+        raw[..11].copy_from_slice(&short_name);
+        raw[12] = case_flags;
+
+        let long_entries = if needs_long_name {
+            long_name_entries(name, &short_name)
+        } else {
+            Vec::new()
+        };
+
+        let slots = self.free_slots(directory, long_entries.len() + 1)?;
+        for (slot, long_entry) in slots.iter().zip(long_entries.iter()) {
+            self.write_bytes(*slot, long_entry)?;
+        }
+
+        let entry = FatEntry {
+            name: String::from(name),
+            raw,
+            slots,
+        };
+        self.write_entry(&entry)?;
+
+        Ok(entry)
+    }
+
+    // Marks the slots of `entry` as deleted, the clusters it uses are left alone
+    fn remove_entry(&self, entry: &FatEntry) -> Result<(), String> {
+        for &slot in &entry.slots {
+            self.write_bytes(slot, &[ENTRY_DELETED])?;
+        }
+
+        Ok(())
+    }
+
+    // Points the `..` entry of the directory starting at `cluster` to `parent`
+    fn set_parent(&self, cluster: u32, parent: Directory) -> Result<(), String> {
+        let offset = self.cluster_offset(cluster) + ENTRY_SIZE as u64;
+        let mut raw = [0; ENTRY_SIZE];
+        self.read_bytes(offset, &mut raw)?;
+
+        if &raw[..2] != b".." {
+            return Err(String::from("Corrupted directory, '..' entry missing"));
+        }
+
+        set_entry_cluster(&mut raw, self.directory_cluster(parent));
+        self.write_bytes(offset, &raw)
+    }
+}
+
+impl Vfs for FatFs {
+    fn name(&self) -> &str {
+        match self.kind {
+            FatKind::Fat16 => "fat16",
+            FatKind::Fat32 => "fat32",
+        }
+    }
+
+    fn device(&self) -> Option<&SharedBlockDevice> {
+        Some(&self.device)
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        match self.find(path)? {
+            Node::Root => Ok(NodeKind::Directory),
+            Node::Entry(entry) => Ok(entry.kind()),
+        }
+    }
+
+    fn create(&mut self, path: &str, kind: NodeKind) -> Result<(), String> {
+        let (parent, name) = split_path("/", path)?;
+        check_name(&name)?;
+
+        let directory = self.find_directory(&parent)?;
+        if self
+            .read_directory(directory)?
+            .iter()
+            .any(|entry| entry.matches(&name))
+        {
+            return Err(format!("A node with the name '{}' already exists", name));
+        }
+
+        let mut raw = new_short_entry(0);
+        match kind {
+            NodeKind::File => raw[11] = ATTRIBUTE_ARCHIVE,
+            NodeKind::Directory => {
+                // A new directory holds `.` and `..`, the rest of its cluster is zeroed
+                let cluster = self.allocate_cluster(None)?;
+                let mut dots = [0; 2 * ENTRY_SIZE];
+                for (index, target) in [(0, cluster), (1, self.directory_cluster(directory))] {
+                    let mut dot = new_short_entry(ATTRIBUTE_DIRECTORY);
+                    dot[..11].copy_from_slice(if index == 0 {
+                        b".          "
+                    } else {
+                        b"..         "
+                    });
+                    set_entry_cluster(&mut dot, target);
+                    dots[index * ENTRY_SIZE..(index + 1) * ENTRY_SIZE].copy_from_slice(&dot);
+                }
+                self.write_bytes(self.cluster_offset(cluster), &dots)?;
+
+                raw[11] = ATTRIBUTE_DIRECTORY;
+                set_entry_cluster(&mut raw, cluster);
+            }
+            NodeKind::Symlink => {
+                return Err(format!("{} does not support symbolic links", self.name()))
+            }
+            NodeKind::CharDevice | NodeKind::BlockDevice => {
+                return Err(format!("{} does not support device nodes", self.name()))
+            }
+        }
+
+        if let Err(err) = self.add_entry(directory, &name, raw) {
+            if kind == NodeKind::Directory {
+                self.free_clusters(&[entry_cluster(&raw)])?;
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        let entry = self.find_file(path)?;
+        let size = entry.size();
+
+        let start = offset.min(size);
+        let count = buffer.len().min(size - start);
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let chain = self.chain(entry.first_cluster())?;
+        if chain.len() < size.div_ceil(self.cluster_size) {
+            return Err(format!(
+                "'{}' is shorter than its size, the filesystem is corrupted",
+                path
+            ));
+        }
+
+        self.transfer(&chain, start, count, |device_offset, done, len| {
+            self.read_bytes(device_offset, &mut buffer[done..done + len])
+        })?;
+
+        Ok(count)
+    }
+
+    fn write(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, String> {
+        let mut entry = self.find_file(path)?;
+        let size = entry.size();
+
+        let end = offset
+            .checked_add(data.len())
+            .filter(|&end| end <= u32::MAX as usize)
+            .ok_or_else(|| String::from("Files on FAT are limited to 4 GiB"))?;
+
+        let mut chain = self.chain(entry.first_cluster())?;
+        self.grow_chain(&mut chain, end)?;
+        if let Some(&first) = chain.first() {
+            entry.set_first_cluster(first);
+        }
+
+        // New clusters come zeroed, only the unused tail of the old last cluster needs clearing
+        let gap_end = offset.min(size.next_multiple_of(self.cluster_size));
+        if gap_end > size {
+            let zeros = vec![0; gap_end - size];
+            self.transfer(&chain, size, zeros.len(), |device_offset, done, len| {
+                self.write_bytes(device_offset, &zeros[done..done + len])
+            })?;
+        }
+
+        self.transfer(&chain, offset, data.len(), |device_offset, done, len| {
+            self.write_bytes(device_offset, &data[done..done + len])
+        })?;
+
+        entry.set_size(size.max(end));
+        entry.touch();
+        self.write_entry(&entry)?;
+
+        Ok(data.len())
+    }
+
+    fn truncate(&mut self, path: &str, len: usize) -> Result<(), String> {
+        let mut entry = self.find_file(path)?;
+        let size = entry.size();
+
+        if len > u32::MAX as usize {
+            return Err(String::from("Files on FAT are limited to 4 GiB"));
+        }
+
+        let mut chain = self.chain(entry.first_cluster())?;
+        let keep = len.div_ceil(self.cluster_size);
+
+        if keep < chain.len() {
+            let freed = chain.split_off(keep);
+            match chain.last() {
+                Some(&last) => self.set_fat_entry(last, self.end_of_chain())?,
+                None => entry.set_first_cluster(0),
+            }
+            self.free_clusters(&freed)?;
+        } else if len > size {
+            self.grow_chain(&mut chain, len)?;
+            if let Some(&first) = chain.first() {
+                entry.set_first_cluster(first);
+            }
+
+            let gap_end = len.min(size.next_multiple_of(self.cluster_size));
+            if gap_end > size {
+                let zeros = vec![0; gap_end - size];
+                self.transfer(&chain, size, zeros.len(), |device_offset, done, count| {
+                    self.write_bytes(device_offset, &zeros[done..done + count])
+                })?;
+            }
+        }
+
+        entry.set_size(len);
+        entry.touch();
+        self.write_entry(&entry)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let entries = self
+            .read_directory(self.find_directory(path)?)?
+            .into_iter()
+            .map(|entry| DirEntry {
+                kind: entry.kind(),
+                name: entry.name,
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, path: &str) -> Result<(), String> {
+        let (parent, name) = split_path("/", path)?;
+
+        let entry = match self.find(path)? {
+            Node::Entry(entry) => entry,
+            Node::Root => return Err(format!("Node '{}' not found in '{}'", name, parent)),
+        };
+
+        if entry.kind() == NodeKind::Directory
+            && !self
+                .read_directory(Directory::Clusters(entry.first_cluster()))?
+                .is_empty()
+        {
+            return Err(format!("Directory '{}' is not empty", name));
+        }
+
+        self.remove_entry(&entry)?;
+        let chain = self.chain(entry.first_cluster())?;
+        self.free_clusters(&chain)
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let entry = match self.find(from)? {
+            Node::Entry(entry) => entry,
+            Node::Root => return Err(String::from("Cannot move the root directory")),
+        };
+
+        // A directory moved below itself would be detached from the tree, names ignore case
+        let from_lower = from.to_ascii_lowercase();
+        let to_lower = to.to_ascii_lowercase();
+        if to_lower.starts_with(&from_lower) && to_lower[from.len()..].starts_with('/') {
+            return Err(format!("Cannot move '{}' into itself", from));
+        }
+
+        let (from_parent, _) = split_path("/", from)?;
+        let (to_parent, name) = split_path("/", to)?;
+        check_name(&name)?;
+
+        let directory = self.find_directory(&to_parent)?;
+        // Renaming to a different case of the same name finds the entry itself
+        if self
+            .read_directory(directory)?
+            .iter()
+            .any(|existing| existing.matches(&name) && existing.offset() != entry.offset())
+        {
+            return Err(format!("A node with the name '{}' already exists", name));
+        }
+
+        // The new entry is written before the old one is removed, so a failure in between
+        // leaves two names rather than none
+        self.add_entry(directory, &name, entry.raw)?;
+        self.remove_entry(&entry)?;
+
+        if entry.kind() == NodeKind::Directory && self.find_directory(&from_parent)? != directory {
+            self.set_parent(entry.first_cluster(), directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let entry = match self.find(path)? {
+            Node::Root => {
+                let size = self.read_directory(self.root_directory())?.len();
+                return Ok(Metadata {
+                    kind: NodeKind::Directory,
+                    size,
+                    inode: ROOT_INODE,
+                    links: 1,
+                    uid: 0,
+                    gid: 0,
+                    mode: DEFAULT_DIRECTORY_MODE,
+                    created: 0,
+                    modified: 0,
+                    accessed: 0,
+                });
+            }
+            Node::Entry(entry) => entry,
+        };
+
+        let (size, mut mode) = match entry.kind() {
+            NodeKind::Directory => (
+                self.read_directory(Directory::Clusters(entry.first_cluster()))?
+                    .len(),
+                DEFAULT_DIRECTORY_MODE,
+            ),
+            _ => (entry.size(), DEFAULT_FILE_MODE),
+        };
+        if entry.attributes() & ATTRIBUTE_READ_ONLY != 0 {
+            mode &= !0o222;
+        }
+
+        let raw = &entry.raw;
+        Ok(Metadata {
+            kind: entry.kind(),
+            size,
+            // FAT has no inodes and empty files share cluster 0, the entry's position is the only
+            // thing telling nodes apart
+            inode: entry.offset(),
+            links: 1,
+            uid: 0,
+            gid: 0,
+            mode,
+            created: unix_time(u16_at(raw, 16), u16_at(raw, 14)) + u64::from(raw[13]) / 100,
+            modified: unix_time(u16_at(raw, 24), u16_at(raw, 22)),
+            accessed: unix_time(u16_at(raw, 18), 0),
+        })
+    }
+
+    fn set_attributes(&mut self, path: &str, attributes: &Attributes) -> Result<(), String> {
+        let mut entry = match self.find(path)? {
+            Node::Entry(entry) => entry,
+            Node::Root => return Err(String::from("The root directory has no attributes on FAT")),
+        };
+
+        if attributes.uid.unwrap_or(0) != 0 || attributes.gid.unwrap_or(0) != 0 {
+            return Err(format!("{} does not store owners", self.name()));
+        }
+
+        if let Some(mode) = attributes.mode {
+            if mode & 0o222 == 0 {
+                entry.raw[11] |= ATTRIBUTE_READ_ONLY;
+            } else {
+                entry.raw[11] &= !ATTRIBUTE_READ_ONLY;
+            }
+        }
+        if let Some(created) = attributes.created {
+            let (date, time) = fat_date_time(created);
+            entry.raw[13] = 0;
+            entry.raw[14..16].copy_from_slice(&time.to_le_bytes());
+            entry.raw[16..18].copy_from_slice(&date.to_le_bytes());
+        }
+        if let Some(modified) = attributes.modified {
+            let (date, time) = fat_date_time(modified);
+            entry.raw[22..24].copy_from_slice(&time.to_le_bytes());
+            entry.raw[24..26].copy_from_slice(&date.to_le_bytes());
+        }
+        if let Some(accessed) = attributes.accessed {
+            let (date, _) = fat_date_time(accessed);
+            entry.raw[18..20].copy_from_slice(&date.to_le_bytes());
+        }
+
+        self.write_entry(&entry)
+    }
+}
+
+// Long name entries collected while reading a directory, they precede the short entry they
+// belong to in reverse order
+#[derive(Default)]
+struct LongName {
+    // UTF-16 characters of each entry by ordinal, starting from ordinal 1
+    parts: Vec<[u16; CHARS_PER_LONG_ENTRY]>,
+    slots: Vec<u64>,
+    // Ordinal of the entry read last, the next one must be one lower
+    ordinal: u8,
+    checksum: u8,
+}
+
+impl LongName {
+    fn push(&mut self, slot: &[u8], offset: u64) {
+        let ordinal = slot[0] & 0x1F;
+        let mut characters = [0; CHARS_PER_LONG_ENTRY];
+        for (character, &position) in characters.iter_mut().zip(LONG_NAME_OFFSETS.iter()) {
+            *character = u16_at(slot, position);
+        }
+
+        if slot[0] & LAST_LONG_ENTRY != 0 {
+            *self = Self {
+                parts: vec![[0; CHARS_PER_LONG_ENTRY]; usize::from(ordinal)],
+                slots: Vec::new(),
+                ordinal: ordinal + 1,
+                checksum: slot[13],
+            };
+        }
+
+        // Entries out of sequence or belonging to another name make the whole name invalid
+        if ordinal == 0 || ordinal + 1 != self.ordinal || slot[13] != self.checksum {
+            *self = Self::default();
+            return;
+        }
+
+        self.parts[usize::from(ordinal) - 1] = characters;
+        self.slots.push(offset);
+        self.ordinal = ordinal;
+    }
+
+    // Returns the name and its slots if a complete long name for `short_entry` was read
+    fn finish(self, short_entry: &[u8; ENTRY_SIZE]) -> Option<(String, Vec<u64>)> {
+        if self.ordinal != 1 || self.checksum != checksum(short_entry[..11].try_into().unwrap()) {
+            return None;
+        }
+
+        let characters = self.parts.iter().flatten().copied().take_while(|&c| c != 0);
+        let name: String = char::decode_utf16(characters)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+
+        Some((name, self.slots))
+    }
+}
+
+// Returns whether `first_sector`, the start of a device, is the boot sector of a FAT volume
+pub fn is_volume(first_sector: &[u8]) -> bool {
+    first_sector.len() >= 512 && is_boot_sector(first_sector)
+}
+
+// Checks the boot sector fields the driver relies on
+fn is_boot_sector(sector: &[u8]) -> bool {
+    let bytes_per_sector = u16_at(sector, 11);
+    let sectors_per_cluster = sector[13];
+
+    matches!(sector[0], 0xEB | 0xE9)
+        && matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+        && sectors_per_cluster.is_power_of_two()
+        && u16_at(sector, 14) != 0
+        && sector[16] != 0
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+// The first cluster of a short entry, whose high half is always 0 on FAT16
+fn entry_cluster(raw: &[u8; ENTRY_SIZE]) -> u32 {
+    u32::from(u16_at(raw, 20)) << 16 | u32::from(u16_at(raw, 26))
+}
+
+fn set_entry_cluster(raw: &mut [u8; ENTRY_SIZE], cluster: u32) {
+    raw[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    raw[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+}
+
+// A short entry with every timestamp set to now
+fn new_short_entry(attributes: u8) -> [u8; ENTRY_SIZE] {
+    let mut raw = [0; ENTRY_SIZE];
+    let (date, time) = fat_date_time(clock::now());
+
+    raw[11] = attributes;
+    raw[14..16].copy_from_slice(&time.to_le_bytes());
+    raw[16..18].copy_from_slice(&date.to_le_bytes());
+    raw[18..20].copy_from_slice(&date.to_le_bytes());
+    raw[22..24].copy_from_slice(&time.to_le_bytes());
+    raw[24..26].copy_from_slice(&date.to_le_bytes());
+
+    raw
+}
+
+// FAT dates count years from 1980 and times have a two second resolution, both are taken to be
+// UTC since FAT does not record a time zone
+fn unix_time(date: u16, time: u16) -> u64 {
+    if date == 0 {
+        return 0;
+    }
+
+    DateTime {
+        year: 1980 + u64::from(date >> 9),
+        month: u64::from((date >> 5) & 0x0F).clamp(1, 12),
+        day: u64::from(date & 0x1F).max(1),
+        hour: u64::from(time >> 11),
+        minute: u64::from((time >> 5) & 0x3F),
+        second: u64::from(time & 0x1F) * 2,
+    }
+    .to_unix()
+}
+
+fn fat_date_time(timestamp: u64) -> (u16, u16) {
+    let time = DateTime::from_unix(timestamp);
+    if time.year < 1980 {
+        return ((1 << 5) | 1, 0);
+    }
+
+    let date = ((time.year - 1980).min(127) << 9) | (time.month << 5) | time.day;
+    let clock = (time.hour << 11) | (time.minute << 5) | (time.second / 2);
+
+    (date as u16, clock as u16)
+}
+
+// Checksum of a short name stored in each of its long name entries
+fn checksum(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+}
+
+// Renders a short name as `NAME.EXT`, honouring the lowercase flags Windows NT and Linux set
+fn display_short_name(raw: &[u8; ENTRY_SIZE]) -> String {
+    let part = |bytes: &[u8], lowercase: bool| -> String {
+        let mut part: String = bytes
+            .iter()
+            .map(|&byte| char::from(byte))
+            .collect::<String>()
+            .trim_end()
+            .into();
+        if lowercase {
+            part.make_ascii_lowercase();
+        }
+        part
+    };
+
+    let mut name = part(&raw[..8], raw[12] & LOWERCASE_BASE != 0);
+    // 0x05 stands in for a leading 0xE5, which would mark the entry as deleted
+    if name.starts_with('\u{5}') {
+        name.replace_range(..1, "\u{E5}");
+    }
+
+    let extension = part(&raw[8..11], raw[12] & LOWERCASE_EXTENSION != 0);
+    if !extension.is_empty() {
+        name.push('.');
+        name.push_str(&extension);
+    }
+
+    name
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.encode_utf16().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "'{}' is longer than {} characters",
+            name, MAX_NAME_LENGTH
+        ));
+    }
+    if let Some(invalid) = name.chars().find(|&c| c < ' ' || "\"*/:<>?\\|".contains(c)) {
+        return Err(format!("'{}' cannot be used in names on FAT", invalid));
+    }
+
+    Ok(())
+}
+
+fn is_short_name_char(c: char) -> bool {
+    c.is_ascii_uppercase() || c.is_ascii_digit() || "$%'-_@~`!(){}^#&".contains(c)
+}
+
+// Picks the short name for a new entry called `name` in a directory holding `existing`
+//
+// Returns the padded 8.3 name, the lowercase flags and whether a long name is needed. Names that
+// already are valid 8.3 names in a single case per part are stored as they are, anything else
+// gets a `BASE~N.EXT` alias unique in the directory.
+fn short_name_for(name: &str, existing: &[FatEntry]) -> ([u8; 11], u8, bool) {
+    let (base, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 => (&name[..dot], &name[dot + 1..]),
+        _ => (name, ""),
+    };
+
+    // Each part may be all uppercase or all lowercase, the flags record the latter
+    let case_flag = |part: &str, flag: u8| -> Option<u8> {
+        let upper = part.to_ascii_uppercase();
+        if !upper.chars().all(is_short_name_char) {
+            None
+        } else if part == upper {
+            Some(0)
+        } else if part == part.to_ascii_lowercase() {
+            Some(flag)
+        } else {
+            None
+        }
+    };
+
+    let mut short_name = [b' '; 11];
+    if (1..=8).contains(&base.len()) && extension.len() <= 3 {
+        if let (Some(base_flag), Some(extension_flag)) = (
+            case_flag(base, LOWERCASE_BASE),
+            case_flag(extension, LOWERCASE_EXTENSION),
+        ) {
+            short_name[..base.len()].copy_from_slice(base.to_ascii_uppercase().as_bytes());
+            short_name[8..8 + extension.len()]
+                .copy_from_slice(extension.to_ascii_uppercase().as_bytes());
+            return (short_name, base_flag | extension_flag, false);
+        }
+    }
+
+    let convert = |part: &str, len: usize| -> Vec<u8> {
+        part.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(|c| {
+                let c = c.to_ascii_uppercase();
+                if is_short_name_char(c) {
+                    c as u8
+                } else {
+                    b'_'
+                }
+            })
+            .take(len)
+            .collect()
+    };
+    let base = convert(base, 8);
+    let extension = convert(extension, 3);
+    short_name[8..8 + extension.len()].copy_from_slice(&extension);
+
+    for number in 1.. {
+        let tail = format!("~{}", number);
+        let kept = base.len().min(8 - tail.len());
+
+        let mut candidate = short_name;
+        candidate[..8].fill(b' ');
+        candidate[..kept].copy_from_slice(&base[..kept]);
+        candidate[kept..kept + tail.len()].copy_from_slice(tail.as_bytes());
+
+        if !existing.iter().any(|entry| entry.raw[..11] == candidate) {
+            return (candidate, 0, true);
+        }
+    }
+
+    unreachable!()
+}
+
+// Builds the long name entries for `name` in the order they are stored, last part first
+fn long_name_entries(name: &str, short_name: &[u8; 11]) -> Vec<[u8; ENTRY_SIZE]> {
+    let mut characters: Vec<u16> = name.encode_utf16().collect();
+    let count = characters.len().div_ceil(CHARS_PER_LONG_ENTRY);
+    // The name ends with a 0 unless it fills the last entry exactly, the rest is 0xFFFF
+    if !characters.len().is_multiple_of(CHARS_PER_LONG_ENTRY) {
+        characters.push(0);
+    }
+    characters.resize(count * CHARS_PER_LONG_ENTRY, 0xFFFF);
+
+    let checksum = checksum(short_name);
+    (1..=count)
+        .rev()
+        .map(|ordinal| {
+            let mut entry = [0; ENTRY_SIZE];
+            entry[0] = ordinal as u8;
+            if ordinal == count {
+                entry[0] |= LAST_LONG_ENTRY;
+            }
+            entry[11] = ATTRIBUTE_LONG_NAME;
+            entry[13] = checksum;
+
+            let part = &characters[(ordinal - 1) * CHARS_PER_LONG_ENTRY..][..CHARS_PER_LONG_ENTRY];
+            for (character, &position) in part.iter().zip(LONG_NAME_OFFSETS.iter()) {
+                entry[position..position + 2].copy_from_slice(&character.to_le_bytes());
+            }
+            entry
+        })
+        .collect()
+}
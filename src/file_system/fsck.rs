@@ -0,0 +1,213 @@
+use super::vfs::{NodeKind, Vfs};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Consistency check of a mounted filesystem, like the Unix `fsck` but without repairs
+//
+// The backend's own check runs first, then the tree is walked from its root through the `Vfs`
+// operations, which is all other code ever sees of it. The walk verifies that:
+//
+// - every entry has a valid name that is unique in its directory
+// - every entry can be looked up and stat'ed with the kind its directory lists
+// - every directory is reachable through exactly one entry, so the tree has no cycles
+// - every other node has as many links as there are entries pointing at it
+// - every symbolic link has a readable target
+//
+// Problems are returned as messages naming the paths below `mount_point`, an empty list means
+// the filesystem is consistent.
+pub fn check(fs: &dyn Vfs, mount_point: &str) -> Vec<String> {
+    let problems = fs.check();
+    // Walking broken structures could go anywhere, they are reported on their own
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    let mut checker = Checker {
+        fs,
+        mount_point,
+        problems,
+        directories: BTreeMap::new(),
+        links: BTreeMap::new(),
+    };
+    checker.walk();
+    checker.check_links();
+
+    checker.problems
+}
+
+// Links a node claims and the entries found pointing at it, with the first path seen
+struct LinkCount {
+    path: String,
+    links: usize,
+    entries: usize,
+}
+
+struct Checker<'a> {
+    fs: &'a dyn Vfs,
+    mount_point: &'a str,
+    problems: Vec<String>,
+    // Inode number -> path of every directory seen
+    directories: BTreeMap<u64, String>,
+    // Inode number -> link count of every other node seen
+    links: BTreeMap<u64, LinkCount>,
+}
+
+impl Checker<'_> {
+    // The path shown to the user for `path` inside of the filesystem
+    fn display(&self, path: &str) -> String {
+        match (self.mount_point, path) {
+            ("/", _) => String::from(path),
+            (_, "/") => String::from(self.mount_point),
+            _ => format!("{}{}", self.mount_point, path),
+        }
+    }
+
+    fn report(&mut self, problem: String) {
+        self.problems.push(problem);
+    }
+
+    fn walk(&mut self) {
+        match self.fs.stat("/") {
+            Ok(metadata) if metadata.kind == NodeKind::Directory => {
+                self.directories.insert(metadata.inode, String::from("/"));
+            }
+            Ok(_) => {
+                let root = self.display("/");
+                return self.report(format!("The root '{}' is not a directory", root));
+            }
+            Err(error) => return self.report(format!("Cannot stat the root: {}", error)),
+        }
+
+        let mut pending = Vec::from([String::from("/")]);
+        while let Some(directory) = pending.pop() {
+            let entries = match self.fs.readdir(&directory) {
+                Ok(entries) => entries,
+                Err(error) => {
+                    let shown = self.display(&directory);
+                    self.report(format!("Cannot read '{}': {}", shown, error));
+                    continue;
+                }
+            };
+
+            let mut names = BTreeSet::new();
+            for entry in entries {
+                let path = super::normalize_path(&directory, &entry.name);
+                let shown = self.display(&path);
+
+                if entry.name.is_empty()
+                    || entry.name == "."
+                    || entry.name == ".."
+                    || entry.name.contains('/')
+                {
+                    let parent = self.display(&directory);
+                    self.report(format!(
+                        "'{}' has an entry with the invalid name '{}'",
+                        parent, entry.name
+                    ));
+                    continue;
+                }
+                if !names.insert(entry.name.clone()) {
+                    self.report(format!("'{}' is listed more than once", shown));
+                    continue;
+                }
+
+                match self.fs.lookup(&path) {
+                    Ok(kind) if kind == entry.kind => {}
+                    Ok(kind) => {
+                        self.report(format!(
+                            "'{}' is listed as a {} but is a {}",
+                            shown,
+                            describe(entry.kind),
+                            describe(kind)
+                        ));
+                        continue;
+                    }
+                    Err(error) => {
+                        self.report(format!("'{}' is listed but not found: {}", shown, error));
+                        continue;
+                    }
+                }
+                let metadata = match self.fs.stat(&path) {
+                    Ok(metadata) => metadata,
+                    Err(error) => {
+                        self.report(format!("Cannot stat '{}': {}", shown, error));
+                        continue;
+                    }
+                };
+                if metadata.kind != entry.kind {
+                    self.report(format!(
+                        "'{}' is listed as a {} but its metadata says {}",
+                        shown,
+                        describe(entry.kind),
+                        describe(metadata.kind)
+                    ));
+                    continue;
+                }
+
+                match metadata.kind {
+                    NodeKind::Directory => {
+                        if let Some(first) = self.directories.get(&metadata.inode) {
+                            let first = self.display(first);
+                            self.report(format!(
+                                "'{}' is the same directory as '{}'",
+                                shown, first
+                            ));
+                            continue;
+                        }
+                        self.directories.insert(metadata.inode, path.clone());
+                        pending.push(path);
+                    }
+                    _ => {
+                        if metadata.kind == NodeKind::Symlink {
+                            if let Err(error) = self.fs.readlink(&path) {
+                                self.report(format!(
+                                    "The target of '{}' cannot be read: {}",
+                                    shown, error
+                                ));
+                            }
+                        }
+
+                        self.links
+                            .entry(metadata.inode)
+                            .or_insert(LinkCount {
+                                path,
+                                links: metadata.links,
+                                entries: 0,
+                            })
+                            .entries += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_links(&mut self) {
+        let mismatches: Vec<String> = self
+            .links
+            .values()
+            .filter(|count| count.links != count.entries)
+            .map(|count| {
+                format!(
+                    "'{}' has {} links but {} entries point at it",
+                    self.display(&count.path),
+                    count.links,
+                    count.entries
+                )
+            })
+            .collect();
+
+        self.problems.extend(mismatches);
+    }
+}
+
+fn describe(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::File => "file",
+        NodeKind::Directory => "directory",
+        NodeKind::Symlink => "symbolic link",
+        NodeKind::CharDevice => "character device",
+        NodeKind::BlockDevice => "block device",
+    }
+}
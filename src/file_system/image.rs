@@ -0,0 +1,285 @@
+use super::normalize_path;
+use super::ramfs::RamFs;
+use super::vfs::{Attributes, NodeKind, Vfs};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Serialized form of a whole filesystem tree, as written to disk by `sync`
+//
+// The image starts with a fixed header:
+//
+//   magic    8 bytes  `CHZRAMFS`
+//   version  u32      `VERSION`
+//   checksum u32      CRC-32 of the payload
+//   length   u64      payload length in bytes
+//
+// followed by the payload, one record per node in pre-order so parents always come before their
+// entries. All integers are little endian. A record is a type byte and the node's path, then:
+//
+//   file       attributes, u32 length, content
+//   directory  attributes
+//   symlink    attributes, u16 length, target
+//   hard link  u16 length, path of the node recorded earlier that it is another name for
+//
+// where attributes are mode u16, uid u32, gid u32 and created, modified, accessed as u64.
+
+pub const MAGIC: &[u8; 8] = b"CHZRAMFS";
+pub const VERSION: u32 = 1;
+pub const HEADER_SIZE: usize = 24;
+
+const RECORD_FILE: u8 = 0;
+const RECORD_DIRECTORY: u8 = 1;
+const RECORD_SYMLINK: u8 = 2;
+const RECORD_HARD_LINK: u8 = 3;
+
+// Serializes everything in `backend` into an image, starting with the header
+pub fn encode(backend: &dyn Vfs) -> Result<Vec<u8>, String> {
+    let mut payload = Vec::new();
+    let mut seen = BTreeMap::new();
+    encode_node(backend, "/", &mut payload, &mut seen)?;
+
+    let mut image = Vec::with_capacity(HEADER_SIZE + payload.len());
+    image.extend_from_slice(MAGIC);
+    image.extend_from_slice(&VERSION.to_le_bytes());
+    image.extend_from_slice(&crc32(&payload).to_le_bytes());
+    image.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    image.extend_from_slice(&payload);
+
+    Ok(image)
+}
+
+// Returns the total size of the image starting with `header`, or `None` when `header` does not
+// start an image at all, e.g. on a blank disk
+pub fn image_size(header: &[u8]) -> Result<Option<usize>, String> {
+    if header.len() < HEADER_SIZE || &header[..8] != MAGIC {
+        return Ok(None);
+    }
+
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version != VERSION {
+        return Err(format!(
+            "Unsupported image version {}, expected {}",
+            version, VERSION
+        ));
+    }
+
+    let length = u64::from_le_bytes(header[16..24].try_into().unwrap());
+    let length = usize::try_from(length).map_err(|_| String::from("Image is too large"))?;
+
+    HEADER_SIZE
+        .checked_add(length)
+        .map(Some)
+        .ok_or_else(|| String::from("Image is too large"))
+}
+
+// Rebuilds the filesystem stored in `image`, trailing bytes after the payload are ignored
+pub fn decode(image: &[u8]) -> Result<RamFs, String> {
+    let size = image_size(image)?.ok_or_else(|| String::from("Not a filesystem image"))?;
+    let payload = image
+        .get(HEADER_SIZE..size)
+        .ok_or_else(|| String::from("Image is truncated"))?;
+
+    let checksum = u32::from_le_bytes(image[12..16].try_into().unwrap());
+    if crc32(payload) != checksum {
+        return Err(String::from(
+            "Image checksum does not match, the image is corrupted",
+        ));
+    }
+
+    let mut fs = RamFs::new();
+    let mut reader = Reader {
+        bytes: payload,
+        position: 0,
+    };
+    // Applied once every node exists, creating entries would bump the directory timestamps
+    let mut attributes = Vec::new();
+
+    while !reader.is_empty() {
+        let record = reader.u8()?;
+        let path = normalize_path("/", &reader.string()?);
+
+        match record {
+            RECORD_FILE => {
+                let node_attributes = reader.attributes()?;
+                let content = reader.bytes()?;
+                fs.create(&path, NodeKind::File)?;
+                fs.write(&path, 0, content)?;
+                attributes.push((path, node_attributes));
+            }
+            RECORD_DIRECTORY => {
+                let node_attributes = reader.attributes()?;
+                if path != "/" {
+                    fs.create(&path, NodeKind::Directory)?;
+                }
+                attributes.push((path, node_attributes));
+            }
+            RECORD_SYMLINK => {
+                let node_attributes = reader.attributes()?;
+                let target = reader.string()?;
+                fs.symlink(&target, &path)?;
+                attributes.push((path, node_attributes));
+            }
+            RECORD_HARD_LINK => {
+                let existing = normalize_path("/", &reader.string()?);
+                fs.link(&existing, &path)?;
+            }
+            _ => return Err(format!("Unknown record type {} in image", record)),
+        }
+    }
+
+    for (path, node_attributes) in attributes {
+        fs.set_attributes(&path, &node_attributes)?;
+    }
+
+    Ok(fs)
+}
+
+fn encode_node(
+    backend: &dyn Vfs,
+    path: &str,
+    payload: &mut Vec<u8>,
+    seen: &mut BTreeMap<u64, String>,
+) -> Result<(), String> {
+    let metadata = backend.stat(path)?;
+
+    // Nodes with several names are stored once and referred to by their first path afterwards
+    if metadata.kind != NodeKind::Directory && metadata.links > 1 {
+        if let Some(existing) = seen.get(&metadata.inode) {
+            payload.push(RECORD_HARD_LINK);
+            put_string(payload, path)?;
+            put_string(payload, existing)?;
+            return Ok(());
+        }
+        seen.insert(metadata.inode, String::from(path));
+    }
+
+    payload.push(match metadata.kind {
+        NodeKind::File => RECORD_FILE,
+        NodeKind::Directory => RECORD_DIRECTORY,
+        NodeKind::Symlink => RECORD_SYMLINK,
+        NodeKind::CharDevice | NodeKind::BlockDevice => {
+            return Err(format!("Device '{}' cannot be saved in an image", path))
+        }
+    });
+    put_string(payload, path)?;
+    payload.extend_from_slice(&metadata.mode.to_le_bytes());
+    payload.extend_from_slice(&metadata.uid.to_le_bytes());
+    payload.extend_from_slice(&metadata.gid.to_le_bytes());
+    payload.extend_from_slice(&metadata.created.to_le_bytes());
+    payload.extend_from_slice(&metadata.modified.to_le_bytes());
+    payload.extend_from_slice(&metadata.accessed.to_le_bytes());
+
+    match metadata.kind {
+        NodeKind::File => {
+            let mut content = vec![0; metadata.size];
+            let count = backend.read(path, 0, &mut content)?;
+            let length = u32::try_from(count)
+                .map_err(|_| format!("'{}' is too large for an image", path))?;
+
+            payload.extend_from_slice(&length.to_le_bytes());
+            payload.extend_from_slice(&content[..count]);
+        }
+        NodeKind::Symlink => put_string(payload, &backend.readlink(path)?)?,
+        NodeKind::CharDevice | NodeKind::BlockDevice => {}
+        NodeKind::Directory => {
+            for entry in backend.readdir(path)? {
+                encode_node(backend, &normalize_path(path, &entry.name), payload, seen)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn put_string(payload: &mut Vec<u8>, string: &str) -> Result<(), String> {
+    let length = u16::try_from(string.len())
+        .map_err(|_| format!("'{}' is too long for an image", string))?;
+
+    payload.extend_from_slice(&length.to_le_bytes());
+    payload.extend_from_slice(string.as_bytes());
+
+    Ok(())
+}
+
+// Cursor over the payload of an image, every read fails once the payload runs out
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| String::from("Image is truncated"))?;
+
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = usize::from(self.u16()?);
+        let bytes = self.take(length)?;
+
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| String::from("Image contains an invalid name"))
+    }
+
+    fn attributes(&mut self) -> Result<Attributes, String> {
+        Ok(Attributes {
+            mode: Some(self.u16()?),
+            uid: Some(self.u32()?),
+            gid: Some(self.u32()?),
+            created: Some(self.u64()?),
+            modified: Some(self.u64()?),
+            accessed: Some(self.u64()?),
+        })
+    }
+}
+
+// CRC-32 as used by zlib and Ethernet, computed bit by bit since images are only written on sync
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,139 @@
+use super::vfs::Vfs;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub struct Mount {
+    // Absolute, normalized path of the directory the filesystem is attached to
+    pub path: String,
+    pub backend: Box<dyn Vfs>,
+}
+
+// Filesystems attached to the directory tree, the first entry is always the root filesystem
+pub struct MountTable {
+    mounts: Vec<Mount>,
+}
+
+// Returns `path` relative to `mount_point` when it lies inside of it
+pub fn relative_to(mount_point: &str, path: &str) -> Option<String> {
+    if mount_point == "/" {
+        return Some(String::from(path));
+    }
+
+    match path.strip_prefix(mount_point) {
+        Some("") => Some(String::from("/")),
+        Some(rest) if rest.starts_with('/') => Some(String::from(rest)),
+        _ => None,
+    }
+}
+
+impl MountTable {
+    pub fn new(root: Box<dyn Vfs>) -> Self {
+        Self {
+            mounts: Vec::from([Mount {
+                path: String::from("/"),
+                backend: root,
+            }]),
+        }
+    }
+
+    // Finds the innermost mount containing the normalized absolute `path`
+    fn find(&self, path: &str) -> (usize, String) {
+        let mut found = (0, String::from(path));
+        let mut longest = 0;
+
+        for (index, mount) in self.mounts.iter().enumerate() {
+            if let Some(relative) = relative_to(&mount.path, path) {
+                if mount.path.len() >= longest {
+                    longest = mount.path.len();
+                    found = (index, relative);
+                }
+            }
+        }
+
+        found
+    }
+
+    // Returns the filesystem holding `path` and the path inside of that filesystem
+    pub fn resolve(&self, path: &str) -> (&dyn Vfs, String) {
+        let (index, relative) = self.find(path);
+        (self.mounts[index].backend.as_ref(), relative)
+    }
+
+    // The innermost mount containing `path`
+    pub fn find_mount(&self, path: &str) -> &Mount {
+        &self.mounts[self.find(path).0]
+    }
+
+    pub fn resolve_mut(&mut self, path: &str) -> (&mut dyn Vfs, String) {
+        let (index, relative) = self.find(path);
+        (self.mounts[index].backend.as_mut(), relative)
+    }
+
+    pub fn same_filesystem(&self, first: &str, second: &str) -> bool {
+        self.find(first).0 == self.find(second).0
+    }
+
+    // Resolves two paths that must live on the same filesystem, as needed by rename and link
+    pub fn resolve_pair_mut(
+        &mut self,
+        first: &str,
+        second: &str,
+    ) -> Result<(&mut dyn Vfs, String, String), String> {
+        let (index, first) = self.find(first);
+        let (second_index, second) = self.find(second);
+
+        if index != second_index {
+            return Err(String::from("Paths are on different filesystems"));
+        }
+
+        Ok((self.mounts[index].backend.as_mut(), first, second))
+    }
+
+    pub fn mount(&mut self, path: String, backend: Box<dyn Vfs>) -> Result<(), String> {
+        if self.mounts.iter().any(|mount| mount.path == path) {
+            return Err(format!("'{}' is already a mount point", path));
+        }
+
+        self.mounts.push(Mount { path, backend });
+
+        Ok(())
+    }
+
+    pub fn unmount(&mut self, path: &str) -> Result<Box<dyn Vfs>, String> {
+        if path == "/" {
+            return Err(String::from("Cannot unmount the root filesystem"));
+        }
+
+        let index = self
+            .mounts
+            .iter()
+            .position(|mount| mount.path == path)
+            .ok_or_else(|| format!("'{}' is not a mount point", path))?;
+
+        if self.has_mounts_below(path) {
+            return Err(format!("'{}' has filesystems mounted below it", path));
+        }
+
+        Ok(self.mounts.remove(index).backend)
+    }
+
+    // Whether another filesystem is mounted strictly below `path`
+    pub fn has_mounts_below(&self, path: &str) -> bool {
+        self.mounts
+            .iter()
+            .any(|mount| mount.path != path && relative_to(path, &mount.path).is_some())
+    }
+
+    // Whether `path` is a mount point or has a filesystem mounted somewhere below it
+    pub fn is_busy(&self, path: &str) -> bool {
+        self.mounts
+            .iter()
+            .any(|mount| relative_to(path, &mount.path).is_some())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Mount> {
+        self.mounts.iter()
+    }
+}
@@ -0,0 +1,208 @@
+use super::vfs::{Attributes, DirEntry, Metadata, NodeKind, Vfs};
+use crate::{allocator, clock, interrupts, memory};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Produces the current content of a file
+type Generator = fn() -> String;
+
+// Files of the procfs and the functions generating their content
+const FILES: [(&str, Generator); 4] = [
+    ("interrupts", interrupts_file),
+    ("meminfo", meminfo_file),
+    ("uptime", uptime_file),
+    ("version", version_file),
+];
+
+const FILE_MODE: u16 = 0o444;
+const DIRECTORY_MODE: u16 = 0o555;
+
+// Read-only view of kernel state, usually mounted at `/proc`
+//
+// The filesystem is a single directory whose files are generated anew on every read, so their
+// size is only known by generating them and two reads may see different content.
+pub struct ProcFs;
+
+impl ProcFs {
+    pub fn new() -> Self {
+        Self
+    }
+
+    // Returns the index of the file at `path` in `FILES`, `None` for the root directory
+    fn find(&self, path: &str) -> Result<Option<usize>, String> {
+        let name = path.trim_start_matches('/');
+        if name.is_empty() {
+            return Ok(None);
+        }
+
+        FILES
+            .iter()
+            .position(|(file, _)| *file == name)
+            .map(Some)
+            .ok_or_else(|| format!("'{}' not found", path))
+    }
+
+    fn read_only(&self) -> Result<(), String> {
+        Err(String::from("proc is read-only"))
+    }
+}
+
+impl Default for ProcFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vfs for ProcFs {
+    fn name(&self) -> &str {
+        "proc"
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        match self.find(path)? {
+            Some(_) => Ok(NodeKind::File),
+            None => Ok(NodeKind::Directory),
+        }
+    }
+
+    fn create(&mut self, _path: &str, _kind: NodeKind) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        let index = self
+            .find(path)?
+            .ok_or_else(|| format!("'{}' is a directory", path))?;
+
+        let content = (FILES[index].1)();
+        let start = offset.min(content.len());
+        let count = buffer.len().min(content.len() - start);
+        buffer[..count].copy_from_slice(&content.as_bytes()[start..start + count]);
+
+        Ok(count)
+    }
+
+    fn write(&mut self, _path: &str, _offset: usize, _data: &[u8]) -> Result<usize, String> {
+        self.read_only().map(|_| 0)
+    }
+
+    fn truncate(&mut self, _path: &str, _len: usize) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        if self.find(path)?.is_some() {
+            return Err(format!("'{}' is not a directory", path));
+        }
+
+        let entries = FILES
+            .iter()
+            .map(|(name, _)| DirEntry {
+                name: String::from(*name),
+                kind: NodeKind::File,
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn rename(&mut self, _from: &str, _to: &str) -> Result<(), String> {
+        self.read_only()
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let (kind, size, inode, mode) = match self.find(path)? {
+            Some(index) => (
+                NodeKind::File,
+                (FILES[index].1)().len(),
+                index as u64 + 2,
+                FILE_MODE,
+            ),
+            None => (NodeKind::Directory, FILES.len(), 1, DIRECTORY_MODE),
+        };
+
+        // Everything is generated on demand, so every node is as new as the data it shows
+        let now = clock::now();
+        Ok(Metadata {
+            kind,
+            size,
+            inode,
+            links: 1,
+            uid: 0,
+            gid: 0,
+            mode,
+            created: now,
+            modified: now,
+            accessed: now,
+        })
+    }
+
+    fn set_attributes(&mut self, _path: &str, _attributes: &Attributes) -> Result<(), String> {
+        self.read_only()
+    }
+}
+
+// Memory in kB like Linux, physical memory first, then the kernel heap
+fn meminfo_file() -> String {
+    let frames = memory::frame_stats();
+    let heap = allocator::stats();
+    let frame_kb = memory::FRAME_SIZE / 1024;
+
+    let fields = [
+        ("MemTotal", frames.usable * frame_kb, " kB"),
+        (
+            "MemFree",
+            frames.usable.saturating_sub(frames.allocated) * frame_kb,
+            " kB",
+        ),
+        ("FramesTotal", frames.usable, ""),
+        ("FramesUsed", frames.allocated, ""),
+        ("HeapTotal", heap.size / 1024, " kB"),
+        ("HeapUsed", heap.used / 1024, " kB"),
+        (
+            "HeapFree",
+            heap.size.saturating_sub(heap.used) / 1024,
+            " kB",
+        ),
+        ("HeapAllocations", heap.allocations, ""),
+    ];
+
+    fields
+        .iter()
+        .map(|(name, value, unit)| format!("{:<16}{:>10}{}\n", format!("{}:", name), value, unit))
+        .collect()
+}
+
+// One line per vector with a handler, whether it was raised yet or not
+fn interrupts_file() -> String {
+    let mut text = String::from("Vector      Count  Name\n");
+    for (vector, name) in interrupts::HANDLED_VECTORS {
+        text.push_str(&format!(
+            "{:>6} {:>10}  {}\n",
+            vector,
+            interrupts::count(vector),
+            name
+        ));
+    }
+
+    text
+}
+
+// Seconds since boot with two decimals, as the first field of the Linux file
+fn uptime_file() -> String {
+    let uptime = clock::uptime_ms();
+    format!("{}.{:02}\n", uptime / 1000, uptime % 1000 / 10)
+}
+
+fn version_file() -> String {
+    format!(
+        "{} version {} (x86_64)\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )
+}
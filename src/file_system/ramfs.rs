@@ -0,0 +1,428 @@
+use super::vfs::{
+    Attributes, DirEntry, Metadata, NodeKind, Vfs, DEFAULT_DIRECTORY_MODE, DEFAULT_FILE_MODE,
+};
+use super::{resolve_path, split_path};
+use crate::clock;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+// Stable identifier of a node, never reused while the filesystem exists
+pub type InodeId = u64;
+
+const ROOT_INODE: InodeId = 1;
+
+pub enum InodeData {
+    File(Vec<u8>),
+    // Entry name -> inode id
+    Directory(BTreeMap<String, InodeId>),
+    // Target path of a symbolic link, relative targets are resolved against the link's directory
+    Symlink(String),
+}
+
+pub struct Inode {
+    pub data: InodeData,
+    // Number of directory entries pointing at this inode
+    pub links: usize,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u16,
+    pub created: u64,
+    pub modified: u64,
+    // Updated by reads, which only have shared access to the filesystem
+    pub accessed: Cell<u64>,
+}
+
+impl Inode {
+    fn new(data: InodeData) -> Self {
+        let mode = match data {
+            InodeData::File(_) => DEFAULT_FILE_MODE,
+            InodeData::Directory(_) => DEFAULT_DIRECTORY_MODE,
+            // Permissions of a symbolic link are never checked, only those of its target
+            InodeData::Symlink(_) => 0o777,
+        };
+        let now = clock::now();
+
+        Self {
+            data,
+            links: 1,
+            uid: 0,
+            gid: 0,
+            mode,
+            created: now,
+            modified: now,
+            accessed: Cell::new(now),
+        }
+    }
+
+    fn kind(&self) -> NodeKind {
+        match self.data {
+            InodeData::File(_) => NodeKind::File,
+            InodeData::Directory(_) => NodeKind::Directory,
+            InodeData::Symlink(_) => NodeKind::Symlink,
+        }
+    }
+}
+
+// In-memory filesystem keeping an inode table on the heap
+//
+// Directories only map names to inode ids, so a file can be reachable from several directory
+// entries (hard links) and renames never copy data.
+pub struct RamFs {
+    inodes: BTreeMap<InodeId, Inode>,
+    next_id: InodeId,
+}
+
+impl RamFs {
+    pub fn new() -> Self {
+        let mut inodes = BTreeMap::new();
+        inodes.insert(
+            ROOT_INODE,
+            Inode::new(InodeData::Directory(BTreeMap::new())),
+        );
+
+        Self {
+            inodes,
+            next_id: ROOT_INODE + 1,
+        }
+    }
+
+    fn inode(&self, id: InodeId) -> &Inode {
+        self.inodes.get(&id).expect("dangling directory entry")
+    }
+
+    fn inode_mut(&mut self, id: InodeId) -> &mut Inode {
+        self.inodes.get_mut(&id).expect("dangling directory entry")
+    }
+
+    // Walks `path` from the root and returns the id of the inode it names
+    fn find_inode(&self, path: &str) -> Result<InodeId, String> {
+        let mut current = ROOT_INODE;
+
+        for part in resolve_path("/", path) {
+            current = match &self.inode(current).data {
+                InodeData::Directory(entries) => *entries
+                    .get(&part)
+                    .ok_or_else(|| format!("'{}' not found", path))?,
+                _ => return Err(format!("'{}' is not a directory", part)),
+            };
+        }
+
+        Ok(current)
+    }
+
+    fn find_entries(&self, path: &str) -> Result<&BTreeMap<String, InodeId>, String> {
+        match &self.inode(self.find_inode(path)?).data {
+            InodeData::Directory(entries) => Ok(entries),
+            _ => Err(format!("'{}' is not a directory", path)),
+        }
+    }
+
+    // Like `find_entries` but for changing the directory, which counts as a modification
+    fn find_entries_mut(&mut self, path: &str) -> Result<&mut BTreeMap<String, InodeId>, String> {
+        let id = self.find_inode(path)?;
+        let inode = self.inode_mut(id);
+        inode.modified = clock::now();
+
+        match &mut inode.data {
+            InodeData::Directory(entries) => Ok(entries),
+            _ => Err(format!("'{}' is not a directory", path)),
+        }
+    }
+
+    // Returns the content of the file at `path` for reading, which counts as an access
+    fn find_content(&self, path: &str) -> Result<&Vec<u8>, String> {
+        let inode = self.inode(self.find_inode(path)?);
+        inode.accessed.set(clock::now());
+
+        match &inode.data {
+            InodeData::File(content) => Ok(content),
+            InodeData::Directory(_) => Err(format!("'{}' is a directory", path)),
+            InodeData::Symlink(_) => Err(format!("'{}' is a symbolic link", path)),
+        }
+    }
+
+    // Returns the content of the file at `path` for writing, which counts as a modification
+    fn find_content_mut(&mut self, path: &str) -> Result<&mut Vec<u8>, String> {
+        let id = self.find_inode(path)?;
+        let inode = self.inode_mut(id);
+        inode.modified = clock::now();
+
+        match &mut inode.data {
+            InodeData::File(content) => Ok(content),
+            InodeData::Directory(_) => Err(format!("'{}' is a directory", path)),
+            InodeData::Symlink(_) => Err(format!("'{}' is a symbolic link", path)),
+        }
+    }
+
+    // Adds the entry `path` -> `id` to its parent directory
+    fn insert_entry(&mut self, path: &str, id: InodeId) -> Result<(), String> {
+        let (parent, name) = split_path("/", path)?;
+        let entries = self.find_entries_mut(&parent)?;
+
+        if entries.contains_key(&name) {
+            return Err(format!("A node with the name '{}' already exists", name));
+        }
+        entries.insert(name, id);
+
+        Ok(())
+    }
+
+    // Drops a link to `id` and frees the inode once nothing points at it anymore
+    fn release(&mut self, id: InodeId) {
+        let inode = self.inode_mut(id);
+        inode.links -= 1;
+
+        if inode.links == 0 {
+            self.inodes.remove(&id);
+        }
+    }
+}
+
+impl Default for RamFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vfs for RamFs {
+    fn name(&self) -> &str {
+        "ramfs"
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeKind, String> {
+        Ok(self.inode(self.find_inode(path)?).kind())
+    }
+
+    fn create(&mut self, path: &str, kind: NodeKind) -> Result<(), String> {
+        let id = self.next_id;
+        self.insert_entry(path, id)?;
+
+        let data = match kind {
+            NodeKind::File => InodeData::File(Vec::new()),
+            NodeKind::Directory => InodeData::Directory(BTreeMap::new()),
+            NodeKind::Symlink => {
+                return Err(String::from("Symbolic links are created with symlink"))
+            }
+            NodeKind::CharDevice | NodeKind::BlockDevice => {
+                return Err(format!("{} does not support device nodes", self.name()))
+            }
+        };
+        self.inodes.insert(id, Inode::new(data));
+        self.next_id += 1;
+
+        Ok(())
+    }
+
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        let content = self.find_content(path)?;
+
+        let start = offset.min(content.len());
+        let count = buffer.len().min(content.len() - start);
+        buffer[..count].copy_from_slice(&content[start..start + count]);
+
+        Ok(count)
+    }
+
+    fn write(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, String> {
+        let content = self.find_content_mut(path)?;
+
+        let end = offset
+            .checked_add(data.len())
+            .ok_or_else(|| String::from("Write past the maximum file size"))?;
+
+        if content.len() < end {
+            content.resize(end, 0);
+        }
+        content[offset..end].copy_from_slice(data);
+
+        Ok(data.len())
+    }
+
+    fn truncate(&mut self, path: &str, len: usize) -> Result<(), String> {
+        self.find_content_mut(path)?.resize(len, 0);
+
+        Ok(())
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let id = self.find_inode(path)?;
+        self.inode(id).accessed.set(clock::now());
+
+        let entries = self
+            .find_entries(path)?
+            .iter()
+            .map(|(name, id)| DirEntry {
+                name: name.clone(),
+                kind: self.inode(*id).kind(),
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn unlink(&mut self, path: &str) -> Result<(), String> {
+        // Locate the parent directory of the node to delete
+        let (parent, name) = split_path("/", path)?;
+
+        let id = *self
+            .find_entries(&parent)?
+            .get(&name)
+            .ok_or_else(|| format!("Node '{}' not found in '{}'", name, parent))?;
+
+        if let InodeData::Directory(entries) = &self.inode(id).data {
+            if !entries.is_empty() {
+                return Err(format!("Directory '{}' is not empty", name));
+            }
+        }
+
+        self.find_entries_mut(&parent)?.remove(&name);
+        self.release(id);
+
+        Ok(())
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let (from_parent, old_name) = split_path("/", from)?;
+        let id = self.find_inode(from)?;
+
+        // A directory moved below itself would be detached from the tree
+        if to.starts_with(from) && to[from.len()..].starts_with('/') {
+            return Err(format!("Cannot move '{}' into itself", from));
+        }
+
+        self.insert_entry(to, id)?;
+        self.find_entries_mut(&from_parent)?.remove(&old_name);
+
+        Ok(())
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<(), String> {
+        let id = self.find_inode(existing)?;
+
+        if self.inode(id).kind() == NodeKind::Directory {
+            return Err(format!("'{}' is a directory", existing));
+        }
+
+        self.insert_entry(new, id)?;
+        self.inode_mut(id).links += 1;
+
+        Ok(())
+    }
+
+    fn symlink(&mut self, target: &str, path: &str) -> Result<(), String> {
+        let id = self.next_id;
+        self.insert_entry(path, id)?;
+
+        self.inodes
+            .insert(id, Inode::new(InodeData::Symlink(String::from(target))));
+        self.next_id += 1;
+
+        Ok(())
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, String> {
+        let inode = self.inode(self.find_inode(path)?);
+        inode.accessed.set(clock::now());
+
+        match &inode.data {
+            InodeData::Symlink(target) => Ok(target.clone()),
+            _ => Err(format!("'{}' is not a symbolic link", path)),
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String> {
+        let id = self.find_inode(path)?;
+        let inode = self.inode(id);
+
+        let size = match &inode.data {
+            InodeData::File(content) => content.len(),
+            InodeData::Directory(entries) => entries.len(),
+            InodeData::Symlink(target) => target.len(),
+        };
+
+        Ok(Metadata {
+            kind: inode.kind(),
+            size,
+            inode: id,
+            links: inode.links,
+            uid: inode.uid,
+            gid: inode.gid,
+            mode: inode.mode,
+            created: inode.created,
+            modified: inode.modified,
+            accessed: inode.accessed.get(),
+        })
+    }
+
+    fn set_attributes(&mut self, path: &str, attributes: &Attributes) -> Result<(), String> {
+        let id = self.find_inode(path)?;
+        let inode = self.inode_mut(id);
+
+        inode.uid = attributes.uid.unwrap_or(inode.uid);
+        inode.gid = attributes.gid.unwrap_or(inode.gid);
+        inode.mode = attributes.mode.unwrap_or(inode.mode);
+        inode.created = attributes.created.unwrap_or(inode.created);
+        inode.modified = attributes.modified.unwrap_or(inode.modified);
+        if let Some(accessed) = attributes.accessed {
+            inode.accessed.set(accessed);
+        }
+
+        Ok(())
+    }
+
+    // Walks the inode table itself, a dangling entry would make the path based walk panic
+    fn check(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        match self.inodes.get(&ROOT_INODE) {
+            Some(inode) if inode.kind() == NodeKind::Directory => {}
+            _ => return Vec::from([String::from("The root directory is missing")]),
+        }
+
+        // Entries pointing at each inode, and the inodes reachable from the root
+        let mut references: BTreeMap<InodeId, usize> = BTreeMap::new();
+        let mut reachable = BTreeSet::from([ROOT_INODE]);
+        let mut pending = Vec::from([ROOT_INODE]);
+
+        while let Some(id) = pending.pop() {
+            let entries = match &self.inode(id).data {
+                InodeData::Directory(entries) => entries,
+                _ => continue,
+            };
+
+            for (name, target) in entries {
+                if !self.inodes.contains_key(target) {
+                    problems.push(format!(
+                        "Entry '{}' of inode {} points at the missing inode {}",
+                        name, id, target
+                    ));
+                    continue;
+                }
+
+                *references.entry(*target).or_default() += 1;
+                if reachable.insert(*target) {
+                    pending.push(*target);
+                }
+            }
+        }
+
+        for (id, inode) in &self.inodes {
+            if !reachable.contains(id) {
+                problems.push(format!("Inode {} cannot be reached from the root", id));
+            } else if *id != ROOT_INODE && references[id] != inode.links {
+                problems.push(format!(
+                    "Inode {} has {} links but {} entries point at it",
+                    id, inode.links, references[id]
+                ));
+            }
+            if *id >= self.next_id {
+                problems.push(format!("Inode {} is past the next free id", id));
+            }
+        }
+
+        problems
+    }
+}
@@ -0,0 +1,277 @@
+use super::normalize_path;
+use super::ramfs::RamFs;
+use super::vfs::{Attributes, NodeKind, Vfs};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Reader for ustar archives, the format written by `tar --format=ustar` and, with a few
+// extensions handled below, by GNU tar and pax
+//
+// An archive is a sequence of 512-byte blocks. Every entry is a header block followed by its
+// content padded to a whole block, and two zero blocks end the archive. The header fields used
+// here are:
+//
+//   name      0   100  path, `prefix` + `/` + `name` when the prefix is set
+//   mode      100 8    permission bits in octal
+//   uid, gid  108 8    owner in octal
+//   size      124 12   content length in octal
+//   mtime     136 12   modification time in octal
+//   checksum  148 8    sum of the header bytes with this field taken as spaces
+//   type      156 1    see the `TYPE_` constants
+//   linkname  157 100  target of links
+//   magic     257 6    `ustar\0`, or `ustar ` followed by a space for GNU tar
+//   prefix    345 155
+//
+// Numbers may also use the base-256 encoding of GNU tar, flagged by the high bit of the first
+// byte.
+
+const BLOCK_SIZE: usize = 512;
+
+const TYPE_FILE: u8 = b'0';
+// Very old archives mark files with a NUL instead of `0`
+const TYPE_OLD_FILE: u8 = 0;
+const TYPE_HARD_LINK: u8 = b'1';
+const TYPE_SYMLINK: u8 = b'2';
+const TYPE_DIRECTORY: u8 = b'5';
+const TYPE_CONTIGUOUS_FILE: u8 = b'7';
+// pax extended header with `key=value` records for the next entry
+const TYPE_PAX_HEADER: u8 = b'x';
+// pax header applying to the whole archive, nothing in it is used
+const TYPE_PAX_GLOBAL_HEADER: u8 = b'g';
+// GNU tar stores names and link targets longer than 100 bytes as the content of these entries
+const TYPE_GNU_LONG_NAME: u8 = b'L';
+const TYPE_GNU_LONG_LINK: u8 = b'K';
+
+// Unpacks `archive` into a new `ramfs` and returns it, keeping modes, owners and modification
+// times
+//
+// Parent directories missing from the archive are created with the default mode. Character and
+// block devices and FIFOs have no equivalent in the tree and are skipped.
+pub fn unpack(archive: &[u8]) -> Result<RamFs, String> {
+    let mut fs = RamFs::new();
+    let mut position = 0;
+    // Applied once every node exists, creating entries would bump the directory timestamps
+    let mut attributes = Vec::new();
+    // Overrides for the next entry from pax or GNU headers
+    let mut long_name = None;
+    let mut long_link = None;
+
+    // Some writers leave out the zero blocks at the end
+    while position != archive.len() {
+        let header = archive
+            .get(position..position + BLOCK_SIZE)
+            .ok_or_else(|| String::from("Archive is truncated"))?;
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        check_header(header)?;
+
+        let size = usize::try_from(number(&header[124..136])?)
+            .map_err(|_| String::from("Archive entry is too large"))?;
+        let start = position + BLOCK_SIZE;
+        let content = start
+            .checked_add(size)
+            .and_then(|end| archive.get(start..end))
+            .ok_or_else(|| String::from("Archive is truncated"))?;
+        position = start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+
+        let kind = header[156];
+        match kind {
+            TYPE_PAX_HEADER => {
+                for (key, value) in pax_records(content)? {
+                    match key {
+                        "path" => long_name = Some(String::from(value)),
+                        "linkpath" => long_link = Some(String::from(value)),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            TYPE_GNU_LONG_NAME => {
+                long_name = Some(text(content)?);
+                continue;
+            }
+            TYPE_GNU_LONG_LINK => {
+                long_link = Some(text(content)?);
+                continue;
+            }
+            TYPE_PAX_GLOBAL_HEADER => continue,
+            _ => {}
+        }
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => header_name(header)?,
+        };
+        let link = match long_link.take() {
+            Some(link) => link,
+            None => text(&header[157..257])?,
+        };
+        let path = normalize_path("/", &name);
+
+        let modified = number(&header[136..148])?;
+        let node_attributes = Attributes {
+            mode: Some((number(&header[100..108])? & 0o7777) as u16),
+            uid: Some(number(&header[108..116])? as u32),
+            gid: Some(number(&header[116..124])? as u32),
+            created: Some(modified),
+            modified: Some(modified),
+            accessed: Some(modified),
+        };
+
+        match kind {
+            TYPE_FILE | TYPE_OLD_FILE | TYPE_CONTIGUOUS_FILE => {
+                create_parents(&mut fs, &path)?;
+                match fs.lookup(&path) {
+                    Ok(NodeKind::File) => fs.truncate(&path, 0)?,
+                    _ => fs.create(&path, NodeKind::File)?,
+                }
+                fs.write(&path, 0, content)?;
+            }
+            TYPE_DIRECTORY => {
+                if fs.lookup(&path) != Ok(NodeKind::Directory) {
+                    create_parents(&mut fs, &path)?;
+                    fs.create(&path, NodeKind::Directory)?;
+                }
+            }
+            TYPE_SYMLINK => {
+                create_parents(&mut fs, &path)?;
+                fs.symlink(&link, &path)?;
+            }
+            TYPE_HARD_LINK => {
+                // Link targets are paths inside the archive, like the names of the entries
+                create_parents(&mut fs, &path)?;
+                fs.link(&normalize_path("/", &link), &path)?;
+                continue;
+            }
+            _ => continue,
+        }
+
+        attributes.push((path, node_attributes));
+    }
+
+    for (path, node_attributes) in attributes {
+        fs.set_attributes(&path, &node_attributes)?;
+    }
+
+    Ok(fs)
+}
+
+fn check_header(header: &[u8]) -> Result<(), String> {
+    if &header[257..262] != b"ustar" {
+        return Err(String::from("Not a ustar archive"));
+    }
+
+    // The checksum field itself counts as eight spaces
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| match i {
+            148..156 => u64::from(b' '),
+            _ => u64::from(byte),
+        })
+        .sum();
+    if number(&header[148..156])? != sum {
+        return Err(String::from(
+            "Archive header checksum does not match, the archive is corrupted",
+        ));
+    }
+
+    Ok(())
+}
+
+// Joins the prefix and name fields, GNU tar uses the prefix area for other data so it only
+// counts in POSIX archives
+fn header_name(header: &[u8]) -> Result<String, String> {
+    let name = text(&header[0..100])?;
+
+    if &header[257..263] == b"ustar\0" && header[345] != 0 {
+        return Ok(format!("{}/{}", text(&header[345..500])?, name));
+    }
+
+    Ok(name)
+}
+
+// Decodes a NUL-terminated or NUL-padded string field
+fn text(field: &[u8]) -> Result<String, String> {
+    let end = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+
+    String::from_utf8(field[..end].to_vec())
+        .map_err(|_| String::from("Archive contains an invalid name"))
+}
+
+// Decodes a numeric field, octal digits padded with spaces or NULs, or base-256
+fn number(field: &[u8]) -> Result<u64, String> {
+    if field[0] & 0x80 != 0 {
+        let value = field[1..]
+            .iter()
+            .fold(u64::from(field[0] & 0x7F), |value, &byte| {
+                (value << 8) | u64::from(byte)
+            });
+        return Ok(value);
+    }
+
+    let digits = field
+        .iter()
+        .skip_while(|&&byte| byte == b' ')
+        .take_while(|&&byte| byte != b' ' && byte != 0);
+
+    let mut value = 0u64;
+    for &digit in digits {
+        if !(b'0'..=b'7').contains(&digit) {
+            return Err(String::from("Archive header contains an invalid number"));
+        }
+        value = value
+            .checked_mul(8)
+            .map(|value| value + u64::from(digit - b'0'))
+            .ok_or_else(|| String::from("Archive header contains an invalid number"))?;
+    }
+
+    Ok(value)
+}
+
+// Splits pax records, each is `<length> <key>=<value>\n` where the length covers the whole record
+fn pax_records(content: &[u8]) -> Result<Vec<(&str, &str)>, String> {
+    let invalid = || String::from("Archive contains an invalid pax header");
+    let content = core::str::from_utf8(content).map_err(|_| invalid())?;
+
+    let mut records = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let (length, _) = rest.split_once(' ').ok_or_else(invalid)?;
+        let length: usize = length.parse().map_err(|_| invalid())?;
+        let record = rest
+            .get(..length)
+            .and_then(|record| record.strip_suffix('\n'))
+            .ok_or_else(invalid)?;
+        let (_, pair) = record.split_once(' ').ok_or_else(invalid)?;
+
+        records.push(pair.split_once('=').ok_or_else(invalid)?);
+        rest = &rest[length..];
+    }
+
+    Ok(records)
+}
+
+// Creates the missing directories above `path`, archives do not have to list them
+fn create_parents(fs: &mut RamFs, path: &str) -> Result<(), String> {
+    let mut parent = String::new();
+    let components: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+
+    for component in components.iter().take(components.len().saturating_sub(1)) {
+        parent.push('/');
+        parent.push_str(component);
+
+        match fs.lookup(&parent) {
+            Ok(NodeKind::Directory) => {}
+            Ok(_) => return Err(format!("'{}' in the archive is not a directory", parent)),
+            Err(_) => fs.create(&parent, NodeKind::Directory)?,
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use crate::devices::block::SharedBlockDevice;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Directory,
+    Symlink,
+    // Devices read and written a byte stream at a time, like a terminal
+    CharDevice,
+    // Devices addressed by offset with a fixed size, like a disk
+    BlockDevice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub kind: NodeKind,
+}
+
+// Permission bits given to new nodes
+pub const DEFAULT_FILE_MODE: u16 = 0o644;
+pub const DEFAULT_DIRECTORY_MODE: u16 = 0o755;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub kind: NodeKind,
+    // Length in bytes for files, number of entries for directories, length of the target for
+    // symbolic links, capacity in bytes for block devices and 0 for character devices
+    pub size: usize,
+    // Identifier of the node, stable for as long as the node exists except on FAT, where it
+    // changes when the node is renamed or moved
+    pub inode: u64,
+    // Number of directory entries referring to the node
+    pub links: usize,
+    pub uid: u32,
+    pub gid: u32,
+    // Unix permission bits, e.g. 0o644
+    pub mode: u16,
+    // Timestamps in seconds since the Unix epoch
+    pub created: u64,
+    pub modified: u64,
+    pub accessed: u64,
+}
+
+impl Metadata {
+    // Renders the kind and permission bits the way `ls -l` does, e.g. `drwxr-xr-x`
+    pub fn permissions(&self) -> String {
+        let mut permissions = String::with_capacity(10);
+
+        permissions.push(match self.kind {
+            NodeKind::File => '-',
+            NodeKind::Directory => 'd',
+            NodeKind::Symlink => 'l',
+            NodeKind::CharDevice => 'c',
+            NodeKind::BlockDevice => 'b',
+        });
+
+        for shift in [6, 3, 0] {
+            let bits = self.mode >> shift;
+            permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            permissions.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+        }
+
+        permissions
+    }
+}
+
+// Metadata that can be changed after a node was created, `None` fields are left as they are
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub mode: Option<u16>,
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+}
+
+// Operations every mountable filesystem implements
+//
+// Paths are absolute and normalized relative to the root of the filesystem itself, the
+// `FileSystem` mount table strips the mount point before calling into a backend. Backends that
+// talk to hardware use interior mutability for the read-only operations.
+pub trait Vfs: Send {
+    // Short type name shown by `mount`, e.g. `ramfs`
+    fn name(&self) -> &str;
+
+    // The disk the filesystem is stored on, `None` for filesystems kept in memory
+    fn device(&self) -> Option<&SharedBlockDevice> {
+        None
+    }
+
+    // Symbolic links are never followed by a backend, `FileSystem` resolves them across mounts
+    fn lookup(&self, path: &str) -> Result<NodeKind, String>;
+
+    // Creates an empty file or directory, the parent directory must already exist
+    fn create(&mut self, path: &str, kind: NodeKind) -> Result<(), String>;
+
+    // Reads into `buffer` starting at `offset` and returns the number of bytes read, which is 0
+    // at or past the end of file
+    fn read(&self, path: &str, offset: usize, buffer: &mut [u8]) -> Result<usize, String>;
+
+    // Writes `data` at `offset`, zero-filling any gap past the old end of file
+    fn write(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, String>;
+
+    // Shrinks or zero-extends a file to exactly `len` bytes
+    fn truncate(&mut self, path: &str, len: usize) -> Result<(), String>;
+
+    fn readdir(&self, path: &str) -> Result<Vec<DirEntry>, String>;
+
+    // Removes a file or an empty directory
+    fn unlink(&mut self, path: &str) -> Result<(), String>;
+
+    // Moves the node at `from` to `to`, which may lie in another directory but must not exist
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), String>;
+
+    // Adds `new` as another name for the file at `existing`
+    fn link(&mut self, existing: &str, new: &str) -> Result<(), String> {
+        let _ = (existing, new);
+        Err(format!("{} does not support hard links", self.name()))
+    }
+
+    // Creates a symbolic link at `path` pointing to `target`, which is stored as given
+    fn symlink(&mut self, target: &str, path: &str) -> Result<(), String> {
+        let _ = (target, path);
+        Err(format!("{} does not support symbolic links", self.name()))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, String> {
+        let _ = path;
+        Err(format!("{} does not support symbolic links", self.name()))
+    }
+
+    fn stat(&self, path: &str) -> Result<Metadata, String>;
+
+    fn set_attributes(&mut self, path: &str, attributes: &Attributes) -> Result<(), String> {
+        let _ = (path, attributes);
+        Err(format!(
+            "{} does not support changing attributes",
+            self.name()
+        ))
+    }
+
+    // Problems in structures that cannot be seen through paths, like nodes no directory points
+    // at, reported by `fsck` before it walks the tree
+    fn check(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,67 @@
+use super::vfs::NodeKind;
+use super::{normalize_path, FileSystem};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// A node visited by `Walk`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkEntry {
+    // Absolute path below the path the walk started at
+    pub path: String,
+    pub name: String,
+    pub kind: NodeKind,
+    // 0 for the starting node, 1 for its entries and so on
+    pub depth: usize,
+}
+
+// Depth-first iterator over a subtree, yielding each directory before its entries and entries
+// in name order
+//
+// Symbolic links are yielded but never descended into, so a link back to an ancestor cannot make
+// the walk loop. A directory that cannot be read is yielded followed by the error, then the walk
+// goes on with the rest of the subtree.
+pub struct Walk<'a> {
+    fs: &'a FileSystem,
+    // Nodes still to visit, the next one is at the end
+    pending: Vec<WalkEntry>,
+    // Why the directory yielded last could not be read
+    error: Option<String>,
+}
+
+impl<'a> Walk<'a> {
+    pub(super) fn new(fs: &'a FileSystem, root: WalkEntry) -> Self {
+        Self {
+            fs,
+            pending: Vec::from([root]),
+            error: None,
+        }
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = Result<WalkEntry, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let entry = self.pending.pop()?;
+
+        if entry.kind == NodeKind::Directory {
+            match self.fs.read_dir(&entry.path) {
+                Ok(children) => {
+                    self.pending
+                        .extend(children.into_iter().rev().map(|child| WalkEntry {
+                            path: normalize_path(&entry.path, &child.name),
+                            name: child.name,
+                            kind: child.kind,
+                            depth: entry.depth + 1,
+                        }))
+                }
+                Err(error) => self.error = Some(error),
+            }
+        }
+
+        Some(Ok(entry))
+    }
+}
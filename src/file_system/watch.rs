@@ -0,0 +1,168 @@
+use super::{mount, split_path};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Upper bound on the number of watches that can exist at the same time
+pub const MAX_WATCHES: usize = 32;
+// Events kept for a watch until they are read, the last slot is taken by an `Overflow` event
+pub const MAX_QUEUED_EVENTS: usize = 128;
+
+// Opaque reference to a watch, only meaningful to the `FileSystem` that returned it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WatchId(usize);
+
+impl WatchId {
+    // The number shown to the user, e.g. by the `watch` command
+    pub fn number(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    // A file, directory or link was created, including hard links to existing files
+    Create,
+    // Content of a file changed, by a write or by truncating it
+    Modify,
+    Delete,
+    // The node moved to `Event::destination`, which may be in another directory
+    Rename,
+    // The queue was full and later events were dropped until it was read
+    Overflow,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    // Absolute path of the node, the old one for a rename and the watched path for an overflow
+    pub path: String,
+    // Absolute path a renamed node moved to
+    pub destination: Option<String>,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, path: &str) -> Self {
+        Self {
+            kind,
+            path: String::from(path),
+            destination: None,
+        }
+    }
+
+    pub fn rename(from: &str, to: &str) -> Self {
+        Self {
+            kind: EventKind::Rename,
+            path: String::from(from),
+            destination: Some(String::from(to)),
+        }
+    }
+}
+
+// Subscription to the changes at a path
+//
+// Watches follow paths rather than nodes: deleting or renaming the watched node ends nothing, and
+// a node created at the path later is watched again.
+pub struct Watch {
+    // Absolute, canonical path of the watched node
+    pub path: String,
+    // Whether changes anywhere below `path` count, otherwise only those to the node itself and
+    // its direct children do
+    pub recursive: bool,
+    events: VecDeque<Event>,
+}
+
+impl Watch {
+    fn matches(&self, path: &str) -> bool {
+        if self.recursive {
+            return mount::relative_to(&self.path, path).is_some();
+        }
+
+        path == self.path || split_path("/", path).is_ok_and(|(parent, _)| parent == self.path)
+    }
+
+    fn push(&mut self, event: Event) {
+        match self.events.len() {
+            length if length >= MAX_QUEUED_EVENTS => {}
+            length if length == MAX_QUEUED_EVENTS - 1 => self
+                .events
+                .push_back(Event::new(EventKind::Overflow, &self.path)),
+            _ => self.events.push_back(event),
+        }
+    }
+}
+
+// Maps ids to watches, ids are reused once a watch is removed
+#[derive(Default)]
+pub struct WatchTable {
+    watches: BTreeMap<usize, Watch>,
+}
+
+impl WatchTable {
+    pub fn new() -> Self {
+        Self {
+            watches: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, path: String, recursive: bool) -> Result<WatchId, String> {
+        // Numbered from 1 so that ids read naturally in the shell
+        let slot = (1..=MAX_WATCHES)
+            .find(|slot| !self.watches.contains_key(slot))
+            .ok_or_else(|| String::from("Too many watches"))?;
+
+        self.watches.insert(
+            slot,
+            Watch {
+                path,
+                recursive,
+                events: VecDeque::new(),
+            },
+        );
+
+        Ok(WatchId(slot))
+    }
+
+    pub fn remove(&mut self, id: WatchId) -> Result<Watch, String> {
+        self.watches
+            .remove(&id.0)
+            .ok_or_else(|| format!("Invalid watch {}", id.0))
+    }
+
+    // Empties the queue of the watch and returns what was in it, oldest first
+    pub fn take_events(&mut self, id: WatchId) -> Result<Vec<Event>, String> {
+        self.watches
+            .get_mut(&id.0)
+            .map(|watch| Vec::from(core::mem::take(&mut watch.events)))
+            .ok_or_else(|| format!("Invalid watch {}", id.0))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (WatchId, &Watch)> {
+        self.watches
+            .iter()
+            .map(|(&slot, watch)| (WatchId(slot), watch))
+    }
+
+    // Queues `event` for every watch covering its path, or its destination for a rename
+    pub fn notify(&mut self, event: Event) {
+        for watch in self.watches.values_mut() {
+            let matches = watch.matches(&event.path)
+                || event
+                    .destination
+                    .as_deref()
+                    .is_some_and(|destination| watch.matches(destination));
+            if matches {
+                watch.push(event.clone());
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.watches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+}
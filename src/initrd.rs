@@ -0,0 +1,12 @@
+extern crate alloc;
+
+use crate::file_system::{ramfs::RamFs, tar};
+use alloc::string::String;
+
+// The `initrd` directory of the source tree, packed into a ustar archive by `build.rs`
+pub static ARCHIVE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/initrd.tar"));
+
+// Unpacks the embedded archive into the root filesystem used when the disk holds none
+pub fn load() -> Result<RamFs, String> {
+    tar::unpack(ARCHIVE)
+}
@@ -1,6 +1,8 @@
+use crate::clock;
 use crate::devices::keyboard::{process_scancode, read_scancode};
 use crate::gdt;
 use crate::println;
+use core::sync::atomic::{AtomicU64, Ordering};
 use lazy_static::lazy_static;
 use pic8259::ChainedPics;
 use spin::Mutex;
@@ -30,6 +32,25 @@ impl InterruptIndex {
     }
 }
 
+// Vectors the IDT has handlers for, with the names shown in `/proc/interrupts`
+pub const HANDLED_VECTORS: [(u8, &str); 4] = [
+    (3, "breakpoint"),
+    (8, "double fault"),
+    (InterruptIndex::Timer as u8, "timer"),
+    (InterruptIndex::Keyboard as u8, "keyboard"),
+];
+
+// Number of times each vector was raised since boot
+static COUNTS: [AtomicU64; 256] = [const { AtomicU64::new(0) }; 256];
+
+pub fn count(vector: u8) -> u64 {
+    COUNTS[usize::from(vector)].load(Ordering::Relaxed)
+}
+
+fn record(vector: u8) {
+    COUNTS[usize::from(vector)].fetch_add(1, Ordering::Relaxed);
+}
+
 // IDT Initialization using lazy_static
 lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
@@ -51,6 +72,7 @@ pub fn init_idt() {
 }
 
 extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
+    record(3);
     println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
 }
 
@@ -58,10 +80,14 @@ extern "x86-interrupt" fn double_fault_handler(
     stack_frame: InterruptStackFrame,
     _error_code: u64,
 ) -> ! {
+    record(8);
     panic!("EXCEPTION: DOUBLE FAULT\n{:#?}", stack_frame);
 }
 
 extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    record(InterruptIndex::Timer.as_u8());
+    clock::tick();
+
     unsafe {
         PICS.lock()
             .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
@@ -69,6 +95,7 @@ extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFr
 }
 
 extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    record(InterruptIndex::Keyboard.as_u8());
     let scancode = read_scancode();
     process_scancode(scancode);
 
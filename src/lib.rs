@@ -13,12 +13,16 @@ use bootloader::{entry_point, BootInfo};
 extern crate alloc;
 
 pub mod allocator;
+pub mod clock;
 pub mod devices;
 pub mod file_system;
 pub mod gdt;
+pub mod initrd;
 pub mod interrupts;
 pub mod memory;
 pub mod serial;
+pub mod storage;
+pub mod users;
 pub mod command;
 
 #[cfg(test)]
@@ -34,6 +38,9 @@ pub fn init() {
     unsafe { interrupts::PICS.lock().initialize() };
     serial_println!("PICs initialized.");
 
+    clock::init();
+    serial_println!("Clock initialized.");
+
     x86_64::instructions::interrupts::enable();
     serial_println!("Interrupts enabled.");
 }
@@ -7,7 +7,7 @@
 extern crate alloc;
 
 use bootloader::{entry_point, BootInfo};
-use charizard::{memory::BootInfoFrameAllocator, println};
+use charizard::{memory::BootInfoFrameAllocator, print, println};
 use core::panic::PanicInfo;
 
 // Tells Rust what the entry function of the OS
@@ -15,7 +15,14 @@ entry_point!(kernel_main);
 
 #[no_mangle]
 fn kernel_main(boot_info: &'static BootInfo) -> ! {
-    use charizard::{allocator, command, devices::keyboard, file_system::FileSystem, memory};
+    use alloc::boxed::Box;
+    use charizard::{
+        allocator,
+        command::{self, Session},
+        devices::{ata, keyboard},
+        file_system::{devfs::DevFs, procfs::ProcFs, ramfs::RamFs, vfs::Vfs, FileSystem},
+        initrd, memory, storage,
+    };
     use x86_64::{instructions, VirtAddr};
 
     println!("Welcome to Charizard!");
@@ -28,7 +35,45 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
 
     allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
 
-    let mut fs = FileSystem::new();
+    ata::init();
+    for error in command::init() {
+        println!("Built-in command not registered: {}", error);
+    }
+
+    let restored = match storage::init() {
+        Ok(Some(root)) => {
+            println!("Filesystem restored from disk.");
+            Some(root)
+        }
+        Ok(None) => None,
+        Err(err) => {
+            println!("{}", err);
+            None
+        }
+    };
+    // Without a saved tree the root starts out with the files of the initial ramdisk
+    let root = restored.unwrap_or_else(|| {
+        initrd::load().unwrap_or_else(|err| {
+            println!("Initial ramdisk could not be unpacked: {}", err);
+            RamFs::new()
+        })
+    });
+    let mut fs = FileSystem::with_root(Box::new(root));
+
+    // A restored tree already has the mount points
+    let kernel_mounts: [(&str, Box<dyn Vfs>); 2] = [
+        ("/proc", Box::new(ProcFs::new())),
+        ("/dev", Box::new(DevFs::new())),
+    ];
+    for (path, backend) in kernel_mounts {
+        if fs.lookup(path).is_err() {
+            let _ = fs.create_directory(path);
+        }
+        if let Err(err) = fs.mount(path, backend) {
+            println!("Could not mount {}: {}", path, err);
+        }
+    }
+    let mut session = Session::new();
 
     println!("Kernel initialized! Waiting for commands...");
 
@@ -36,12 +81,21 @@ fn kernel_main(boot_info: &'static BootInfo) -> ! {
     test_main();
 
     loop {
-        let command = keyboard::read_line();
+        // Watched changes are printed while the shell waits for input too, not only after a
+        // command
+        let command =
+            keyboard::read_line(session.cwd(), || command::watch_events(&mut fs, &session));
 
-        match command::parse_and_execute_command(&command, &mut fs) {
+        match command::parse_and_execute_command(&command, &mut fs, &mut session) {
+            Ok(response) if response.is_empty() => {}
+            Ok(response) if response.ends_with('\n') => print!("{}", response),
             Ok(response) => println!("{}", response),
             Err(err) => println!("Error: {}", err),
         }
+        // Watched changes show up after the output of the command that made them
+        for line in command::watch_events(&mut fs, &session) {
+            println!("{}", line);
+        }
         instructions::hlt();
     }
 }
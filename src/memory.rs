@@ -1,4 +1,5 @@
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use x86_64::{
     structures::paging::{
         FrameAllocator, Mapper, OffsetPageTable, Page, PageTable, PhysFrame, Size4KiB,
@@ -6,6 +7,25 @@ use x86_64::{
     PhysAddr, VirtAddr,
 };
 
+pub const FRAME_SIZE: usize = 4096;
+
+// Usable frames in the memory map and how many of them the frame allocator handed out
+static USABLE_FRAMES: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    pub usable: usize,
+    pub allocated: usize,
+}
+
+pub fn frame_stats() -> FrameStats {
+    FrameStats {
+        usable: USABLE_FRAMES.load(Ordering::Relaxed),
+        allocated: ALLOCATED_FRAMES.load(Ordering::Relaxed),
+    }
+}
+
 pub struct EmptyFrameAllocator;
 
 pub struct BootInfoFrameAllocator {
@@ -16,10 +36,15 @@ pub struct BootInfoFrameAllocator {
 impl BootInfoFrameAllocator {
     // Create a FrameAlloctor from the passed memory map
     pub unsafe fn init(memory_map: &'static MemoryMap) -> Self {
-        BootInfoFrameAllocator {
+        let allocator = BootInfoFrameAllocator {
             memory_map,
             next: 0,
-        }
+        };
+
+        USABLE_FRAMES.store(allocator.usable_frames().count(), Ordering::Relaxed);
+        ALLOCATED_FRAMES.store(0, Ordering::Relaxed);
+
+        allocator
     }
 
     // Returns an iterator over the usable frames specified in the memory map
@@ -32,7 +57,7 @@ impl BootInfoFrameAllocator {
         let addr_ranges = usable_regions.map(|r| r.range.start_addr()..r.range.end_addr());
 
         // Transform to an iterator of frame start addresses
-        let frame_addresses = addr_ranges.flat_map(|r| r.step_by(4096));
+        let frame_addresses = addr_ranges.flat_map(|r| r.step_by(FRAME_SIZE));
 
         // Create `PhyFrame` types from the start address
         frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
@@ -43,6 +68,10 @@ unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
     fn allocate_frame(&mut self) -> Option<PhysFrame> {
         let frame = self.usable_frames().nth(self.next);
         self.next += 1;
+
+        if frame.is_some() {
+            ALLOCATED_FRAMES.fetch_add(1, Ordering::Relaxed);
+        }
         frame
     }
 }
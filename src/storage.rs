@@ -0,0 +1,112 @@
+extern crate alloc;
+
+use crate::devices::block::{self, SharedBlockDevice};
+use crate::file_system::{cfs, ext2, fat, image, ramfs::RamFs, FileSystem};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+
+// The primary master, which holds the kernel image built by `bootimage`
+pub const BOOT_DISK: &str = "hda";
+
+// Keeps the root filesystem as an image at the start of the disk attached as primary slave
+//
+// The boot disk comes first, so the data disk is the second drive QEMU gets, e.g.
+// `-drive file=disk.img,format=raw,index=1`.
+pub const DATA_DISK: &str = "hdb";
+
+// Bytes read from the start of the data disk to tell what it holds, ext2 keeps its superblock
+// 1024 bytes in
+const PROBE_SIZE: u64 = 2048;
+
+fn data_disk() -> Result<SharedBlockDevice, String> {
+    block::get(DATA_DISK).ok_or_else(|| format!("No disk attached as {}", DATA_DISK))
+}
+
+// Returns the filesystem saved on the data disk by the last `sync`, `None` if the disk is blank
+pub fn init() -> Result<Option<RamFs>, String> {
+    let disk = data_disk().map_err(|err| format!("{}, files will not survive a reboot", err))?;
+    let mut disk = disk.lock();
+    let sector_size = disk.sector_size();
+
+    let mut header = vec![0; sector_size];
+    disk.read_sectors(0, &mut header)?;
+
+    let size = match image::image_size(&header)? {
+        Some(size) => size,
+        None => return Ok(None),
+    };
+    if size as u64 > disk.size() {
+        return Err(String::from("Image on disk is larger than the disk"));
+    }
+
+    let mut content = vec![0; size.div_ceil(sector_size) * sector_size];
+    disk.read_sectors(0, &mut content)?;
+
+    image::decode(&content).map(Some)
+}
+
+// Writes the root filesystem to the data disk and returns the size of the image, filesystems
+// mounted on top of it are not included
+pub fn sync(fs: &FileSystem) -> Result<usize, String> {
+    if !fs.credentials().is_root() {
+        return Err(String::from("Only root can sync the filesystem to disk"));
+    }
+    let disk = data_disk()?;
+    if fs.uses_device(&disk) {
+        return Err(format!(
+            "{} is mounted, unmount it before syncing to it",
+            DATA_DISK
+        ));
+    }
+
+    // The image would overwrite the start of the volume
+    let probe_size = PROBE_SIZE.min(disk.lock().size());
+    let mut start = vec![0; probe_size as usize];
+    block::read_bytes(&disk, 0, &mut start)?;
+    if let Some(volume) = existing_volume(&start) {
+        return Err(format!(
+            "{} holds {}, mount it instead of syncing to it",
+            DATA_DISK, volume
+        ));
+    }
+
+    let mut disk = disk.lock();
+    let sector_size = disk.sector_size();
+
+    let mut content = image::encode(fs.root())?;
+    let size = content.len();
+
+    if size as u64 > disk.size() {
+        return Err(format!(
+            "Image of {} bytes does not fit on the disk ({} bytes)",
+            size,
+            disk.size()
+        ));
+    }
+
+    content.resize(size.div_ceil(sector_size) * sector_size, 0);
+    disk.write_sectors(0, &content)?;
+
+    Ok(size)
+}
+
+// Describes what `start`, the first bytes of the data disk, holds when it is anything but blank
+// or an image written by `sync`
+fn existing_volume(start: &[u8]) -> Option<&'static str> {
+    if !matches!(image::image_size(start), Ok(None)) {
+        return None;
+    }
+
+    if cfs::is_volume(start) {
+        Some("a cfs volume")
+    } else if fat::is_volume(start) {
+        Some("a FAT filesystem")
+    } else if ext2::is_volume(start) {
+        Some("an ext2 filesystem")
+    } else if !block::mbr_partitions(start).is_empty() {
+        Some("a partition table")
+    } else {
+        None
+    }
+}
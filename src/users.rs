@@ -0,0 +1,134 @@
+extern crate alloc;
+
+use crate::file_system::{
+    access::Credentials, normalize_path, resolve_path, vfs::Attributes, FileSystem,
+};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use passwd::{Group, User};
+
+pub mod passwd;
+
+// The user and group databases, see `passwd` for their format
+pub const PASSWD_PATH: &str = "/etc/passwd";
+pub const GROUP_PATH: &str = "/etc/group";
+
+// Home directories are created on the first login and only readable by their owner
+const HOME_MODE: u16 = 0o700;
+
+// Returns the users of the system, without a user database there is only root
+pub fn users(fs: &FileSystem) -> Result<Vec<User>, String> {
+    if fs.lookup(PASSWD_PATH).is_err() {
+        return Ok(Vec::from([passwd::root()]));
+    }
+
+    passwd::parse_passwd(&String::from_utf8_lossy(&fs.read_file(PASSWD_PATH)?))
+}
+
+pub fn groups(fs: &FileSystem) -> Result<Vec<Group>, String> {
+    if fs.lookup(GROUP_PATH).is_err() {
+        return Ok(Vec::new());
+    }
+
+    passwd::parse_group(&String::from_utf8_lossy(&fs.read_file(GROUP_PATH)?))
+}
+
+pub fn find_user(fs: &FileSystem, name: &str) -> Result<User, String> {
+    users(fs)?
+        .into_iter()
+        .find(|user| user.name == name)
+        .ok_or_else(|| format!("Unknown user '{}'", name))
+}
+
+// Returns the user named `name` when `password` is theirs
+pub fn authenticate(fs: &FileSystem, name: &str, password: &str) -> Result<User, String> {
+    // The same answer for unknown users and wrong passwords, names are not given away
+    find_user(fs, name)
+        .ok()
+        .filter(|user| user.check_password(password))
+        .ok_or_else(|| String::from("Login incorrect"))
+}
+
+// Credentials of `user`, with the groups that list them as a member
+pub fn credentials(fs: &FileSystem, user: &User) -> Result<Credentials, String> {
+    let groups = groups(fs)?
+        .into_iter()
+        .filter(|group| group.gid != user.gid && group.members.contains(&user.name))
+        .map(|group| group.gid)
+        .collect();
+
+    Ok(Credentials {
+        uid: user.uid,
+        gid: user.gid,
+        groups,
+    })
+}
+
+// Name of the user with `uid`, or the number when there is none
+pub fn user_name(fs: &FileSystem, uid: u32) -> String {
+    users(fs)
+        .ok()
+        .and_then(|users| users.into_iter().find(|user| user.uid == uid))
+        .map_or_else(|| format!("{}", uid), |user| user.name)
+}
+
+// Turns a user name or number, as given to `chown`, into a uid
+pub fn parse_user(fs: &FileSystem, user: &str) -> Result<u32, String> {
+    if let Ok(uid) = user.parse() {
+        return Ok(uid);
+    }
+
+    find_user(fs, user).map(|user| user.uid)
+}
+
+// Turns a group name or number into a gid
+pub fn parse_group(fs: &FileSystem, group: &str) -> Result<u32, String> {
+    if let Ok(gid) = group.parse() {
+        return Ok(gid);
+    }
+
+    groups(fs)?
+        .into_iter()
+        .find(|candidate| candidate.name == group)
+        .map(|candidate| candidate.gid)
+        .ok_or_else(|| format!("Unknown group '{}'", group))
+}
+
+// Creates the home directory of `user` and the directories above it when missing, the home
+// directory belongs to the user and the others to root
+pub fn create_home(fs: &mut FileSystem, user: &User) -> Result<(), String> {
+    let previous = fs.set_credentials(Credentials::root());
+    let result = create_directories(fs, &user.home).and_then(|created| {
+        if !created {
+            return Ok(());
+        }
+
+        let attributes = Attributes {
+            uid: Some(user.uid),
+            gid: Some(user.gid),
+            mode: Some(HOME_MODE),
+            ..Attributes::default()
+        };
+        fs.set_attributes(&user.home, &attributes)
+    });
+    fs.set_credentials(previous);
+
+    result
+}
+
+// Like `mkdir -p`, returns whether the last directory was created
+fn create_directories(fs: &mut FileSystem, path: &str) -> Result<bool, String> {
+    let mut created = false;
+    let mut current = String::from("/");
+
+    for component in resolve_path("/", path) {
+        current = normalize_path(&current, &component);
+        created = fs.lookup(&current).is_err();
+        if created {
+            fs.create_directory(&current)?;
+        }
+    }
+
+    Ok(created)
+}
@@ -0,0 +1,187 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// Entries of the user and group databases
+//
+// `/etc/passwd` has one user per line, `name:password:uid:gid:full name:home`, which is the Unix
+// format without the login shell since there is only one shell. `/etc/group` is the Unix format
+// `name:password:gid:member,member`, the group password is not used. Empty lines and lines
+// starting with `#` are skipped.
+//
+// The password field holds the SHA-256 digest of `name:password` in hex, so the same password
+// gives different digests for different users. An empty field means the user has no password.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+    password: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub full_name: String,
+    pub home: String,
+}
+
+impl User {
+    pub fn has_password(&self) -> bool {
+        !self.password.is_empty()
+    }
+
+    pub fn check_password(&self, password: &str) -> bool {
+        !self.has_password() || hash_password(&self.name, password) == self.password
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    pub gid: u32,
+    pub members: Vec<String>,
+}
+
+// Superuser of a system without a user database
+pub fn root() -> User {
+    User {
+        name: String::from("root"),
+        password: String::new(),
+        uid: 0,
+        gid: 0,
+        full_name: String::from("root"),
+        home: String::from("/root"),
+    }
+}
+
+pub fn parse_passwd(text: &str) -> Result<Vec<User>, String> {
+    entries(text)
+        .map(|(number, fields)| match fields.as_slice() {
+            [name, password, uid, gid, full_name, home] if !name.is_empty() => Ok(User {
+                name: String::from(*name),
+                password: String::from(*password),
+                uid: id(uid, number, "passwd")?,
+                gid: id(gid, number, "passwd")?,
+                full_name: String::from(*full_name),
+                home: String::from(*home),
+            }),
+            _ => Err(format!("Line {} of passwd is malformed", number)),
+        })
+        .collect()
+}
+
+pub fn parse_group(text: &str) -> Result<Vec<Group>, String> {
+    entries(text)
+        .map(|(number, fields)| match fields.as_slice() {
+            [name, _, gid, members] if !name.is_empty() => Ok(Group {
+                name: String::from(*name),
+                gid: id(gid, number, "group")?,
+                members: members
+                    .split(',')
+                    .filter(|member| !member.is_empty())
+                    .map(String::from)
+                    .collect(),
+            }),
+            _ => Err(format!("Line {} of group is malformed", number)),
+        })
+        .collect()
+}
+
+// Returns the line number and the colon separated fields of the lines that are not comments
+fn entries(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| (number, line.split(':').collect()))
+}
+
+fn id(field: &str, line: usize, file: &str) -> Result<u32, String> {
+    field
+        .parse()
+        .map_err(|_| format!("Line {} of {} has an invalid id '{}'", line, file, field))
+}
+
+// The value stored in the password field for `password`
+pub fn hash_password(name: &str, password: &str) -> String {
+    let digest = sha256(format!("{}:{}", name, password).as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Round constants, the first 32 bits of the fractional parts of the cube roots of the first 64
+// primes
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// SHA-256 as specified in FIPS 180-4
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // A one bit, zeros up to 8 bytes before a block boundary, then the length in bits
+    let mut message = Vec::from(data);
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut schedule = [0u32; 64];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7)
+                ^ schedule[i - 15].rotate_right(18)
+                ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17)
+                ^ schedule[i - 2].rotate_right(19)
+                ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (constant, word) in ROUND_CONSTANTS.iter().zip(schedule) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*constant)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
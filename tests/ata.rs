@@ -0,0 +1,136 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::{format, vec};
+use bootloader::{entry_point, BootInfo};
+use charizard::devices::{ata, block};
+use charizard::file_system::{vfs::NodeKind, FileSystem};
+use charizard::storage;
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+// `tests/disk.img` is attached as hdb, sector n starts with "CHARIZARD TEST DISK SECTOR n"
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    test_main();
+
+    loop {}
+}
+
+fn sector_tag(n: u64) -> alloc::string::String {
+    format!("CHARIZARD TEST DISK SECTOR {:03}", n)
+}
+
+#[test_case]
+fn finds_the_boot_and_test_disks() {
+    assert!(block::get("hda").is_some());
+
+    let disk = block::get("hdb").expect("test disk not found");
+    let disk = disk.lock();
+    assert_eq!(disk.sector_size(), ata::SECTOR_SIZE);
+    assert_eq!(disk.sector_count(), 128);
+    assert!(!disk.model().is_empty());
+}
+
+#[test_case]
+fn reads_sectors() {
+    let disk = block::get("hdb").unwrap();
+    let mut disk = disk.lock();
+
+    let mut buffer = vec![0; 4 * ata::SECTOR_SIZE];
+    disk.read_sectors(5, &mut buffer).unwrap();
+
+    for (index, sector) in buffer.chunks(ata::SECTOR_SIZE).enumerate() {
+        let tag = sector_tag(5 + index as u64);
+        assert_eq!(&sector[..tag.len()], tag.as_bytes());
+    }
+}
+
+#[test_case]
+fn writes_sectors() {
+    let disk = block::get("hdb").unwrap();
+    let mut disk = disk.lock();
+
+    let data = vec![0xA5; 2 * ata::SECTOR_SIZE];
+    disk.write_sectors(9, &data).unwrap();
+
+    let mut buffer = vec![0; 3 * ata::SECTOR_SIZE];
+    disk.read_sectors(9, &mut buffer).unwrap();
+    assert_eq!(&buffer[..data.len()], &data[..]);
+
+    let tag = sector_tag(11);
+    assert_eq!(&buffer[data.len()..data.len() + tag.len()], tag.as_bytes());
+}
+
+#[test_case]
+fn rejects_transfers_past_the_end() {
+    let disk = block::get("hdb").unwrap();
+    let mut disk = disk.lock();
+
+    let mut buffer = vec![0; 2 * ata::SECTOR_SIZE];
+    assert!(disk.read_sectors(127, &mut buffer).is_err());
+    assert!(disk.read_sectors(0, &mut buffer[..100]).is_err());
+}
+
+#[test_case]
+fn sync_keeps_partitioned_disks() {
+    let disk = block::get("hdb").unwrap();
+    let mut original = vec![0; ata::SECTOR_SIZE];
+    disk.lock().read_sectors(0, &mut original).unwrap();
+
+    // A single Linux partition starting at sector 2048
+    let mut mbr = vec![0; ata::SECTOR_SIZE];
+    mbr[446 + 4] = 0x83;
+    mbr[446 + 8..446 + 12].copy_from_slice(&2048u32.to_le_bytes());
+    mbr[446 + 12..446 + 16].copy_from_slice(&4096u32.to_le_bytes());
+    mbr[510..].copy_from_slice(&[0x55, 0xAA]);
+    disk.lock().write_sectors(0, &mbr).unwrap();
+
+    let result = storage::sync(&FileSystem::new());
+    disk.lock().write_sectors(0, &original).unwrap();
+    assert_eq!(
+        result,
+        Err(alloc::string::String::from(
+            "hdb holds a partition table, mount it instead of syncing to it"
+        ))
+    );
+}
+
+#[test_case]
+fn sync_survives_a_reload() {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/saved").unwrap();
+    fs.create_file("/saved/note", b"still here").unwrap();
+    storage::sync(&fs).unwrap();
+
+    let restored = FileSystem::with_root(alloc::boxed::Box::new(
+        storage::init().unwrap().expect("no image on disk"),
+    ));
+    assert_eq!(restored.lookup("/saved"), Ok(NodeKind::Directory));
+    assert_eq!(restored.read_file("/saved/note").unwrap(), b"still here");
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
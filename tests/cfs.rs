@@ -0,0 +1,336 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{parse_and_execute_command, Session};
+use charizard::devices::{
+    ata,
+    block::{self, BlockDevice, SharedBlockDevice},
+};
+use charizard::file_system::{
+    self,
+    cfs::{self, CfsFs},
+    vfs::{NodeKind, Vfs},
+    FileSystem,
+};
+use charizard::storage;
+use core::panic::PanicInfo;
+use spin::Mutex;
+
+entry_point!(main);
+
+// `tests/disk.img` is attached as hdb and formatted again by every test
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    test_main();
+
+    loop {}
+}
+
+fn disk() -> SharedBlockDevice {
+    block::get("hdb").expect("test disk not found")
+}
+
+fn formatted() -> SharedBlockDevice {
+    let disk = disk();
+    cfs::format(&disk).unwrap();
+    disk
+}
+
+fn mounted(disk: SharedBlockDevice) -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+    fs.mount("/mnt", file_system::new_backend("cfs", Some(disk)).unwrap())
+        .unwrap();
+    fs
+}
+
+// Byte i of the pattern is (i * 7 + seed) % 256
+fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(7).wrapping_add(seed))
+        .collect()
+}
+
+#[test_case]
+fn keeps_data_across_mounts() {
+    let disk = formatted();
+    {
+        let mut fs = mounted(disk.clone());
+        fs.create_directory("/mnt/docs").unwrap();
+        fs.create_file("/mnt/docs/note.txt", b"written to cfs")
+            .unwrap();
+        fs.create_file("/mnt/big.bin", &pattern(20 * 1024, 3))
+            .unwrap();
+        fs.symlink("docs/note.txt", "/mnt/link").unwrap();
+        fs.link("/mnt/docs/note.txt", "/mnt/hard").unwrap();
+        fs.unmount("/mnt").unwrap();
+    }
+
+    let fs = mounted(disk);
+    assert_eq!(
+        fs.read_file("/mnt/docs/note.txt").unwrap(),
+        b"written to cfs"
+    );
+    assert_eq!(fs.read_file("/mnt/link").unwrap(), b"written to cfs");
+    assert_eq!(fs.stat("/mnt/hard").unwrap().links, 2);
+    assert_eq!(fs.read_file("/mnt/big.bin").unwrap(), pattern(20 * 1024, 3));
+    assert_eq!(fs.lookup("/mnt/docs"), Ok(NodeKind::Directory));
+}
+
+#[test_case]
+fn refuses_what_does_not_fit() {
+    let mut fs = mounted(formatted());
+
+    let long = format!("/mnt/{}", "n".repeat(60));
+    assert!(fs.create_file(&long, b"").is_err());
+
+    // The disk has 64 KiB, most of them taken by the journal, bitmap and inodes
+    fs.create_file("/mnt/huge", b"").unwrap();
+    assert!(fs.write_at("/mnt/huge", 0, &pattern(64 * 1024, 1)).is_err());
+    // The failed write left nothing behind
+    assert_eq!(fs.size("/mnt/huge"), Ok(0));
+    fs.write_at("/mnt/huge", 0, &pattern(8 * 1024, 1)).unwrap();
+}
+
+#[test_case]
+fn is_not_overwritten_by_sync() {
+    let disk = formatted();
+    let mut fs = mounted(disk.clone());
+
+    assert_eq!(
+        storage::sync(&fs),
+        Err(String::from(
+            "hdb is mounted, unmount it before syncing to it"
+        ))
+    );
+    fs.unmount("/mnt").unwrap();
+    assert_eq!(
+        storage::sync(&fs),
+        Err(String::from(
+            "hdb holds a cfs volume, mount it instead of syncing to it"
+        ))
+    );
+    assert!(CfsFs::new(disk).is_ok());
+}
+
+#[test_case]
+fn mkfs_refuses_mounted_and_boot_disks() {
+    let mut fs = mounted(formatted());
+    fs.create_file("/mnt/kept", b"kept").unwrap();
+    let mut session = Session::new();
+
+    assert_eq!(
+        parse_and_execute_command("mkfs cfs hdb", &mut fs, &mut session),
+        Err(String::from(
+            "hdb is mounted, unmount it before formatting it"
+        ))
+    );
+    assert_eq!(
+        parse_and_execute_command("mkfs cfs hda", &mut fs, &mut session),
+        Err(String::from(
+            "hda is the boot disk, formatting it would leave nothing to boot"
+        ))
+    );
+    assert_eq!(fs.read_file("/mnt/kept").unwrap(), b"kept");
+
+    fs.unmount("/mnt").unwrap();
+    parse_and_execute_command("mkfs cfs hdb", &mut fs, &mut session).unwrap();
+}
+
+// Loses power after a number of sectors: the sectors written until then reach the disk, the
+// later ones are dropped. ATA PIO writes are done once the drive took them, so this is what the
+// volume sees when QEMU is killed at that point.
+//
+// Written sectors are kept in memory over the real disk, so the disk stays in the state the test
+// started from and `restore` goes back to it.
+struct PowerCut {
+    disk: SharedBlockDevice,
+    written: BTreeMap<u64, Vec<u8>>,
+    // Sectors that still reach the disk, `None` while the power is on
+    budget: Option<usize>,
+    // Sectors written since `restore`, dropped ones included
+    count: usize,
+}
+
+impl PowerCut {
+    fn restore(&mut self) {
+        self.written.clear();
+        self.budget = None;
+        self.count = 0;
+    }
+}
+
+impl BlockDevice for PowerCut {
+    fn sector_size(&self) -> usize {
+        self.disk.lock().sector_size()
+    }
+
+    fn sector_count(&self) -> u64 {
+        self.disk.lock().sector_count()
+    }
+
+    fn model(&self) -> &str {
+        "POWER CUT"
+    }
+
+    fn read_sectors(&mut self, lba: u64, buffer: &mut [u8]) -> Result<(), String> {
+        let sector_size = self.sector_size();
+        self.disk.lock().read_sectors(lba, buffer)?;
+        for (index, sector) in buffer.chunks_mut(sector_size).enumerate() {
+            if let Some(written) = self.written.get(&(lba + index as u64)) {
+                sector.copy_from_slice(written);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), String> {
+        let sector_size = self.sector_size();
+        for (index, sector) in data.chunks(sector_size).enumerate() {
+            self.count += 1;
+            match &mut self.budget {
+                Some(0) => continue,
+                Some(budget) => *budget -= 1,
+                None => {}
+            }
+            self.written.insert(lba + index as u64, sector.to_vec());
+        }
+        Ok(())
+    }
+}
+
+// Every node below the root with its kind, size and a checksum of its content
+fn snapshot(fs: &CfsFs) -> Vec<String> {
+    let mut nodes = Vec::new();
+    let mut pending = vec![String::new()];
+
+    while let Some(directory) = pending.pop() {
+        let path = if directory.is_empty() {
+            "/"
+        } else {
+            &directory
+        };
+        for entry in fs.readdir(path).unwrap() {
+            let child = format!("{}/{}", directory, entry.name);
+            let size = fs.stat(&child).unwrap().size;
+            let content = match entry.kind {
+                NodeKind::Directory => {
+                    pending.push(child.clone());
+                    0
+                }
+                NodeKind::Symlink => {
+                    file_system::image::crc32(fs.readlink(&child).unwrap().as_bytes())
+                }
+                _ => {
+                    let mut buffer = vec![0; size];
+                    fs.read(&child, 0, &mut buffer).unwrap();
+                    file_system::image::crc32(&buffer)
+                }
+            };
+            nodes.push(format!(
+                "{} {:?} {} {:08x}",
+                child, entry.kind, size, content
+            ));
+        }
+    }
+
+    nodes.sort();
+    nodes
+}
+
+type Operation = fn(&mut CfsFs) -> Result<(), String>;
+
+#[test_case]
+fn recovers_from_a_crash_at_any_point() {
+    let power = Arc::new(Mutex::new(PowerCut {
+        disk: formatted(),
+        written: BTreeMap::new(),
+        budget: None,
+        count: 0,
+    }));
+    let device: SharedBlockDevice = power.clone();
+
+    // The state every operation starts from is written to the real disk
+    {
+        let mut fs = CfsFs::new(disk()).unwrap();
+        fs.create("/docs", NodeKind::Directory).unwrap();
+        fs.create("/docs/a.txt", NodeKind::File).unwrap();
+        fs.write("/docs/a.txt", 0, &pattern(3000, 1)).unwrap();
+        fs.create("/b.txt", NodeKind::File).unwrap();
+        fs.write("/b.txt", 0, b"bee").unwrap();
+        fs.symlink("docs/a.txt", "/link").unwrap();
+    }
+
+    let operations: [(&str, Operation); 5] = [
+        ("overwrite", |fs| {
+            fs.write("/docs/a.txt", 500, &pattern(4000, 9)).map(|_| ())
+        }),
+        ("rename", |fs| fs.rename("/b.txt", "/docs/c.txt")),
+        ("unlink", |fs| fs.unlink("/docs/a.txt")),
+        ("create", |fs| fs.create("/docs/new", NodeKind::Directory)),
+        ("truncate", |fs| fs.truncate("/docs/a.txt", 100)),
+    ];
+
+    for (name, operation) in operations {
+        power.lock().restore();
+        let before = snapshot(&CfsFs::new(device.clone()).unwrap());
+        operation(&mut CfsFs::new(device.clone()).unwrap()).unwrap();
+        let after = snapshot(&CfsFs::new(device.clone()).unwrap());
+        assert_ne!(before, after, "{} changed nothing", name);
+
+        power.lock().restore();
+        let mut fs = CfsFs::new(device.clone()).unwrap();
+        operation(&mut fs).unwrap();
+        let total = power.lock().count;
+
+        for cut in 0..total {
+            power.lock().restore();
+            let mut fs = CfsFs::new(device.clone()).unwrap();
+            power.lock().budget = Some(cut);
+            let _ = operation(&mut fs);
+            power.lock().budget = None;
+
+            let recovered = snapshot(&CfsFs::new(device.clone()).unwrap());
+            assert!(
+                recovered == before || recovered == after,
+                "{} cut after {} of {} sectors",
+                name,
+                cut,
+                total
+            );
+        }
+    }
+
+    power.lock().restore();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,126 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::devices::{
+    ata,
+    block::{self, SharedBlockDevice},
+};
+use charizard::file_system::{self, cfs, Conflict, FileSystem};
+use charizard::{exit_qemu, serial_println, QemuExitCode};
+use core::panic::PanicInfo;
+
+// Booted twice on the same image by `host-tests/tests/qemu_crash.rs`, which kills QEMU in
+// between. What a boot does depends on hdb:
+// - starting with `MARKER`, it is formatted, `COMMITTED` is written, then transactions run until
+//   QEMU is killed, each one reported on the serial port once it is committed
+// - holding a cfs volume, it is mounted again and checked, and the last generation found is
+//   reported so that the harness can compare it with the last one committed
+// - anything else, like `tests/disk.img` under a plain `cargo test`, means the harness is not
+//   driving the test and there is nothing to do
+entry_point!(main);
+
+const MARKER: &[u8] = b"CFS CRASH TEST";
+const COMMITTED: &[u8] = b"committed before the crash";
+// Large enough for a write to take several sectors, two of them fit on the 64 KiB disk
+const SIZE: usize = 4 * 1024;
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    let disk = block::get("hdb").expect("test disk not found");
+    let mut start = [0; 512];
+    block::read_bytes(&disk, 0, &mut start).unwrap();
+
+    if start.starts_with(MARKER) {
+        run_until_killed(disk);
+    } else if cfs::is_volume(&start) {
+        check_recovered(disk);
+    } else {
+        serial_println!("cfs_crash: hdb was not prepared by the crash harness, skipped");
+    }
+
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
+
+fn mounted(disk: SharedBlockDevice) -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+    fs.mount("/mnt", file_system::new_backend("cfs", Some(disk)).unwrap())
+        .unwrap();
+    fs
+}
+
+// Content of generation `generation`: its number on the first line, padded with dots to `SIZE`
+fn generation(generation: usize) -> Vec<u8> {
+    let mut content = format!("{}\n", generation).into_bytes();
+    content.resize(SIZE, b'.');
+    content
+}
+
+// Parses what `generation` wrote, `None` when the content is anything else
+fn parse_generation(content: &[u8]) -> Option<usize> {
+    let end = content.iter().position(|&byte| byte == b'\n')?;
+    let number = core::str::from_utf8(&content[..end]).ok()?.parse().ok()?;
+    (content == generation(number)).then_some(number)
+}
+
+// Each generation is written to `/mnt/next` and renamed over `/mnt/current`, so a crash can land
+// in the middle of content writes as well as of metadata changes
+fn run_until_killed(disk: SharedBlockDevice) -> ! {
+    cfs::format(&disk).unwrap();
+    let mut fs = mounted(disk);
+    fs.create_file("/mnt/committed", COMMITTED).unwrap();
+    serial_println!("committed");
+
+    for number in 0.. {
+        fs.create_file("/mnt/next", &generation(number)).unwrap();
+        fs.move_node("/mnt/next", "/mnt/current", Conflict::Overwrite)
+            .unwrap();
+        serial_println!("transaction {}", number);
+    }
+    unreachable!();
+}
+
+fn check_recovered(disk: SharedBlockDevice) {
+    let fs = mounted(disk);
+
+    assert_eq!(fs.fsck("/mnt").unwrap(), Vec::<String>::new());
+    assert_eq!(fs.read_file("/mnt/committed").unwrap(), COMMITTED);
+
+    let current = fs.read_file("/mnt/current").unwrap();
+    let number = parse_generation(&current).expect("/mnt/current holds a partial generation");
+    // The next generation is either not created yet, still empty or complete
+    if let Ok(next) = fs.read_file("/mnt/next") {
+        assert!(
+            next.is_empty() || parse_generation(&next) == Some(number + 1),
+            "/mnt/next holds a partial generation"
+        );
+    }
+
+    serial_println!("recovered {}", number);
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,237 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{
+    self, parse_and_execute_command,
+    registry::{self, Builtin, Command, Context},
+    Session,
+};
+use charizard::file_system::FileSystem;
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    command::init();
+
+    test_main();
+
+    loop {}
+}
+
+fn run(command: &str, fs: &mut FileSystem, session: &mut Session) -> Result<String, String> {
+    parse_and_execute_command(command, fs, session)
+}
+
+// Counts the lines of its standard input, the way a module outside the shell adds a command
+struct Lines;
+
+impl Command for Lines {
+    fn name(&self) -> &str {
+        "lines"
+    }
+
+    fn usage(&self) -> &str {
+        ""
+    }
+
+    fn summary(&self) -> &str {
+        "Count the lines of the standard input"
+    }
+
+    fn execute(&self, arguments: &[&str], context: Context) -> Result<String, String> {
+        if arguments.len() > 1 || context.streams.stdin.is_terminal() {
+            return Err(registry::usage_error(arguments[0]));
+        }
+        let input = context.streams.stdin.read_to_end(context.fs)?;
+        let count = input.iter().filter(|&&byte| byte == b'\n').count();
+        Ok(format!("{}", count))
+    }
+}
+
+#[test_case]
+fn help_lists_every_command() {
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+
+    let output = run("help", &mut fs, &mut session).unwrap();
+    let names: Vec<&str> = output
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect();
+
+    for name in ["cat", "cd", "fsck", "help", "ls", "mount", "su", "watch"] {
+        assert!(names.contains(&name), "{}", name);
+    }
+    assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(names.len(), registry::commands().len());
+}
+
+#[test_case]
+fn help_shows_how_to_use_a_command() {
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+
+    assert_eq!(
+        run("help cp", &mut fs, &mut session),
+        Ok(String::from(
+            "Usage: cp [-r] [-f|-n] <source>... <destination>\n\
+             Copy nodes to another path or into a directory"
+        ))
+    );
+    // No trailing space for commands without arguments
+    assert!(run("help pwd", &mut fs, &mut session)
+        .unwrap()
+        .starts_with("Usage: pwd\n"));
+    assert_eq!(
+        run("help nosuchcommand", &mut fs, &mut session),
+        Err(String::from("Unknown command: nosuchcommand"))
+    );
+    assert_eq!(
+        run("help ls cd", &mut fs, &mut session),
+        Err(String::from("Usage: help [command]"))
+    );
+}
+
+#[test_case]
+fn usage_errors_come_from_the_registry() {
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+
+    assert_eq!(
+        run("mkdir", &mut fs, &mut session),
+        Err(String::from("Usage: mkdir <path>"))
+    );
+    assert_eq!(
+        run("rm -r", &mut fs, &mut session),
+        Err(String::from("Usage: rm [-r] <path>..."))
+    );
+    assert_eq!(
+        run("mv -r a b", &mut fs, &mut session),
+        Err(String::from("Usage: mv [-f|-n] <source>... <destination>"))
+    );
+    assert_eq!(
+        run("nosuchcommand", &mut fs, &mut session),
+        Err(String::from("Unknown command: nosuchcommand"))
+    );
+}
+
+#[test_case]
+fn registered_commands_run_like_built_in_ones() {
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+    fs.create_file("/log.txt", b"one\ntwo\nthree\n").unwrap();
+
+    registry::register(Arc::new(Lines)).unwrap();
+
+    assert_eq!(
+        run("cat /log.txt | lines", &mut fs, &mut session),
+        Ok(String::from("3"))
+    );
+    assert_eq!(
+        run("lines < /log.txt", &mut fs, &mut session),
+        Ok(String::from("3"))
+    );
+    assert_eq!(
+        run("lines", &mut fs, &mut session),
+        Err(String::from("Usage: lines"))
+    );
+    assert!(run("help", &mut fs, &mut session)
+        .unwrap()
+        .lines()
+        .any(|line| line.starts_with("lines ")
+            && line.ends_with("Count the lines of the standard input")));
+}
+
+#[test_case]
+fn init_runs_once() {
+    let count = registry::commands().len();
+
+    assert!(command::init().is_empty());
+    assert!(command::init().is_empty());
+    assert_eq!(registry::commands().len(), count);
+}
+
+#[test_case]
+fn names_must_be_unique_and_typeable() {
+    let ls = Builtin {
+        name: "ls",
+        usage: "",
+        summary: "Another ls",
+        run: |_, _| Ok(String::new()),
+    };
+    assert!(registry::register(Arc::new(ls)).is_err());
+
+    for name in ["", "two words"] {
+        let command = Builtin { name, ..ls };
+        assert!(registry::register(Arc::new(command)).is_err(), "{:?}", name);
+    }
+
+    // The original is still the one that runs
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+    assert!(run("ls /", &mut fs, &mut session)
+        .unwrap()
+        .starts_with("Contents of '/'"));
+}
+
+#[test_case]
+fn commands_can_write_while_they_run() {
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+
+    let count = Builtin {
+        name: "count",
+        usage: "",
+        summary: "Print the numbers from 1 to 3",
+        run: |_, context| {
+            for number in 1..=3 {
+                let line = format!("{}\n", number);
+                context.streams.stdout.write(context.fs, line.as_bytes())?;
+            }
+            Ok(String::from("done"))
+        },
+    };
+    registry::register(Arc::new(count)).unwrap();
+
+    // What it wrote is already on the screen, only what it returns is handed back
+    assert_eq!(
+        run("count", &mut fs, &mut session),
+        Ok(String::from("done"))
+    );
+    assert_eq!(
+        run("count | grep 2", &mut fs, &mut session),
+        Ok(String::from("2\n"))
+    );
+    run("count > /out.txt", &mut fs, &mut session).unwrap();
+    assert_eq!(fs.read_file("/out.txt").unwrap(), b"1\n2\n3\ndone");
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,133 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{parse_and_execute_command, Session};
+use charizard::devices::ata;
+use charizard::file_system::{descriptor::OpenMode, devfs::DevFs, vfs::NodeKind, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+// `tests/disk.img` is attached as hdb, sector n starts with "CHARIZARD TEST DISK SECTOR n"
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    test_main();
+
+    loop {}
+}
+
+fn mounted() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/dev").unwrap();
+    fs.mount("/dev", Box::new(DevFs::new())).unwrap();
+    fs
+}
+
+#[test_case]
+fn lists_the_devices() {
+    let fs = mounted();
+    let names: Vec<String> = fs
+        .read_dir("/dev")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+
+    assert_eq!(
+        names,
+        ["console", "null", "random", "ttyS0", "zero", "hda", "hdb", "hdc", "hdd"]
+    );
+    assert_eq!(fs.lookup("/dev/ttyS0"), Ok(NodeKind::CharDevice));
+    assert_eq!(fs.lookup("/dev/hdb"), Ok(NodeKind::BlockDevice));
+    assert_eq!(fs.stat("/dev/hdb").unwrap().size, 64 * 1024);
+    assert_eq!(fs.stat("/dev/null").unwrap().permissions(), "crw-rw-rw-");
+}
+
+#[test_case]
+fn null_and_zero() {
+    let mut fs = mounted();
+
+    assert_eq!(fs.write_at("/dev/null", 0, b"gone"), Ok(4));
+    assert_eq!(fs.read_at("/dev/null", 0, 16).unwrap(), b"");
+    assert_eq!(fs.read_at("/dev/zero", 0, 16).unwrap(), [0; 16]);
+}
+
+#[test_case]
+fn random_changes() {
+    let fs = mounted();
+
+    let first = fs.read_at("/dev/random", 0, 32).unwrap();
+    let second = fs.read_at("/dev/random", 0, 32).unwrap();
+    assert_eq!(first.len(), 32);
+    assert_ne!(first, second);
+}
+
+#[test_case]
+fn disks_read_and_write_their_sectors() {
+    let mut fs = mounted();
+
+    let tag = fs.read_at("/dev/hdb", 3 * 512, 30).unwrap();
+    assert_eq!(tag, b"CHARIZARD TEST DISK SECTOR 003");
+
+    // Straddles two sectors
+    fs.write_at("/dev/hdb", 100 * 512 - 2, b"edge").unwrap();
+    assert_eq!(fs.read_at("/dev/hdb", 100 * 512 - 2, 4).unwrap(), b"edge");
+
+    // Reads stop at the end of the disk and writes past it fail
+    assert_eq!(fs.read_at("/dev/hdb", 64 * 1024 - 3, 16).unwrap().len(), 3);
+    assert!(fs.write_at("/dev/hdb", 64 * 1024 - 3, b"long").is_err());
+}
+
+#[test_case]
+fn redirects_into_the_serial_port() {
+    let mut fs = mounted();
+    let mut session = Session::new();
+
+    // Shows up in the test log on the host
+    fs.create_file("/message", b"(through /dev/ttyS0) ")
+        .unwrap();
+    let result = parse_and_execute_command("cat /message > /dev/ttyS0", &mut fs, &mut session);
+    assert!(result.is_ok());
+
+    let handle = fs.open("/dev/ttyS0", OpenMode::Append).unwrap();
+    assert_eq!(fs.write(handle, b"(appended) "), Ok(11));
+    fs.close(handle).unwrap();
+}
+
+#[test_case]
+fn devices_are_fixed() {
+    let mut fs = mounted();
+
+    assert!(fs.create_file("/dev/new", b"").is_err());
+    assert!(fs.delete_node("/dev/null").is_err());
+    assert!(fs.rename_node("/dev/zero", "other").is_err());
+    assert!(fs.read_dir("/dev/null").is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
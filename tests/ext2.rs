@@ -0,0 +1,209 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::devices::{ata, block};
+use charizard::file_system::{self, vfs::NodeKind, Conflict, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+// `tests/partitioned.img` is attached as hdd, its second partition is an ext2 volume with 1 KiB
+// blocks and four block groups made by `mke2fs -d` from:
+//
+//   hello.txt                "Hello from ext2\n"
+//   big.bin                  300000 bytes, byte i is (i * 13 + i / 1024) % 253, reaching into the
+//                            double indirect block
+//   sparse.bin               200000 bytes of hole followed by "end of a sparse file\n"
+//   docs/readme.md
+//   docs/deeper/bottom.txt   "three levels down\n"
+//   many/                    "file with a long name number 00.txt" .. 99, five directory blocks
+//   run.sh                   mode 0755
+//   private.txt              mode 0600
+//   owned.txt                owned by 1000:1000, its inode is in the second block group
+//   link                     fast symbolic link to hello.txt
+//   longlink                 96 byte symbolic link stored in a block, to docs/deeper/bottom.txt
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    test_main();
+
+    loop {}
+}
+
+// Bytes read at once from files too large for the heap
+const CHUNK_SIZE: usize = 16 * 1024;
+
+// Mounts the ext2 partition at `/mnt` of a fresh tree
+fn mounted() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+
+    let disk = block::get("hdd").expect("ext2 test disk not found");
+    fs.mount(
+        "/mnt",
+        file_system::new_backend("ext2", Some(disk)).unwrap(),
+    )
+    .unwrap();
+
+    fs
+}
+
+#[test_case]
+fn lists_directories() {
+    let fs = mounted();
+    let mut names: Vec<String> = fs
+        .read_dir("/mnt")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    names.sort();
+
+    assert_eq!(
+        names,
+        [
+            "big.bin",
+            "docs",
+            "hello.txt",
+            "link",
+            "longlink",
+            "lost+found",
+            "many",
+            "owned.txt",
+            "private.txt",
+            "run.sh",
+            "sparse.bin"
+        ]
+    );
+    assert_eq!(fs.lookup("/mnt/docs/deeper"), Ok(NodeKind::Directory));
+    assert_eq!(fs.lstat("/mnt/link").unwrap().kind, NodeKind::Symlink);
+
+    let many = fs.read_dir("/mnt/many").unwrap();
+    assert_eq!(many.len(), 100);
+    assert!(many
+        .iter()
+        .any(|entry| entry.name == "file with a long name number 99.txt"));
+}
+
+#[test_case]
+fn reads_files() {
+    let fs = mounted();
+
+    assert_eq!(
+        fs.read_file("/mnt/hello.txt").unwrap(),
+        b"Hello from ext2\n"
+    );
+    assert_eq!(
+        fs.read_file("/mnt/docs/deeper/bottom.txt").unwrap(),
+        b"three levels down\n"
+    );
+    assert_eq!(
+        fs.read_file("/mnt/many/file with a long name number 42.txt")
+            .unwrap(),
+        b"entry 42\n"
+    );
+
+    // Both files are larger than the heap, so they are checked a piece at a time
+    assert_eq!(fs.size("/mnt/big.bin").unwrap(), 300_000);
+    for offset in (0..300_000).step_by(CHUNK_SIZE) {
+        let chunk = fs.read_at("/mnt/big.bin", offset, CHUNK_SIZE).unwrap();
+        assert_eq!(chunk.len(), CHUNK_SIZE.min(300_000 - offset));
+        assert!(chunk.iter().enumerate().all(|(i, &byte)| {
+            let i = offset + i;
+            usize::from(byte) == (i * 13 + i / 1024) % 253
+        }));
+    }
+
+    assert_eq!(fs.size("/mnt/sparse.bin").unwrap(), 200_021);
+    for offset in (0..200_000).step_by(CHUNK_SIZE) {
+        let chunk = fs.read_at("/mnt/sparse.bin", offset, CHUNK_SIZE).unwrap();
+        assert!(chunk[..CHUNK_SIZE.min(200_000 - offset)]
+            .iter()
+            .all(|&byte| byte == 0));
+    }
+    assert_eq!(
+        fs.read_at("/mnt/sparse.bin", 200_000, CHUNK_SIZE).unwrap(),
+        b"end of a sparse file\n"
+    );
+}
+
+#[test_case]
+fn follows_symbolic_links() {
+    let fs = mounted();
+
+    assert_eq!(fs.readlink("/mnt/link").unwrap(), "hello.txt");
+    assert_eq!(fs.read_file("/mnt/link").unwrap(), b"Hello from ext2\n");
+    assert_eq!(fs.readlink("/mnt/longlink").unwrap().len(), 96);
+    assert_eq!(
+        fs.read_file("/mnt/longlink").unwrap(),
+        b"three levels down\n"
+    );
+}
+
+#[test_case]
+fn reports_owners_and_modes() {
+    let fs = mounted();
+
+    assert_eq!(fs.stat("/mnt/run.sh").unwrap().mode, 0o755);
+    assert_eq!(fs.stat("/mnt/private.txt").unwrap().mode, 0o600);
+
+    let owned = fs.stat("/mnt/owned.txt").unwrap();
+    assert_eq!((owned.uid, owned.gid), (1000, 1000));
+    assert_eq!(owned.size, 6);
+    assert_eq!(owned.inode, 121);
+}
+
+#[test_case]
+fn refuses_changes() {
+    let mut fs = mounted();
+
+    assert!(fs.create_file("/mnt/new.txt", b"data").is_err());
+    assert!(fs.create_directory("/mnt/new").is_err());
+    assert!(fs.write_at("/mnt/hello.txt", 0, b"Bye").is_err());
+    assert!(fs.truncate("/mnt/hello.txt", 0).is_err());
+    assert!(fs.delete_node("/mnt/hello.txt").is_err());
+    assert!(fs
+        .move_node("/mnt/hello.txt", "/mnt/moved.txt", Conflict::Fail)
+        .is_err());
+    assert_eq!(
+        fs.read_file("/mnt/hello.txt").unwrap(),
+        b"Hello from ext2\n"
+    );
+}
+
+#[test_case]
+fn needs_an_ext2_volume() {
+    assert!(file_system::new_backend("ext2", None).is_err());
+
+    // Neither the ATA test disk nor the FAT16 image holds an ext2 filesystem
+    for device in ["hdb", "hdc"] {
+        let disk = block::get(device).unwrap();
+        assert!(file_system::new_backend("ext2", Some(disk)).is_err());
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
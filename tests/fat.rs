@@ -0,0 +1,195 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::devices::{ata, block};
+use charizard::file_system::{self, vfs::NodeKind, Conflict, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+// `tests/fat16.img` is attached as hdc and `tests/partitioned.img`, whose first partition is
+// FAT32, as hdd. Both volumes hold:
+//
+//   HELLO.TXT                "Hello from FAT16\n" or "Hello from FAT32\n"
+//   readme.md                short name flagged as lowercase
+//   A long file name.txt     "Long file names work\n"
+//   EMPTY                    no clusters at all
+//   DOCS/NOTES.TXT           3000 bytes, byte i is (i * 7 + i / 256) % 251, fragmented
+//   DOCS/Nested Directory With A Very Long Name/deep.txt
+//   DOCS/file number 00.dat .. file number 19.dat, spilling DOCS into a second cluster
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    test_main();
+
+    loop {}
+}
+
+const DISKS: [(&str, &str); 2] = [("hdc", "fat16"), ("hdd", "fat32")];
+
+// Mounts the FAT image on `device` at `/mnt` of a fresh tree
+fn mounted(device: &str) -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/mnt").unwrap();
+
+    let disk = block::get(device).expect("FAT test disk not found");
+    fs.mount("/mnt", file_system::new_backend("fat", Some(disk)).unwrap())
+        .unwrap();
+
+    fs
+}
+
+#[test_case]
+fn detects_the_fat_type() {
+    for (device, name) in DISKS {
+        let fs = mounted(device);
+        assert_eq!(fs.mounts().last().unwrap().backend.name(), name);
+    }
+}
+
+#[test_case]
+fn lists_long_and_short_names() {
+    for (device, _) in DISKS {
+        let fs = mounted(device);
+        let names: Vec<String> = fs
+            .read_dir("/mnt")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                "HELLO.TXT",
+                "readme.md",
+                "A long file name.txt",
+                "EMPTY",
+                "DOCS"
+            ]
+        );
+        assert_eq!(fs.lookup("/mnt/DOCS"), Ok(NodeKind::Directory));
+        assert_eq!(fs.read_dir("/mnt/DOCS").unwrap().len(), 22);
+    }
+}
+
+#[test_case]
+fn reads_files() {
+    for (device, name) in DISKS {
+        let fs = mounted(device);
+        let hello = alloc::format!("Hello from {}\n", name.to_uppercase());
+
+        // Names match ignoring case, and the short name of a long name works too
+        assert_eq!(fs.read_file("/mnt/hello.txt").unwrap(), hello.as_bytes());
+        assert_eq!(
+            fs.read_file("/mnt/A long file name.txt").unwrap(),
+            b"Long file names work\n"
+        );
+        assert_eq!(
+            fs.read_file("/mnt/ALONGF~1.TXT").unwrap(),
+            b"Long file names work\n"
+        );
+        assert_eq!(fs.read_file("/mnt/EMPTY").unwrap(), b"");
+        assert_eq!(
+            fs.read_file("/mnt/docs/nested directory with a very long name/deep.txt")
+                .unwrap(),
+            b"deep inside\n"
+        );
+
+        let notes = fs.read_file("/mnt/DOCS/NOTES.TXT").unwrap();
+        assert_eq!(notes.len(), 3000);
+        assert!(notes
+            .iter()
+            .enumerate()
+            .all(|(i, &byte)| usize::from(byte) == (i * 7 + i / 256) % 251));
+    }
+}
+
+#[test_case]
+fn writes_survive_a_remount() {
+    for (device, _) in DISKS {
+        let mut fs = mounted(device);
+        let data: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+
+        fs.create_directory("/mnt/New Folder").unwrap();
+        fs.create_file("/mnt/New Folder/a rather long name.bin", &data)
+            .unwrap();
+        fs.append("/mnt/New Folder/a rather long name.bin", b"tail")
+            .unwrap();
+        fs.truncate("/mnt/HELLO.TXT", 5).unwrap();
+
+        let fs = mounted(device);
+        let content = fs
+            .read_file("/mnt/new folder/A RATHER LONG NAME.BIN")
+            .unwrap();
+        assert_eq!(&content[..5000], &data[..]);
+        assert_eq!(&content[5000..], b"tail");
+        assert_eq!(fs.read_file("/mnt/HELLO.TXT").unwrap(), b"Hello");
+    }
+}
+
+#[test_case]
+fn deletes_and_moves_nodes() {
+    for (device, _) in DISKS {
+        let mut fs = mounted(device);
+
+        fs.delete_node("/mnt/DOCS/file number 00.dat").unwrap();
+        assert!(fs.delete_node("/mnt/DOCS").is_err());
+
+        fs.create_directory("/mnt/moving").unwrap();
+        fs.create_file("/mnt/moving/inside", b"moved along")
+            .unwrap();
+        fs.move_node("/mnt/moving", "/mnt/DOCS/moved", Conflict::Fail)
+            .unwrap();
+        fs.move_node("/mnt/readme.md", "/mnt/README.MD", Conflict::Fail)
+            .unwrap();
+
+        let fs = mounted(device);
+        assert!(fs.lookup("/mnt/DOCS/file number 00.dat").is_err());
+        assert!(fs.lookup("/mnt/moving").is_err());
+        assert_eq!(
+            fs.read_file("/mnt/DOCS/moved/inside").unwrap(),
+            b"moved along"
+        );
+        assert!(fs
+            .read_dir("/mnt")
+            .unwrap()
+            .iter()
+            .any(|entry| entry.name == "README.MD"));
+    }
+}
+
+#[test_case]
+fn needs_a_device() {
+    assert!(file_system::new_backend("fat", None).is_err());
+
+    // The ATA test disk does not hold a FAT filesystem
+    let disk = block::get("hdb").unwrap();
+    assert!(file_system::new_backend("fat", Some(disk)).is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
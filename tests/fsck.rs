@@ -0,0 +1,97 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{parse_and_execute_command, Session};
+use charizard::devices::{ata, block};
+use charizard::file_system::{self, access::Credentials, cfs, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+    ata::init();
+
+    test_main();
+
+    loop {}
+}
+
+// Mounts `fs_type` at `path`, on the disk `device` when given
+fn mount(fs: &mut FileSystem, fs_type: &str, path: &str, device: Option<&str>) {
+    let device = device.map(|name| block::get(name).expect("test disk not found"));
+
+    fs.create_directory(path).unwrap();
+    fs.mount(path, file_system::new_backend(fs_type, device).unwrap())
+        .unwrap();
+}
+
+// The filesystems that cannot run on the host, and the disk images, which are made by other
+// tools than the kernel itself
+#[test_case]
+fn every_backend_is_consistent() {
+    let mut fs = FileSystem::new();
+    cfs::format(&block::get("hdb").unwrap()).unwrap();
+
+    mount(&mut fs, "proc", "/proc", None);
+    mount(&mut fs, "dev", "/dev", None);
+    mount(&mut fs, "cfs", "/cfs", Some("hdb"));
+    mount(&mut fs, "fat", "/fat16", Some("hdc"));
+    mount(&mut fs, "fat", "/fat32", Some("hdd"));
+    mount(&mut fs, "ext2", "/ext2", Some("hdd"));
+
+    fs.create_directory("/cfs/docs").unwrap();
+    fs.create_file("/cfs/docs/note.txt", b"note").unwrap();
+    fs.link("/cfs/docs/note.txt", "/cfs/again.txt").unwrap();
+    fs.symlink("docs/note.txt", "/cfs/link").unwrap();
+
+    let mount_points: Vec<String> = fs.mounts().map(|mount| mount.path.clone()).collect();
+    for path in mount_points {
+        assert_eq!(fs.fsck(&path), Ok(Vec::new()), "{}", path);
+    }
+}
+
+#[test_case]
+fn the_shell_summarizes_the_check() {
+    let mut fs = FileSystem::new();
+    let mut session = Session::new();
+    fs.create_directory("/docs").unwrap();
+
+    assert_eq!(
+        parse_and_execute_command("fsck", &mut fs, &mut session),
+        Ok(String::from("No problems found"))
+    );
+    assert!(parse_and_execute_command("fsck /missing", &mut fs, &mut session).is_err());
+
+    fs.set_credentials(Credentials {
+        uid: 1000,
+        gid: 1000,
+        groups: Vec::new(),
+    });
+    assert!(parse_and_execute_command("fsck /docs", &mut fs, &mut session).is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,124 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use bootloader::{entry_point, BootInfo};
+use charizard::file_system::{tar, vfs::NodeKind, FileSystem};
+use charizard::initrd;
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+// Made with `tar --format=pax --owner=1000 --group=100` from a tree holding:
+//
+//   docs/readme.txt          "read me\n"
+//   docs/nested/             mode 0750
+//   docs/nested/secret.txt   mode 0600
+//   hardlink.txt             hard link to docs/readme.txt
+//   run.sh                   mode 0755
+//   shortcut                 symbolic link to docs/readme.txt
+//   a directory name ... stop at 100/file.txt
+//
+// with every modification time set to 2024-05-17 12:34:56 UTC. The long directory name only fits
+// in a pax header.
+static ARCHIVE: &[u8] = include_bytes!("archive.tar");
+const LONG_NAME: &str = "/a directory name that is long enough to need the pax path record in \
+                         the archive since ustar fields stop at 100";
+const MODIFIED: u64 = 1_715_949_296;
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+fn unpacked() -> FileSystem {
+    FileSystem::with_root(Box::new(tar::unpack(ARCHIVE).unwrap()))
+}
+
+#[test_case]
+fn unpacks_the_tree() {
+    let fs = unpacked();
+
+    assert_eq!(fs.read_file("/docs/readme.txt").unwrap(), b"read me\n");
+    assert_eq!(
+        fs.read_file("/docs/nested/secret.txt").unwrap(),
+        b"hidden\n"
+    );
+    assert_eq!(
+        fs.read_file(&alloc::format!("{}/file.txt", LONG_NAME))
+            .unwrap(),
+        b"long path\n"
+    );
+    assert_eq!(fs.lookup(LONG_NAME), Ok(NodeKind::Directory));
+}
+
+#[test_case]
+fn keeps_modes_owners_and_times() {
+    let fs = unpacked();
+
+    assert_eq!(fs.stat("/run.sh").unwrap().mode, 0o755);
+    assert_eq!(fs.stat("/docs/nested").unwrap().mode, 0o750);
+
+    let secret = fs.stat("/docs/nested/secret.txt").unwrap();
+    assert_eq!(secret.mode, 0o600);
+    assert_eq!((secret.uid, secret.gid), (1000, 100));
+    assert_eq!(secret.modified, MODIFIED);
+    assert_eq!(fs.stat("/docs").unwrap().modified, MODIFIED);
+}
+
+#[test_case]
+fn restores_links() {
+    let fs = unpacked();
+
+    let original = fs.stat("/docs/readme.txt").unwrap();
+    assert_eq!(original.links, 2);
+    assert_eq!(fs.stat("/hardlink.txt").unwrap().inode, original.inode);
+
+    assert_eq!(fs.readlink("/shortcut").unwrap(), "docs/readme.txt");
+    assert_eq!(fs.read_file("/shortcut").unwrap(), b"read me\n");
+}
+
+#[test_case]
+fn rejects_damaged_archives() {
+    assert!(tar::unpack(&ARCHIVE[..1000]).is_err());
+
+    let mut damaged = alloc::vec::Vec::from(ARCHIVE);
+    damaged[0] ^= 0xFF;
+    assert!(tar::unpack(&damaged).is_err());
+
+    assert!(tar::unpack(&[0x42; 1024]).is_err());
+}
+
+#[test_case]
+fn embedded_initrd_unpacks() {
+    let fs = FileSystem::with_root(Box::new(initrd::load().unwrap()));
+
+    assert_eq!(fs.read_file("/etc/hostname").unwrap(), b"charizard\n");
+    assert_eq!(fs.stat("/scripts/hello.sh").unwrap().mode, 0o755);
+    assert_eq!(fs.stat("/etc/hostname").unwrap().uid, 0);
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,202 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{parse_and_execute_command, Session};
+use charizard::file_system::{ramfs::RamFs, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+const LOG: &[u8] = b"ok: boot\nERROR: disk full\nok: network\nerror: no route\n";
+
+// A tree with `/foo.txt`, `/football`, `/bar` and `/log.txt`
+fn populated() -> (FileSystem, Session) {
+    let mut fs = FileSystem::new();
+    fs.create_file("/foo.txt", b"").unwrap();
+    fs.create_directory("/football").unwrap();
+    fs.create_file("/bar", b"").unwrap();
+    fs.create_file("/log.txt", LOG).unwrap();
+
+    (fs, Session::new())
+}
+
+fn run(command: &str, fs: &mut FileSystem, session: &mut Session) -> Result<String, String> {
+    parse_and_execute_command(command, fs, session)
+}
+
+#[test_case]
+fn pipes_feed_the_next_command() {
+    let (mut fs, mut session) = populated();
+
+    assert_eq!(
+        run("ls / | grep foo", &mut fs, &mut session),
+        Ok(String::from("foo.txt\nfootball\n"))
+    );
+    assert_eq!(
+        run(
+            "cat /log.txt | grep -i error | grep -v disk",
+            &mut fs,
+            &mut session
+        ),
+        Ok(String::from("error: no route\n"))
+    );
+    // On the screen `ls` keeps its usual format
+    assert!(run("ls /", &mut fs, &mut session)
+        .unwrap()
+        .starts_with("Contents of '/'"));
+}
+
+#[test_case]
+fn pipelines_write_to_files() {
+    let (mut fs, mut session) = populated();
+
+    assert_eq!(
+        run("ls / | grep foo > out.txt", &mut fs, &mut session),
+        Ok(String::new())
+    );
+    assert_eq!(fs.read_file("/out.txt").unwrap(), b"foo.txt\nfootball\n");
+
+    run("echo bar | cat >> out.txt", &mut fs, &mut session).unwrap();
+    assert_eq!(
+        fs.read_file("/out.txt").unwrap(),
+        b"foo.txt\nfootball\nbar\n"
+    );
+}
+
+#[test_case]
+fn binary_files_are_copied_unchanged() {
+    let (mut fs, mut session) = populated();
+    // Not UTF-8, with the bytes a serial console would turn into erase sequences, and longer
+    // than what `cat` reads at once
+    let data: Vec<u8> = (0..3000).map(|index| (index % 256) as u8).collect();
+    fs.create_file("/data.bin", &data).unwrap();
+
+    for command in [
+        "cat /data.bin > /copy",
+        "cat < /data.bin > /copy",
+        "cat /data.bin | cat > /copy",
+    ] {
+        run(command, &mut fs, &mut session).unwrap();
+        assert_eq!(fs.read_file("/copy").unwrap(), data, "{}", command);
+    }
+
+    run("cat /data.bin /data.bin >> /copy", &mut fs, &mut session).unwrap();
+    assert_eq!(fs.size("/copy").unwrap(), 3 * data.len());
+}
+
+#[test_case]
+fn files_can_be_read_as_input() {
+    let (mut fs, mut session) = populated();
+
+    assert_eq!(
+        run("grep ok < log.txt", &mut fs, &mut session),
+        Ok(String::from("ok: boot\nok: network\n"))
+    );
+    assert_eq!(
+        run(
+            "cat < /log.txt | grep -v ok > errors.txt",
+            &mut fs,
+            &mut session
+        ),
+        Ok(String::new())
+    );
+    assert_eq!(
+        fs.read_file("/errors.txt").unwrap(),
+        b"ERROR: disk full\nerror: no route\n"
+    );
+
+    // Files named on the command line win over the standard input
+    assert_eq!(
+        run(
+            "grep route log.txt errors.txt < /bar",
+            &mut fs,
+            &mut session
+        ),
+        Ok(String::from(
+            "log.txt:error: no route\nerrors.txt:error: no route\n"
+        ))
+    );
+}
+
+#[test_case]
+fn input_files_are_closed_after_failures() {
+    let (mut fs, mut session) = populated();
+    fs.create_directory("/mnt").unwrap();
+    fs.mount("/mnt", Box::new(RamFs::new())).unwrap();
+    fs.create_file("/mnt/input", b"data").unwrap();
+
+    assert!(run("cat < /mnt/input | nosuchcommand", &mut fs, &mut session).is_err());
+    assert!(run("nosuchcommand < /mnt/input", &mut fs, &mut session).is_err());
+    // The mount would be busy if the input were still open
+    fs.unmount("/mnt").unwrap();
+}
+
+#[test_case]
+fn output_files_are_closed_after_failures() {
+    let (mut fs, mut session) = populated();
+    fs.create_directory("/mnt").unwrap();
+    fs.mount("/mnt", Box::new(RamFs::new())).unwrap();
+
+    // Like in Unix shells the file is created before the command runs
+    assert!(run("nosuchcommand > /mnt/output", &mut fs, &mut session).is_err());
+    assert_eq!(fs.read_file("/mnt/output").unwrap(), b"");
+    assert!(run("cat /missing >> /mnt/output", &mut fs, &mut session).is_err());
+    assert!(run("cat < /missing > /mnt/other", &mut fs, &mut session).is_err());
+    fs.unmount("/mnt").unwrap();
+}
+
+#[test_case]
+fn malformed_pipelines_are_refused() {
+    let (mut fs, mut session) = populated();
+
+    for command in [
+        "ls |",
+        "| grep foo",
+        "ls | | grep foo",
+        "ls > out.txt | grep foo",
+        "ls | grep foo < log.txt",
+        "grep ok < log.txt < bar",
+        "ls > a.txt >> b.txt",
+        "grep ok <",
+        "cat < missing.txt",
+        "grep foo",
+        "cat",
+    ] {
+        assert!(run(command, &mut fs, &mut session).is_err(), "{}", command);
+    }
+    assert!(fs.lookup("/out.txt").is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
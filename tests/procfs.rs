@@ -0,0 +1,140 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::clock;
+use charizard::file_system::{procfs::ProcFs, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+fn mounted() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/proc").unwrap();
+    fs.mount("/proc", Box::new(ProcFs::new())).unwrap();
+    fs
+}
+
+fn read(fs: &FileSystem, path: &str) -> String {
+    String::from_utf8(fs.read_file(path).unwrap()).unwrap()
+}
+
+// Returns the number in the line of `text` starting with `name`, e.g. `HeapUsed:  12 kB`
+fn field(text: &str, name: &str) -> u64 {
+    let line = text
+        .lines()
+        .find(|line| line.split_whitespace().next() == Some(name))
+        .unwrap_or_else(|| panic!("{} is missing", name));
+
+    line.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+fn wait_for_ticks(count: u64) {
+    let target = clock::ticks() + count;
+    while clock::ticks() < target {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[test_case]
+fn lists_the_files() {
+    let fs = mounted();
+    let names: Vec<String> = fs
+        .read_dir("/proc")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+
+    assert_eq!(names, ["interrupts", "meminfo", "uptime", "version"]);
+    assert_eq!(fs.stat("/proc/meminfo").unwrap().mode, 0o444);
+}
+
+#[test_case]
+fn meminfo_follows_the_heap() {
+    let fs = mounted();
+    let before = read(&fs, "/proc/meminfo");
+
+    assert_eq!(field(&before, "HeapTotal:"), 100);
+    assert!(field(&before, "MemTotal:") > 0);
+    // The heap pages were mapped with frames from the frame allocator
+    assert!(field(&before, "FramesUsed:") >= 25);
+
+    let block: Vec<u8> = Vec::with_capacity(8 * 1024);
+    let during = read(&fs, "/proc/meminfo");
+    assert!(field(&during, "HeapUsed:") >= field(&before, "HeapUsed:") + 8);
+    // Keeps the allocation from being optimized out
+    core::hint::black_box(block);
+}
+
+#[test_case]
+fn interrupts_are_counted() {
+    let fs = mounted();
+
+    let before = field(&read(&fs, "/proc/interrupts"), "32");
+    wait_for_ticks(3);
+    let after = field(&read(&fs, "/proc/interrupts"), "32");
+
+    assert!(after >= before + 3);
+}
+
+#[test_case]
+fn uptime_advances() {
+    let fs = mounted();
+
+    // Hundredths of a second
+    let parse = |text: String| -> u64 { text.trim().replace('.', "").parse().unwrap() };
+    let before = parse(read(&fs, "/proc/uptime"));
+    wait_for_ticks(clock::TICKS_PER_SECOND / 10);
+    let after = parse(read(&fs, "/proc/uptime"));
+
+    assert!(after >= before + 10);
+}
+
+#[test_case]
+fn version_names_the_kernel() {
+    let fs = mounted();
+    assert!(read(&fs, "/proc/version").starts_with("charizard version 0.1.0"));
+}
+
+#[test_case]
+fn refuses_changes() {
+    let mut fs = mounted();
+
+    assert!(fs.create_file("/proc/new", b"data").is_err());
+    assert!(fs.write_at("/proc/version", 0, b"hacked").is_err());
+    assert!(fs.delete_node("/proc/uptime").is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use bootloader::{entry_point, BootInfo};
+use charizard::file_system::{image, vfs::NodeKind, FileSystem};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+fn sample() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_directory("/docs/empty").unwrap();
+    fs.create_file("/docs/readme", b"hello\0world").unwrap();
+    fs.link("/docs/readme", "/readme").unwrap();
+    fs.symlink("docs/readme", "/shortcut").unwrap();
+    fs
+}
+
+#[test_case]
+fn round_trip_keeps_the_tree() {
+    let fs = sample();
+    let encoded = image::encode(fs.root()).unwrap();
+    let restored = FileSystem::with_root(Box::new(image::decode(&encoded).unwrap()));
+
+    assert_eq!(restored.read_file("/shortcut").unwrap(), b"hello\0world");
+    assert_eq!(restored.lookup("/docs/empty"), Ok(NodeKind::Directory));
+    assert_eq!(restored.readlink("/shortcut").unwrap(), "docs/readme");
+
+    let original = fs.stat("/docs/readme").unwrap();
+    let copy = restored.stat("/docs/readme").unwrap();
+    assert_eq!(copy.links, 2);
+    assert_eq!(copy.mode, original.mode);
+    assert_eq!(copy.modified, original.modified);
+    assert_eq!(restored.stat("/readme").unwrap().inode, copy.inode);
+
+    assert_eq!(image::encode(restored.root()).unwrap(), encoded);
+}
+
+#[test_case]
+fn corrupted_images_are_rejected() {
+    let mut encoded = image::encode(sample().root()).unwrap();
+
+    assert!(image::decode(&encoded[..encoded.len() - 1]).is_err());
+
+    let last = encoded.len() - 1;
+    encoded[last] ^= 0xFF;
+    assert!(image::decode(&encoded).is_err());
+}
+
+#[test_case]
+fn blank_disk_has_no_image() {
+    assert_eq!(image::image_size(&[0; 512]), Ok(None));
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,148 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::string::String;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{parse_and_execute_command, Session};
+use charizard::file_system::FileSystem;
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+// `/docs` holding `a.txt` (5 bytes), a link to it, and `sub` with `b.txt` (3 bytes) and an
+// empty directory
+fn populated() -> (FileSystem, Session) {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/docs").unwrap();
+    fs.create_file("/docs/a.txt", b"hello").unwrap();
+    fs.create_directory("/docs/sub").unwrap();
+    fs.create_file("/docs/sub/b.txt", b"hi!").unwrap();
+    fs.symlink("a.txt", "/docs/link").unwrap();
+    fs.create_directory("/docs/sub/empty").unwrap();
+
+    (fs, Session::new())
+}
+
+fn run(command: &str, fs: &mut FileSystem, session: &mut Session) -> Result<String, String> {
+    parse_and_execute_command(command, fs, session)
+}
+
+#[test_case]
+fn tree_draws_the_subtree_with_counts() {
+    let (mut fs, mut session) = populated();
+
+    assert_eq!(
+        run("tree /docs", &mut fs, &mut session),
+        Ok(String::from(
+            "/docs\n\
+             ├── a.txt\n\
+             ├── link -> a.txt\n\
+             └── sub/ (1 directory, 1 file)\n    \
+                 ├── b.txt\n    \
+                 └── empty/ (0 directories, 0 files)\n\
+             \n\
+             2 directories, 3 files"
+        ))
+    );
+    assert!(run("tree docs/a.txt", &mut fs, &mut session).is_err());
+}
+
+#[test_case]
+fn du_adds_up_every_directory() {
+    let (mut fs, mut session) = populated();
+
+    // The link counts with the length of its target
+    assert_eq!(
+        run("du /docs", &mut fs, &mut session),
+        Ok(String::from(
+            "0        /docs/sub/empty\n3        /docs/sub\n13       /docs"
+        ))
+    );
+    assert_eq!(
+        run("du -s docs", &mut fs, &mut session),
+        Ok(String::from("13       docs"))
+    );
+    assert_eq!(
+        run("du docs/a.txt", &mut fs, &mut session),
+        Ok(String::from("5        docs/a.txt"))
+    );
+}
+
+#[test_case]
+fn rm_r_deletes_whole_directories() {
+    let (mut fs, mut session) = populated();
+    fs.create_directory("/kept").unwrap();
+    fs.create_file("/kept/file", b"").unwrap();
+    fs.symlink("/kept", "/docs/sub/kept").unwrap();
+
+    assert!(run("rm /docs", &mut fs, &mut session).is_err());
+    run("rm -r /docs/sub", &mut fs, &mut session).unwrap();
+    assert!(fs.lookup("/docs/sub").is_err());
+    assert!(fs.lookup("/docs/a.txt").is_ok());
+
+    run("rm -r /docs", &mut fs, &mut session).unwrap();
+    assert!(fs.lookup("/docs").is_err());
+    // Links are removed, not followed
+    assert!(fs.lookup("/kept/file").is_ok());
+}
+
+#[test_case]
+fn rm_goes_on_after_a_failure() {
+    let (mut fs, mut session) = populated();
+
+    assert_eq!(
+        run("rm /docs/a.txt /docs/sub /docs/link", &mut fs, &mut session),
+        Err(String::from(
+            "Node '/docs/a.txt' deleted\n\
+             Cannot remove '/docs/sub': Directory 'sub' is not empty\n\
+             Node '/docs/link' deleted"
+        ))
+    );
+    assert!(fs.lookup("/docs/a.txt").is_err());
+    assert!(fs.lookup("/docs/sub/b.txt").is_ok());
+    assert!(fs.lstat("/docs/link").is_err());
+}
+
+#[test_case]
+fn rm_r_refuses_to_remove_the_root() {
+    let (mut fs, mut session) = populated();
+
+    for command in ["rm -r /", "rm -r /docs/..", "rm -rf //"] {
+        assert!(run(command, &mut fs, &mut session).is_err(), "{}", command);
+    }
+    assert_eq!(
+        fs.delete_tree("/"),
+        Err(String::from("Refusing to remove the root directory"))
+    );
+    assert!(fs.lookup("/docs/sub/b.txt").is_ok());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
@@ -0,0 +1,241 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{parse_and_execute_command, Session};
+use charizard::file_system::{procfs::ProcFs, FileSystem};
+use charizard::initrd;
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+// The users come from `initrd/etc/passwd`: root (password `charizard`), alice (`wonderland`) and
+// bob (`builder`), who are both in the group `users`
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+// The initial ramdisk with a few nodes set up by root:
+//
+//   /shared        0777, anyone can add files
+//   /secret.txt    0600
+//   /team.txt      0640, group users
+fn booted() -> (FileSystem, Session) {
+    let mut fs = FileSystem::with_root(Box::new(initrd::load().unwrap()));
+    let mut session = Session::new();
+
+    for command in [
+        "mkdir /shared",
+        "chmod 777 /shared",
+        "touch /secret.txt root only",
+        "chmod 600 /secret.txt",
+        "touch /team.txt for the team",
+        "chown root:users /team.txt",
+        "chmod 640 /team.txt",
+    ] {
+        run(command, &mut fs, &mut session).unwrap();
+    }
+
+    (fs, session)
+}
+
+fn run(command: &str, fs: &mut FileSystem, session: &mut Session) -> Result<String, String> {
+    parse_and_execute_command(command, fs, session)
+}
+
+#[test_case]
+fn login_switches_to_the_home_directory() {
+    let (mut fs, mut session) = booted();
+
+    assert_eq!(
+        run("whoami", &mut fs, &mut session),
+        Ok(String::from("root"))
+    );
+    assert_eq!(
+        run("login alice wonderland", &mut fs, &mut session),
+        Ok(String::from("Logged in as alice"))
+    );
+    assert_eq!(
+        run("whoami", &mut fs, &mut session),
+        Ok(String::from("alice"))
+    );
+    assert_eq!(session.cwd(), "/home/alice");
+
+    let home = fs.stat("/home/alice").unwrap();
+    assert_eq!((home.uid, home.gid, home.mode), (1000, 1000, 0o700));
+    assert_eq!(fs.stat("/home").unwrap().uid, 0);
+}
+
+#[test_case]
+fn wrong_passwords_are_refused() {
+    let (mut fs, mut session) = booted();
+
+    let incorrect = Err(String::from("Login incorrect"));
+    assert_eq!(run("login alice builder", &mut fs, &mut session), incorrect);
+    assert_eq!(run("login nobody", &mut fs, &mut session), incorrect);
+
+    // Only root switches users without their password
+    run("su bob", &mut fs, &mut session).unwrap();
+    assert!(run("su alice", &mut fs, &mut session).is_err());
+    assert!(run("su", &mut fs, &mut session).is_err());
+    run("su root charizard", &mut fs, &mut session).unwrap();
+    assert_eq!(
+        run("whoami", &mut fs, &mut session),
+        Ok(String::from("root"))
+    );
+}
+
+#[test_case]
+fn permission_bits_are_enforced() {
+    let (mut fs, mut session) = booted();
+    run("su alice", &mut fs, &mut session).unwrap();
+
+    assert!(fs.read_file("/secret.txt").is_err());
+    assert!(fs.write_at("/etc/hostname", 0, b"hacked").is_err());
+    assert!(fs.create_file("/etc/new", b"").is_err());
+    assert!(fs.delete_node("/etc/passwd").is_err());
+    assert_eq!(fs.read_file("/team.txt").unwrap(), b"for the team");
+
+    // New nodes belong to their creator
+    fs.create_file("/shared/alice.txt", b"from alice").unwrap();
+    let created = fs.stat("/shared/alice.txt").unwrap();
+    assert_eq!(
+        (created.uid, created.gid, created.mode),
+        (1000, 1000, 0o644)
+    );
+
+    run("login bob builder", &mut fs, &mut session).unwrap();
+    assert_eq!(fs.read_file("/shared/alice.txt").unwrap(), b"from alice");
+    assert!(fs.append("/shared/alice.txt", b" and bob").is_err());
+    assert!(run("chmod 666 /shared/alice.txt", &mut fs, &mut session).is_err());
+    // The directory is writable by everyone, so is renaming and removing in it
+    fs.rename_node("/shared/alice.txt", "taken.txt").unwrap();
+}
+
+#[test_case]
+fn touch_reports_why_it_failed() {
+    let (mut fs, mut session) = booted();
+    run("su alice", &mut fs, &mut session).unwrap();
+
+    assert_eq!(
+        run("touch /etc/new", &mut fs, &mut session),
+        Err(String::from("'/etc': Permission denied"))
+    );
+    assert!(run("touch /missing/new", &mut fs, &mut session).is_err());
+    assert!(fs.lookup("/etc/new").is_err());
+
+    // Touching an existing file keeps its content
+    run("touch /shared/notes.txt first", &mut fs, &mut session).unwrap();
+    run("touch /shared/notes.txt second", &mut fs, &mut session).unwrap();
+    assert_eq!(fs.read_file("/shared/notes.txt").unwrap(), b"first");
+}
+
+#[test_case]
+fn home_directories_are_private() {
+    let (mut fs, mut session) = booted();
+    run("login alice wonderland", &mut fs, &mut session).unwrap();
+    run("touch diary.txt dear diary", &mut fs, &mut session).unwrap();
+    assert_eq!(
+        fs.read_file("/home/alice/diary.txt").unwrap(),
+        b"dear diary"
+    );
+
+    run("login bob builder", &mut fs, &mut session).unwrap();
+    assert!(fs.read_dir("/home/alice").is_err());
+    assert!(fs.read_file("/home/alice/diary.txt").is_err());
+    assert!(run("cd /home/alice", &mut fs, &mut session).is_err());
+}
+
+#[test_case]
+fn unreadable_directories_are_reported() {
+    let (mut fs, mut session) = booted();
+    for command in [
+        "mkdir /shared/open",
+        "touch /shared/open/y hi",
+        "mkdir /shared/closed",
+        "touch /shared/closed/x",
+        "chmod 700 /shared/closed",
+        "login alice wonderland",
+    ] {
+        run(command, &mut fs, &mut session).unwrap();
+    }
+
+    // What can be read is still listed, the error comes last
+    assert_eq!(
+        run("find /shared", &mut fs, &mut session),
+        Err(String::from(
+            "/shared\n/shared/closed\n/shared/open\n/shared/open/y\n\
+             '/shared/closed': Permission denied"
+        ))
+    );
+    assert_eq!(
+        run("du /shared", &mut fs, &mut session),
+        Err(String::from(
+            "0        /shared/closed\n2        /shared/open\n2        /shared\n\
+             '/shared/closed': Permission denied"
+        ))
+    );
+    assert!(run("tree /shared", &mut fs, &mut session)
+        .unwrap_err()
+        .ends_with("2 directories, 1 file\n'/shared/closed': Permission denied"));
+}
+
+#[test_case]
+fn owners_change_modes_and_root_changes_owners() {
+    let (mut fs, mut session) = booted();
+    run("su alice", &mut fs, &mut session).unwrap();
+    run("touch /shared/notes.txt", &mut fs, &mut session).unwrap();
+
+    run("chmod 600 /shared/notes.txt", &mut fs, &mut session).unwrap();
+    assert_eq!(fs.stat("/shared/notes.txt").unwrap().mode, 0o600);
+    run("chown :users /shared/notes.txt", &mut fs, &mut session).unwrap();
+    assert_eq!(fs.stat("/shared/notes.txt").unwrap().gid, 100);
+
+    assert!(run("chown bob /shared/notes.txt", &mut fs, &mut session).is_err());
+    assert!(run("chown :root /shared/notes.txt", &mut fs, &mut session).is_err());
+    assert!(run("chmod 9 /shared/notes.txt", &mut fs, &mut session).is_err());
+
+    run("su root charizard", &mut fs, &mut session).unwrap();
+    run("chown bob:bob /shared/notes.txt", &mut fs, &mut session).unwrap();
+    let metadata = fs.stat("/shared/notes.txt").unwrap();
+    assert_eq!((metadata.uid, metadata.gid), (1001, 1001));
+}
+
+#[test_case]
+fn administration_needs_root() {
+    let (mut fs, mut session) = booted();
+    fs.create_directory("/proc").unwrap();
+    run("su alice", &mut fs, &mut session).unwrap();
+
+    assert!(fs.mount("/proc", Box::new(ProcFs::new())).is_err());
+    assert!(run("sync", &mut fs, &mut session).is_err());
+    assert!(run("mkfs cfs hdb", &mut fs, &mut session).is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}
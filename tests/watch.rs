@@ -0,0 +1,186 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(charizard::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bootloader::{entry_point, BootInfo};
+use charizard::command::{self, parse_and_execute_command, Session};
+use charizard::file_system::{
+    watch::{Event, EventKind, MAX_QUEUED_EVENTS},
+    Conflict, FileSystem,
+};
+use core::panic::PanicInfo;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use charizard::{
+        allocator,
+        memory::{self, BootInfoFrameAllocator},
+    };
+    use x86_64::VirtAddr;
+
+    charizard::init();
+    let phys_mem_offset: VirtAddr = VirtAddr::new(boot_info.physical_memory_offset);
+
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+
+    loop {}
+}
+
+// A tree with `/out/old.txt` and an empty `/other`
+fn populated() -> FileSystem {
+    let mut fs = FileSystem::new();
+    fs.create_directory("/out").unwrap();
+    fs.create_file("/out/old.txt", b"old").unwrap();
+    fs.create_directory("/other").unwrap();
+    fs
+}
+
+fn event(kind: EventKind, path: &str) -> Event {
+    Event::new(kind, path)
+}
+
+#[test_case]
+fn sees_the_directory_and_its_children() {
+    let mut fs = populated();
+    let id = fs.watch("/out", false).unwrap();
+
+    fs.create_file("/out/result.txt", b"").unwrap();
+    fs.append("/out/result.txt", b"passed").unwrap();
+    fs.create_directory("/out/logs").unwrap();
+    fs.create_file("/out/logs/deep.log", b"not seen").unwrap();
+    fs.create_file("/other/file", b"not seen").unwrap();
+    fs.delete_node("/out/old.txt").unwrap();
+
+    assert_eq!(
+        fs.read_events(id).unwrap(),
+        [
+            event(EventKind::Create, "/out/result.txt"),
+            event(EventKind::Modify, "/out/result.txt"),
+            event(EventKind::Create, "/out/logs"),
+            event(EventKind::Delete, "/out/old.txt"),
+        ]
+    );
+    // Reading empties the queue
+    assert_eq!(fs.read_events(id).unwrap(), []);
+}
+
+#[test_case]
+fn recursive_watches_see_the_subtree() {
+    let mut fs = populated();
+    fs.create_directory("/out/logs").unwrap();
+    let id = fs.watch("/out", true).unwrap();
+
+    fs.create_file("/out/logs/deep.log", b"").unwrap();
+    fs.truncate("/out/old.txt", 1).unwrap();
+    fs.create_file("/other/file", b"not seen").unwrap();
+    fs.delete_tree("/out/logs").unwrap();
+
+    assert_eq!(
+        fs.read_events(id).unwrap(),
+        [
+            event(EventKind::Create, "/out/logs/deep.log"),
+            event(EventKind::Modify, "/out/old.txt"),
+            event(EventKind::Delete, "/out/logs/deep.log"),
+            event(EventKind::Delete, "/out/logs"),
+        ]
+    );
+}
+
+#[test_case]
+fn renames_are_seen_from_both_sides() {
+    let mut fs = populated();
+    let out = fs.watch("/out", false).unwrap();
+    let other = fs.watch("/other", false).unwrap();
+
+    fs.move_node("/out/old.txt", "/other/moved.txt", Conflict::Fail)
+        .unwrap();
+
+    let renamed = Event::rename("/out/old.txt", "/other/moved.txt");
+    assert_eq!(
+        fs.read_events(out).unwrap(),
+        core::slice::from_ref(&renamed)
+    );
+    assert_eq!(fs.read_events(other).unwrap(), [renamed]);
+}
+
+#[test_case]
+fn full_queues_report_the_loss() {
+    let mut fs = populated();
+    let id = fs.watch("/out/old.txt", false).unwrap();
+
+    for _ in 0..MAX_QUEUED_EVENTS + 10 {
+        fs.append("/out/old.txt", b".").unwrap();
+    }
+
+    let events = fs.read_events(id).unwrap();
+    assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+    assert_eq!(events[0], event(EventKind::Modify, "/out/old.txt"));
+    assert_eq!(events[MAX_QUEUED_EVENTS - 1].kind, EventKind::Overflow);
+
+    // Events are queued again once the queue was read
+    fs.append("/out/old.txt", b".").unwrap();
+    assert_eq!(fs.read_events(id).unwrap().len(), 1);
+}
+
+#[test_case]
+fn removed_watches_are_gone() {
+    let mut fs = populated();
+    let id = fs.watch("/out", false).unwrap();
+
+    assert!(fs.watch("/missing", false).is_err());
+    assert_eq!(fs.watches().count(), 1);
+
+    fs.unwatch(id).unwrap();
+    assert!(fs.read_events(id).is_err());
+    assert!(fs.unwatch(id).is_err());
+    assert_eq!(fs.watches().count(), 0);
+}
+
+#[test_case]
+fn the_shell_prints_events_after_each_command() {
+    let mut fs = populated();
+    let mut session = Session::new();
+    let run = |line: &str, fs: &mut FileSystem, session: &mut Session| {
+        let output = parse_and_execute_command(line, fs, session);
+        (output, command::watch_events(fs, session))
+    };
+
+    let (output, _) = run("watch -r /out", &mut fs, &mut session);
+    assert_eq!(output, Ok(String::from("Watching '/out' as 1")));
+
+    let (_, events) = run("echo done > /out/status", &mut fs, &mut session);
+    assert_eq!(
+        events,
+        [
+            "[watch 1] created /out/status",
+            "[watch 1] modified /out/status",
+        ]
+    );
+
+    let (output, _) = run("watch", &mut fs, &mut session);
+    assert_eq!(output, Ok(format!("{:>3}  /out and below", 1)));
+
+    let (output, _) = run("unwatch 1", &mut fs, &mut session);
+    assert!(output.is_ok());
+    let (_, events) = run("rm /out/status", &mut fs, &mut session);
+    assert_eq!(events, Vec::<String>::new());
+    assert!(run("unwatch 1", &mut fs, &mut session).0.is_err());
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    charizard::test_panic_handler(info);
+}